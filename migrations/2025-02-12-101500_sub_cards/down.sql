@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "column_card"
+    DROP COLUMN IF EXISTS "completed_at",
+    DROP COLUMN IF EXISTS "parent_id";
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+ALTER TABLE "column_card"
+    ADD COLUMN "parent_id" UUID DEFAULT NULL REFERENCES "column_card" ("id") ON DELETE SET NULL,
+    ADD COLUMN "completed_at" TIMESTAMP DEFAULT NULL;
+
+CREATE INDEX ON "column_card" ("parent_id");
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS card_recurrences (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    card_id UUID NOT NULL UNIQUE REFERENCES column_card(id) ON DELETE CASCADE,
+    column_id UUID NOT NULL REFERENCES board_column(id) ON DELETE CASCADE,
+    rule VARCHAR(255) NOT NULL,
+    next_run_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX ON card_recurrences (next_run_at);
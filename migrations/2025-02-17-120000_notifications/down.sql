@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS notifications;
+
+ALTER TABLE column_card DROP COLUMN IF EXISTS assignee_id;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS mentions (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    card_id UUID REFERENCES column_card(id) ON DELETE CASCADE,
+    message_id UUID REFERENCES chat_messages(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((card_id IS NULL) <> (message_id IS NULL))
+);
+
+CREATE INDEX ON mentions (user_id);
+CREATE INDEX ON mentions (card_id);
+CREATE INDEX ON mentions (message_id);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-- History of the columns each card went through, `from_column_id` is NULL when
+-- the card was created and `to_column_id` is NULL when it was deleted.
+-- Columns are not referenced so the history outlives the cards and columns it describes.
+CREATE TABLE IF NOT EXISTS card_movements (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    card_id UUID NOT NULL,
+    from_column_id UUID DEFAULT NULL,
+    to_column_id UUID DEFAULT NULL,
+    moved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    moved_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX ON card_movements (board_id, moved_at);
+CREATE INDEX ON card_movements (card_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE column_card DROP COLUMN IF EXISTS lane_id;
+DROP TABLE IF EXISTS board_lane;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS board_lane (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    name VARCHAR(255),
+    position INT NOT NULL,
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE
+);
+
+CREATE INDEX ON board_lane (board_id);
+
+ALTER TABLE column_card
+    ADD COLUMN lane_id UUID DEFAULT NULL REFERENCES board_lane(id) ON DELETE SET NULL;
+
+CREATE INDEX ON column_card (lane_id);
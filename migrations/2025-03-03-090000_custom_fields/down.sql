@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS card_field_values;
+DROP TABLE IF EXISTS board_custom_fields;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS board_custom_fields (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    kind VARCHAR(32) NOT NULL,
+    options JSONB NOT NULL DEFAULT '[]',
+    position INT NOT NULL DEFAULT 0,
+    UNIQUE (board_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS card_field_values (
+    card_id UUID NOT NULL REFERENCES column_card(id) ON DELETE CASCADE,
+    field_id UUID NOT NULL REFERENCES board_custom_fields(id) ON DELETE CASCADE,
+    value JSONB NOT NULL,
+    PRIMARY KEY (card_id, field_id)
+);
+
+CREATE INDEX ON card_field_values (field_id);
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS time_entries (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    card_id UUID NOT NULL REFERENCES column_card(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP DEFAULT NULL,
+    note TEXT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (ended_at IS NULL OR ended_at >= started_at)
+);
+
+-- A user can only have one running timer
+CREATE UNIQUE INDEX time_entries_running_timer ON time_entries (user_id) WHERE ended_at IS NULL;
+CREATE INDEX ON time_entries (card_id);
+CREATE INDEX ON time_entries (board_id, started_at);
+CREATE INDEX ON time_entries (user_id, started_at);
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS sprints (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    goal TEXT DEFAULT NULL,
+    starts_on DATE NOT NULL,
+    ends_on DATE NOT NULL,
+    state VARCHAR(16) NOT NULL DEFAULT 'planned',
+    -- Unfinished cards moved out of the sprint when it was completed
+    carried_over INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP DEFAULT NULL,
+    CHECK (ends_on >= starts_on),
+    CHECK (state IN ('planned', 'active', 'completed'))
+);
+
+-- A board runs at most one sprint at a time
+CREATE UNIQUE INDEX sprints_active_per_board ON sprints (board_id) WHERE state = 'active';
+CREATE INDEX ON sprints (board_id, starts_on);
+
+ALTER TABLE column_card
+    ADD COLUMN sprint_id UUID DEFAULT NULL REFERENCES sprints(id) ON DELETE SET NULL;
+
+CREATE INDEX ON column_card (sprint_id);
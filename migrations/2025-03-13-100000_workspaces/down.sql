@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE boards DROP COLUMN IF EXISTS workspace_id;
+DROP TABLE IF EXISTS workspace_members;
+DROP TABLE IF EXISTS workspaces;
@@ -0,0 +1,40 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    name VARCHAR(255) NOT NULL,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Every user gets exactly one personal workspace, it can't be shared or deleted
+    personal BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX workspaces_personal_per_user ON workspaces (owner_id) WHERE personal;
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL DEFAULT 'member',
+    joined_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, user_id),
+    CHECK (role IN ('owner', 'admin', 'member'))
+);
+
+CREATE INDEX ON workspace_members (user_id);
+
+-- Existing boards move into a personal workspace of their creator
+INSERT INTO workspaces (name, owner_id, personal)
+SELECT DISTINCT 'Personal', creator_id, TRUE FROM boards;
+
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT id, owner_id, 'owner' FROM workspaces;
+
+ALTER TABLE boards ADD COLUMN workspace_id UUID DEFAULT NULL REFERENCES workspaces(id);
+
+UPDATE boards SET workspace_id = workspaces.id
+FROM workspaces
+WHERE workspaces.owner_id = boards.creator_id AND workspaces.personal;
+
+ALTER TABLE boards ALTER COLUMN workspace_id SET NOT NULL;
+
+CREATE INDEX ON boards (workspace_id);
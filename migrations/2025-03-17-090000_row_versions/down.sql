@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE board_column DROP COLUMN IF EXISTS version;
+ALTER TABLE column_card DROP COLUMN IF EXISTS version;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- Bumped on every edit of the row itself, position shifts caused by
+-- neighbouring cards or columns leave it untouched
+ALTER TABLE column_card ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE board_column ADD COLUMN version INT NOT NULL DEFAULT 1;
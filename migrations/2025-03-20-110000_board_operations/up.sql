@@ -0,0 +1,23 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS board_operations (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    kind VARCHAR(32) NOT NULL,
+    -- The card or column the operation was applied to
+    target_id UUID NOT NULL,
+    -- What undoing the operation restores, NULL for creations
+    snapshot JSONB DEFAULT NULL,
+    -- Version of the target right after the operation, NULL for deletions
+    result_version INT DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    undone_at TIMESTAMP DEFAULT NULL,
+    CHECK (kind IN (
+        'card_created', 'card_updated', 'card_moved', 'card_deleted',
+        'column_created', 'column_updated', 'column_deleted'
+    ))
+);
+
+CREATE INDEX ON board_operations (board_id, created_at);
+CREATE INDEX ON board_operations (created_at);
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS card_revisions (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    card_id UUID NOT NULL REFERENCES column_card(id) ON DELETE CASCADE,
+    -- Counts up from 1 per card
+    revision INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    description TEXT DEFAULT NULL,
+    author_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (card_id, revision)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS rule_executions;
+DROP TABLE IF EXISTS board_rules;
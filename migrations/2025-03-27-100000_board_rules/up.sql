@@ -0,0 +1,35 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS board_rules (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    -- Definitions, see `models::automation`
+    trigger JSONB NOT NULL,
+    conditions JSONB NOT NULL DEFAULT '[]',
+    actions JSONB NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX ON board_rules (board_id);
+
+CREATE TABLE IF NOT EXISTS rule_executions (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    rule_id UUID NOT NULL REFERENCES board_rules(id) ON DELETE CASCADE,
+    board_id UUID NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    -- Kept after the card is deleted, so no foreign key
+    card_id UUID NOT NULL,
+    event VARCHAR(32) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    -- What fired the rule, e.g. the date that passed
+    context JSONB DEFAULT NULL,
+    -- The actions that were applied, or why the rule failed
+    detail JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (status IN ('applied', 'failed'))
+);
+
+CREATE INDEX ON rule_executions (board_id, created_at);
+CREATE INDEX ON rule_executions (rule_id, card_id);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    -- SHA-256 of the token, the token itself is only shown when it is created
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- The first characters of the token, to tell tokens apart
+    prefix VARCHAR(16) NOT NULL,
+    scopes JSONB NOT NULL DEFAULT '[]',
+    expires_at TIMESTAMP DEFAULT NULL,
+    last_used_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX ON api_tokens (user_id);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS calendar_feeds (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- The feed covers every board of the user when NULL
+    board_id UUID REFERENCES boards(id) ON DELETE CASCADE,
+    -- Part of the feed URL, which calendar apps fetch without logging in
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX ON calendar_feeds (user_id) WHERE board_id IS NULL;
+CREATE UNIQUE INDEX ON calendar_feeds (user_id, board_id) WHERE board_id IS NOT NULL;
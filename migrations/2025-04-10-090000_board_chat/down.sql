@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM conversations WHERE board_id IS NOT NULL;
+
+ALTER TABLE conversations
+    DROP CONSTRAINT conversations_members_or_board,
+    DROP COLUMN board_id,
+    ALTER COLUMN member_one SET NOT NULL,
+    ALTER COLUMN member_two SET NOT NULL;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- A conversation is either between two users or the chat of a board, whose
+-- members are the members of the board
+ALTER TABLE conversations
+    ALTER COLUMN member_one DROP NOT NULL,
+    ALTER COLUMN member_two DROP NOT NULL,
+    ADD COLUMN board_id UUID UNIQUE REFERENCES boards(id) ON DELETE CASCADE,
+    ADD CONSTRAINT conversations_members_or_board CHECK (
+        (board_id IS NULL AND member_one IS NOT NULL AND member_two IS NOT NULL)
+        OR (board_id IS NOT NULL AND member_one IS NULL AND member_two IS NULL)
+    );
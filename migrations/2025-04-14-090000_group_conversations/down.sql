@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS conversation_members;
+
+DELETE FROM conversations WHERE name IS NOT NULL;
+
+ALTER TABLE conversations
+    DROP CONSTRAINT conversations_direct_board_or_group,
+    DROP COLUMN name,
+    ADD CONSTRAINT conversations_members_or_board CHECK (
+        (board_id IS NULL AND member_one IS NOT NULL AND member_two IS NOT NULL)
+        OR (board_id IS NOT NULL AND member_one IS NULL AND member_two IS NULL)
+    );
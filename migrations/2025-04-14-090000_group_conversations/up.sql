@@ -0,0 +1,30 @@
+-- Your SQL goes here
+
+-- A conversation is now between two users, the chat of a board or a named group.
+-- Members of direct conversations and groups are listed in `conversation_members`,
+-- the chat of a board still follows the members of the board
+ALTER TABLE conversations
+    ADD COLUMN name VARCHAR(255) DEFAULT NULL,
+    DROP CONSTRAINT conversations_members_or_board,
+    ADD CONSTRAINT conversations_direct_board_or_group CHECK (
+        (member_one IS NOT NULL AND member_two IS NOT NULL AND board_id IS NULL AND name IS NULL)
+        OR (member_one IS NULL AND member_two IS NULL AND board_id IS NOT NULL AND name IS NULL)
+        OR (member_one IS NULL AND member_two IS NULL AND board_id IS NULL AND name IS NOT NULL)
+    );
+
+CREATE TABLE IF NOT EXISTS conversation_members (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL DEFAULT 'member',
+    joined_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (conversation_id, user_id),
+    CHECK (role IN ('admin', 'member'))
+);
+
+CREATE INDEX ON conversation_members (user_id);
+
+-- Existing direct conversations keep both their members
+INSERT INTO conversation_members (conversation_id, user_id)
+SELECT id, member_one FROM conversations WHERE member_one IS NOT NULL
+UNION
+SELECT id, member_two FROM conversations WHERE member_two IS NOT NULL;
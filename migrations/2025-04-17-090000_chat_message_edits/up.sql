@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Previous versions of edited chat messages, `edited_at` is when the content was replaced.
+-- The history is dropped along with the content when the message is deleted
+CREATE TABLE IF NOT EXISTS chat_message_edits (
+    id UUID PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    message_id UUID NOT NULL REFERENCES chat_messages(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    edited_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX ON chat_message_edits (message_id, edited_at);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Pages of history are read in `(created_at, id)` order within a conversation
+CREATE INDEX chat_messages_history ON chat_messages (conversation_id, created_at, id);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+-- How far each user has read each conversation, messages sent after
+-- `(message_created_at, message_id)` by someone else are unread.
+-- Kept apart from `conversation_members` since board chats have no members of their own
+CREATE TABLE IF NOT EXISTS conversation_reads (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES chat_messages(id) ON DELETE CASCADE,
+    message_created_at TIMESTAMP NOT NULL,
+    read_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (conversation_id, user_id)
+);
+
+CREATE INDEX ON conversation_reads (user_id);
+
+-- Messages used to be dated by the sender's clock, one dated in the future
+-- would keep its conversation unread until then
+UPDATE chat_messages
+SET created_at = NOW() AT TIME ZONE 'UTC'
+WHERE created_at > NOW() AT TIME ZONE 'UTC';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE board_users_relation DROP CONSTRAINT IF EXISTS board_users_relation_has_source;
+ALTER TABLE board_users_relation DROP COLUMN IF EXISTS via_workspace;
+ALTER TABLE board_users_relation DROP COLUMN IF EXISTS direct;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+-- Access to a board comes from a direct invitation, from the workspace of the board or both
+ALTER TABLE board_users_relation
+    ADD COLUMN direct BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN via_workspace BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Members of the board's workspace also get access through it. Whether they were
+-- invited directly as well can't be told anymore, so existing access stays direct
+UPDATE board_users_relation
+SET via_workspace = TRUE
+FROM boards, workspace_members
+WHERE boards.id = board_users_relation.board_id
+    AND workspace_members.workspace_id = boards.workspace_id
+    AND workspace_members.user_id = board_users_relation.user_id;
+
+ALTER TABLE board_users_relation
+    ADD CONSTRAINT board_users_relation_has_source CHECK (direct OR via_workspace);
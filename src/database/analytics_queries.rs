@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Days, NaiveDate, NaiveDateTime, Utc};
+use diesel::{
+    ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::models::analytics::{
+    BoardAnalytics, CardMovement, CardTimeline, ColumnStay, ColumnTimes, ColumnTotal,
+    DailyThroughput, FlowPoint, StaleCard,
+};
+use crate::schema::{board_column, card_movements, column_card};
+
+pub struct AnalyticsQueries;
+
+fn hours(from: NaiveDateTime, to: NaiveDateTime) -> f64 {
+    (to - from).num_seconds() as f64 / 3600.0
+}
+
+fn column_names(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+) -> QueryResult<HashMap<Uuid, Option<String>>> {
+    Ok(board_column::table
+        .filter(board_column::board_id.eq(board_id))
+        .select((board_column::id, board_column::name))
+        .load::<(Uuid, Option<String>)>(conn)?
+        .into_iter()
+        .collect())
+}
+
+fn average_hours(durations: Option<&Vec<i64>>) -> Option<f64> {
+    let durations = durations.filter(|durations| !durations.is_empty())?;
+    let total: i64 = durations.iter().sum();
+    Some(total as f64 / durations.len() as f64 / 3600.0)
+}
+
+impl AnalyticsQueries {
+    /// Appends a step to the history of the card
+    ///
+    /// Takes a plain connection so it runs inside the transaction that moves the card
+    pub fn record_movement(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Uuid,
+        from_column_id: Option<Uuid>,
+        to_column_id: Option<Uuid>,
+        moved_by: Option<Uuid>,
+    ) -> QueryResult<()> {
+        diesel::insert_into(card_movements::table)
+            .values(CardMovement {
+                id: Uuid::new_v4(),
+                board_id,
+                card_id,
+                from_column_id,
+                to_column_id,
+                moved_by,
+                moved_at: Utc::now().naive_utc(),
+            })
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Computes the analytics of the board between `from` and `to`, both days included
+    ///
+    /// Everything is derived from the movement history, so cards that have not
+    /// moved since the history started being recorded are not taken into account
+    pub fn board_analytics(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> QueryResult<BoardAnalytics> {
+        let start = from.and_hms_opt(0, 0, 0).unwrap_or_default();
+        let end = (to + Days::new(1)).and_hms_opt(0, 0, 0).unwrap_or_default();
+        let in_range = |time: NaiveDateTime| time >= start && time < end;
+        let days = (to - from).num_days() as usize + 1;
+        let day_of = |time: NaiveDateTime| (time.date() - from).num_days() as usize;
+
+        let columns = board_column::table
+            .filter(board_column::board_id.eq(board_id))
+            .order(board_column::position.asc())
+            .select((board_column::id, board_column::name))
+            .load::<(Uuid, Option<String>)>(conn)?;
+        let movements = card_movements::table
+            .filter(card_movements::board_id.eq(board_id))
+            .filter(card_movements::moved_at.lt(end))
+            .order((card_movements::moved_at.asc(), card_movements::id.asc()))
+            .select(CardMovement::as_select())
+            .load::<CardMovement>(conn)?;
+        let completions = column_card::table
+            .inner_join(board_column::table)
+            .filter(board_column::board_id.eq(board_id))
+            .filter(column_card::completed_at.ge(start))
+            .filter(column_card::completed_at.lt(end))
+            .select(column_card::completed_at)
+            .load::<Option<NaiveDateTime>>(conn)?;
+
+        // Throughput
+        let mut throughput = (0..days)
+            .map(|day| DailyThroughput {
+                date: from + Days::new(day as u64),
+                created: 0,
+                completed: 0,
+            })
+            .collect::<Vec<DailyThroughput>>();
+        for movement in &movements {
+            if movement.from_column_id.is_none() && in_range(movement.moved_at) {
+                throughput[day_of(movement.moved_at)].created += 1;
+            }
+        }
+        for completed_at in completions.into_iter().flatten() {
+            throughput[day_of(completed_at)].completed += 1;
+        }
+
+        // Cycle and lead time, in seconds, per column
+        let mut by_card: HashMap<Uuid, Vec<&CardMovement>> = HashMap::new();
+        for movement in &movements {
+            by_card.entry(movement.card_id).or_default().push(movement);
+        }
+        let mut cycle: HashMap<Uuid, Vec<i64>> = HashMap::new();
+        let mut lead: HashMap<Uuid, Vec<i64>> = HashMap::new();
+        for history in by_card.values() {
+            let created_at = history
+                .iter()
+                .find(|movement| movement.from_column_id.is_none())
+                .map(|movement| movement.moved_at);
+            let mut visited = HashSet::new();
+            for (i, movement) in history.iter().enumerate() {
+                let Some(column_id) = movement.to_column_id else {
+                    continue;
+                };
+                if let Some(next) = history.get(i + 1) {
+                    if in_range(next.moved_at) {
+                        cycle
+                            .entry(column_id)
+                            .or_default()
+                            .push((next.moved_at - movement.moved_at).num_seconds());
+                    }
+                }
+                if !visited.insert(column_id) || movement.from_column_id.is_none() {
+                    continue;
+                }
+                if let Some(created_at) = created_at {
+                    if in_range(movement.moved_at) {
+                        lead.entry(column_id)
+                            .or_default()
+                            .push((movement.moved_at - created_at).num_seconds());
+                    }
+                }
+            }
+        }
+
+        // Cumulative flow, replaying the history up to the end of each day
+        let mut cumulative_flow = Vec::with_capacity(days);
+        let mut positions: HashMap<Uuid, Uuid> = HashMap::new();
+        let mut replayed = movements.iter().peekable();
+        for day in 0..days {
+            let date = from + Days::new(day as u64);
+            let end_of_day = (date + Days::new(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default();
+            while let Some(movement) = replayed.next_if(|movement| movement.moved_at < end_of_day) {
+                match movement.to_column_id {
+                    Some(column_id) => positions.insert(movement.card_id, column_id),
+                    None => positions.remove(&movement.card_id),
+                };
+            }
+            let mut counts = columns
+                .iter()
+                .map(|(column_id, _)| (*column_id, 0))
+                .collect::<HashMap<Uuid, i64>>();
+            for column_id in positions.values() {
+                if let Some(count) = counts.get_mut(column_id) {
+                    *count += 1;
+                }
+            }
+            cumulative_flow.push(FlowPoint {
+                date,
+                columns: counts,
+            });
+        }
+
+        let columns = columns
+            .into_iter()
+            .map(|(column_id, name)| ColumnTimes {
+                column_id,
+                name,
+                average_cycle_hours: average_hours(cycle.get(&column_id)),
+                average_lead_hours: average_hours(lead.get(&column_id)),
+            })
+            .collect();
+
+        Ok(BoardAnalytics {
+            from,
+            to,
+            throughput,
+            columns,
+            cumulative_flow,
+        })
+    }
+
+    /// Returns the columns the card went through and how long it stayed in each of them
+    ///
+    /// Works for deleted cards as long as their history is kept, `None` when the board
+    /// knows nothing about the card
+    pub fn card_timeline(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Uuid,
+        now: NaiveDateTime,
+    ) -> QueryResult<Option<CardTimeline>> {
+        let movements = card_movements::table
+            .filter(card_movements::board_id.eq(board_id))
+            .filter(card_movements::card_id.eq(card_id))
+            .order((card_movements::moved_at.asc(), card_movements::id.asc()))
+            .select(CardMovement::as_select())
+            .load::<CardMovement>(conn)?;
+        if movements.is_empty() {
+            let on_board = column_card::table
+                .inner_join(board_column::table)
+                .filter(column_card::id.eq(card_id))
+                .filter(board_column::board_id.eq(board_id))
+                .count()
+                .get_result::<i64>(conn)?
+                > 0;
+            if !on_board {
+                return Ok(None);
+            }
+        }
+        let names = column_names(conn, board_id)?;
+        let name_of = |column_id: Uuid| names.get(&column_id).cloned().flatten();
+
+        let mut stays = Vec::new();
+        for (i, movement) in movements.iter().enumerate() {
+            let Some(column_id) = movement.to_column_id else {
+                continue;
+            };
+            let left_at = movements.get(i + 1).map(|next| next.moved_at);
+            stays.push(ColumnStay {
+                column_id,
+                column_name: name_of(column_id),
+                entered_at: movement.moved_at,
+                left_at,
+                moved_by: movement.moved_by,
+                hours: hours(movement.moved_at, left_at.unwrap_or(now)),
+            });
+        }
+
+        let mut columns: Vec<ColumnTotal> = Vec::new();
+        for stay in &stays {
+            match columns
+                .iter_mut()
+                .find(|total| total.column_id == stay.column_id)
+            {
+                Some(total) => {
+                    total.visits += 1;
+                    total.hours += stay.hours;
+                }
+                None => columns.push(ColumnTotal {
+                    column_id: stay.column_id,
+                    column_name: stay.column_name.clone(),
+                    visits: 1,
+                    hours: stay.hours,
+                }),
+            }
+        }
+
+        Ok(Some(CardTimeline {
+            card_id,
+            created_at: movements
+                .first()
+                .filter(|movement| movement.from_column_id.is_none())
+                .map(|movement| movement.moved_at),
+            deleted_at: movements
+                .last()
+                .filter(|movement| movement.to_column_id.is_none())
+                .map(|movement| movement.moved_at),
+            stays,
+            columns,
+        }))
+    }
+
+    /// Returns the open cards of the board that entered their column before `threshold`,
+    /// longest idle first
+    ///
+    /// Cards that have not moved since the history started being recorded can't be dated,
+    /// they are listed last without `entered_at`
+    pub fn stale_cards(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        threshold: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> QueryResult<Vec<StaleCard>> {
+        let cards = column_card::table
+            .inner_join(board_column::table)
+            .filter(board_column::board_id.eq(board_id))
+            .filter(column_card::completed_at.is_null())
+            .select((
+                column_card::id,
+                column_card::name,
+                column_card::column_id,
+                board_column::name,
+            ))
+            .load::<(Uuid, String, Uuid, Option<String>)>(conn)?;
+        let entries = card_movements::table
+            .filter(card_movements::board_id.eq(board_id))
+            .filter(card_movements::card_id.eq_any(cards.iter().map(|card| card.0)))
+            .order((card_movements::moved_at.asc(), card_movements::id.asc()))
+            .select((
+                card_movements::card_id,
+                card_movements::to_column_id,
+                card_movements::moved_at,
+            ))
+            .load::<(Uuid, Option<Uuid>, NaiveDateTime)>(conn)?
+            .into_iter()
+            .map(|(card_id, column_id, moved_at)| (card_id, (column_id, moved_at)))
+            .collect::<HashMap<Uuid, (Option<Uuid>, NaiveDateTime)>>();
+
+        let mut stale = cards
+            .into_iter()
+            .filter_map(|(card_id, name, column_id, column_name)| {
+                // The last movement tells when the card entered its current column
+                let entered_at = entries
+                    .get(&card_id)
+                    .filter(|(to_column_id, _)| *to_column_id == Some(column_id))
+                    .map(|(_, moved_at)| *moved_at);
+                if entered_at.is_some_and(|entered_at| entered_at > threshold) {
+                    return None;
+                }
+                Some(StaleCard {
+                    card_id,
+                    name,
+                    column_id,
+                    column_name,
+                    entered_at,
+                    idle_days: entered_at
+                        .map(|entered_at| (now - entered_at).num_seconds() as f64 / 86400.0),
+                })
+            })
+            .collect::<Vec<_>>();
+        stale.sort_by_key(|card| (card.entered_at.is_none(), card.entered_at));
+        Ok(stale)
+    }
+}
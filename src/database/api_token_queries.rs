@@ -0,0 +1,72 @@
+use chrono::{Duration, Utc};
+use diesel::{
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::models::api_token::{hash_token, ApiToken, NewApiToken, TOKEN_PREFIX};
+use crate::schema::api_tokens;
+
+/// `last_used_at` is only written again once it is this old, so busy scripts
+/// don't turn every request into a write
+const LAST_USED_PRECISION: Duration = Duration::minutes(1);
+
+pub struct ApiTokenQueries;
+
+impl ApiTokenQueries {
+    /// Stores a new token for the user, returning it along with the token itself
+    pub fn create(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        token: &NewApiToken,
+    ) -> QueryResult<(ApiToken, String)> {
+        let mut buffer = [0u8; 32];
+        getrandom::getrandom(&mut buffer).expect("random token");
+        let secret = format!("{}{}", TOKEN_PREFIX, hex::encode(buffer));
+
+        let row = diesel::insert_into(api_tokens::table)
+            .values(ApiToken {
+                id: Uuid::new_v4(),
+                user_id,
+                name: token.name.trim().to_string(),
+                token_hash: hash_token(&secret),
+                prefix: secret[..TOKEN_PREFIX.len() + 8].to_string(),
+                scopes: serde_json::to_value(&token.scopes).unwrap_or_default(),
+                expires_at: token.expires_at,
+                last_used_at: None,
+                created_at: Utc::now().naive_utc(),
+            })
+            .returning(ApiToken::as_returning())
+            .get_result::<ApiToken>(conn)?;
+        Ok((row, secret))
+    }
+
+    /// Finds the unexpired token and records that it was used
+    pub fn authenticate(conn: &mut PgConnection, token: &str) -> QueryResult<Option<ApiToken>> {
+        let now = Utc::now().naive_utc();
+        let Some(mut row) = api_tokens::table
+            .filter(api_tokens::token_hash.eq(hash_token(token)))
+            .select(ApiToken::as_select())
+            .first::<ApiToken>(conn)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        if row.is_expired(now) {
+            return Ok(None);
+        }
+
+        if row
+            .last_used_at
+            .is_none_or(|last_used_at| last_used_at + LAST_USED_PRECISION <= now)
+        {
+            diesel::update(api_tokens::table)
+                .filter(api_tokens::id.eq(row.id))
+                .set(api_tokens::last_used_at.eq(now))
+                .execute(conn)?;
+            row.last_used_at = Some(now);
+        }
+        Ok(Some(row))
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::{
+    upsert::excluded, Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl, SelectableHelper,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::database::analytics_queries::AnalyticsQueries;
+use crate::errors::{ApiError, ApiErrorType};
+use crate::models::automation::{
+    Action, BoardRule, DryRunMatch, ExecutionStatus, RuleCard, RuleDefinition, RuleEvent,
+    RuleExecution, Trigger,
+};
+use crate::models::custom_field::{CardFieldValue, CustomField};
+use crate::schema::{
+    board_column, board_custom_fields, board_rules, board_users_relation, card_field_values,
+    column_card, rule_executions,
+};
+
+type CardRow = (
+    Uuid,
+    String,
+    Uuid,
+    Option<Uuid>,
+    Option<Uuid>,
+    Option<chrono::NaiveDateTime>,
+);
+
+pub struct AutomationQueries;
+
+impl AutomationQueries {
+    /// Loads the cards of the board, or only the given one, with their custom field values
+    pub fn load_cards(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Option<Uuid>,
+    ) -> QueryResult<Vec<RuleCard>> {
+        let mut query = column_card::table
+            .inner_join(board_column::table)
+            .filter(board_column::board_id.eq(board_id))
+            .select((
+                column_card::id,
+                column_card::name,
+                column_card::column_id,
+                column_card::lane_id,
+                column_card::assignee_id,
+                column_card::completed_at,
+            ))
+            .into_boxed();
+        if let Some(card_id) = card_id {
+            query = query.filter(column_card::id.eq(card_id));
+        }
+        let rows = query.load::<CardRow>(conn)?;
+
+        let values = card_field_values::table
+            .filter(card_field_values::card_id.eq_any(rows.iter().map(|row| row.0)))
+            .select(CardFieldValue::as_select())
+            .load::<CardFieldValue>(conn)?;
+        let mut fields: HashMap<Uuid, HashMap<Uuid, Value>> = HashMap::new();
+        for value in values {
+            fields
+                .entry(value.card_id)
+                .or_default()
+                .insert(value.field_id, value.value);
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(id, name, column_id, lane_id, assignee_id, completed_at)| RuleCard {
+                    id,
+                    name,
+                    column_id,
+                    lane_id,
+                    assignee_id,
+                    completed_at,
+                    fields: fields.remove(&id).unwrap_or_default(),
+                },
+            )
+            .collect())
+    }
+
+    /// Runs the enabled rules of the board the event triggers on the card
+    ///
+    /// Called inside the transaction of the change that caused the event. Each rule
+    /// runs in its own savepoint, so a rule that fails is logged and rolled back
+    /// without failing that change. What rules do doesn't fire other rules, which
+    /// keeps them from looping
+    pub fn fire(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Uuid,
+        event: RuleEvent,
+    ) -> QueryResult<usize> {
+        let rules = board_rules::table
+            .filter(board_rules::board_id.eq(board_id))
+            .filter(board_rules::enabled.eq(true))
+            .order(board_rules::created_at.asc())
+            .select(BoardRule::as_select())
+            .load::<BoardRule>(conn)?;
+
+        let mut applied = 0;
+        for rule in rules {
+            let Some(definition) = rule.definition() else {
+                continue;
+            };
+            if definition.trigger.fires_on(&event)
+                && Self::run_rule(conn, &rule, &definition, card_id, event, None)?
+            {
+                applied += 1;
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Fires the `date_passed` rules for every card whose date is now in the past
+    ///
+    /// A rule runs once per card and date, changing the date makes it run again
+    pub fn fire_passed_dates(conn: &mut PgConnection) -> QueryResult<usize> {
+        let today = Utc::now().date_naive();
+        let rules = board_rules::table
+            .filter(board_rules::enabled.eq(true))
+            .order(board_rules::created_at.asc())
+            .select(BoardRule::as_select())
+            .load::<BoardRule>(conn)?;
+
+        let mut applied = 0;
+        for rule in rules {
+            let Some(definition) = rule.definition() else {
+                continue;
+            };
+            let Trigger::DatePassed { field_id } = definition.trigger else {
+                continue;
+            };
+            let card_ids = card_field_values::table
+                .filter(card_field_values::field_id.eq(field_id))
+                .select(card_field_values::card_id)
+                .load::<Uuid>(conn)?;
+            for card_id in card_ids {
+                let Some(card) = Self::load_cards(conn, rule.board_id, Some(card_id))?.pop() else {
+                    continue;
+                };
+                if !card.date_passed(field_id, today) {
+                    continue;
+                }
+                let context = json!({ "date": card.fields[&field_id] });
+                let fired = rule_executions::table
+                    .filter(rule_executions::rule_id.eq(rule.id))
+                    .filter(rule_executions::card_id.eq(card_id))
+                    .filter(rule_executions::context.eq(&context))
+                    .count()
+                    .get_result::<i64>(conn)?;
+                if fired > 0 {
+                    continue;
+                }
+                let event = RuleEvent::DatePassed { field_id };
+                if Self::run_rule(conn, &rule, &definition, card_id, event, Some(context))? {
+                    applied += 1;
+                }
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Returns the cards the rule would currently apply to, without changing anything
+    pub fn dry_run(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        definition: &RuleDefinition,
+        card_id: Option<Uuid>,
+    ) -> QueryResult<Vec<DryRunMatch>> {
+        let today = Utc::now().date_naive();
+        Ok(Self::load_cards(conn, board_id, card_id)?
+            .into_iter()
+            .filter(|card| {
+                definition.trigger.could_fire_for(card, today) && definition.matches(card)
+            })
+            .map(|card| DryRunMatch {
+                card_id: card.id,
+                card_name: card.name,
+                actions: definition.actions.clone(),
+            })
+            .collect())
+    }
+
+    /// Checks the conditions and applies the actions, logging the outcome
+    ///
+    /// Returns whether the actions were applied
+    fn run_rule(
+        conn: &mut PgConnection,
+        rule: &BoardRule,
+        definition: &RuleDefinition,
+        card_id: Uuid,
+        event: RuleEvent,
+        context: Option<Value>,
+    ) -> QueryResult<bool> {
+        let Some(card) = Self::load_cards(conn, rule.board_id, Some(card_id))?.pop() else {
+            return Ok(false);
+        };
+        if !definition.matches(&card) {
+            return Ok(false);
+        }
+
+        let outcome = conn.transaction::<_, ApiError, _>(|conn| {
+            Self::apply(conn, rule.board_id, &card, &definition.actions)
+        });
+        let (status, detail) = match &outcome {
+            Ok(()) => (
+                ExecutionStatus::Applied,
+                json!({ "actions": definition.actions }),
+            ),
+            Err(error) => (
+                ExecutionStatus::Failed,
+                json!({ "actions": definition.actions, "error": error }),
+            ),
+        };
+        diesel::insert_into(rule_executions::table)
+            .values(RuleExecution {
+                id: Uuid::new_v4(),
+                rule_id: rule.id,
+                board_id: rule.board_id,
+                card_id,
+                event: event.as_str().to_string(),
+                status: status.as_str().to_string(),
+                context,
+                detail,
+                created_at: Utc::now().naive_utc(),
+            })
+            .execute(conn)?;
+        Ok(outcome.is_ok())
+    }
+
+    fn apply(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card: &RuleCard,
+        actions: &[Action],
+    ) -> Result<(), ApiError> {
+        for action in actions {
+            match action {
+                Action::SetCompleted { completed } => {
+                    let completed_at = match completed {
+                        true => card.completed_at.or(Some(Utc::now().naive_utc())),
+                        false => None,
+                    };
+                    diesel::update(column_card::table)
+                        .filter(column_card::id.eq(card.id))
+                        .set(column_card::completed_at.eq(completed_at))
+                        .execute(conn)?;
+                }
+                Action::Assign { user_id } => {
+                    let member = board_users_relation::table
+                        .filter(board_users_relation::board_id.eq(board_id))
+                        .filter(board_users_relation::user_id.eq(user_id))
+                        .count()
+                        .get_result::<i64>(conn)?;
+                    if member == 0 {
+                        return Err(ApiError::from_type(ApiErrorType::InvalidUserId));
+                    }
+                    diesel::update(column_card::table)
+                        .filter(column_card::id.eq(card.id))
+                        .set(column_card::assignee_id.eq(user_id))
+                        .execute(conn)?;
+                }
+                Action::Unassign => {
+                    diesel::update(column_card::table)
+                        .filter(column_card::id.eq(card.id))
+                        .set(column_card::assignee_id.eq(None::<Uuid>))
+                        .execute(conn)?;
+                }
+                Action::SetField { field_id, value } => {
+                    let field = board_custom_fields::table
+                        .filter(board_custom_fields::id.eq(field_id))
+                        .filter(board_custom_fields::board_id.eq(board_id))
+                        .select(CustomField::as_select())
+                        .first::<CustomField>(conn)
+                        .optional()?
+                        .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidCustomField))?;
+                    if value.is_null() {
+                        diesel::delete(card_field_values::table)
+                            .filter(card_field_values::card_id.eq(card.id))
+                            .filter(card_field_values::field_id.eq(field_id))
+                            .execute(conn)?;
+                    } else {
+                        field.kind()?.validate(value, &field.options())?;
+                        diesel::insert_into(card_field_values::table)
+                            .values(CardFieldValue {
+                                card_id: card.id,
+                                field_id: *field_id,
+                                value: value.clone(),
+                            })
+                            .on_conflict((card_field_values::card_id, card_field_values::field_id))
+                            .do_update()
+                            .set(card_field_values::value.eq(excluded(card_field_values::value)))
+                            .execute(conn)?;
+                    }
+                }
+                Action::MoveToColumn { column_id } => {
+                    let column = board_column::table
+                        .filter(board_column::id.eq(column_id))
+                        .filter(board_column::board_id.eq(board_id))
+                        .select(board_column::id)
+                        .first::<Uuid>(conn)
+                        .optional()?;
+                    if column.is_none() {
+                        return Err(ApiError::from_type(ApiErrorType::NotFound));
+                    }
+                    // Moves made by rules don't fire rules again
+                    Self::move_to_column_end(conn, board_id, card.id, *column_id, None)?;
+                }
+            }
+        }
+
+        diesel::update(column_card::table)
+            .filter(column_card::id.eq(card.id))
+            .set(column_card::version.eq(column_card::version + 1))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Moves the card to the end of another column of the board and closes the gap it leaves,
+    /// without firing the rules that react to it
+    ///
+    /// Returns whether the card changed column
+    pub fn move_to_column_end(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Uuid,
+        to_column_id: Uuid,
+        moved_by: Option<Uuid>,
+    ) -> QueryResult<bool> {
+        let (from_column_id, pos) = column_card::table
+            .filter(column_card::id.eq(card_id))
+            .select((column_card::column_id, column_card::position))
+            .first::<(Uuid, i32)>(conn)?;
+        if from_column_id == to_column_id {
+            return Ok(false);
+        }
+
+        diesel::update(column_card::table)
+            .filter(column_card::column_id.eq(from_column_id))
+            .filter(column_card::position.gt(pos))
+            .set(column_card::position.eq(column_card::position - 1))
+            .execute(conn)?;
+        let to_pos = column_card::table
+            .filter(column_card::column_id.eq(to_column_id))
+            .count()
+            .get_result::<i64>(conn)?;
+        diesel::update(column_card::table)
+            .filter(column_card::id.eq(card_id))
+            .set((
+                column_card::column_id.eq(to_column_id),
+                column_card::position.eq(to_pos as i32),
+                column_card::version.eq(column_card::version + 1),
+            ))
+            .execute(conn)?;
+
+        AnalyticsQueries::record_movement(
+            conn,
+            board_id,
+            card_id,
+            Some(from_column_id),
+            Some(to_column_id),
+            moved_by,
+        )?;
+        Ok(true)
+    }
+}
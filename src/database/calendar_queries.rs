@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::{
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::calendar::{CalendarEntry, CalendarFeed};
+use crate::models::custom_field::FieldKind;
+use crate::schema::{
+    board_column, board_custom_fields, board_users_relation, boards, calendar_feeds,
+    card_field_values, column_card,
+};
+
+type CardRow = (
+    Uuid,
+    String,
+    Option<String>,
+    Option<NaiveDateTime>,
+    String,
+    Option<String>,
+);
+
+pub struct CalendarQueries;
+
+impl CalendarQueries {
+    /// Replaces the feed of the user, for one board or all of them, with one under a new URL
+    ///
+    /// The previous URL stops working right away
+    pub fn regenerate(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        board_id: Option<Uuid>,
+    ) -> QueryResult<CalendarFeed> {
+        let mut existing = diesel::delete(calendar_feeds::table)
+            .filter(calendar_feeds::user_id.eq(user_id))
+            .into_boxed();
+        existing = match board_id {
+            Some(board_id) => existing.filter(calendar_feeds::board_id.eq(board_id)),
+            None => existing.filter(calendar_feeds::board_id.is_null()),
+        };
+        existing.execute(conn)?;
+
+        let mut buffer = [0u8; 24];
+        getrandom::getrandom(&mut buffer).expect("random token");
+        diesel::insert_into(calendar_feeds::table)
+            .values(CalendarFeed {
+                id: Uuid::new_v4(),
+                user_id,
+                board_id,
+                token: hex::encode(buffer),
+                created_at: Utc::now().naive_utc(),
+            })
+            .returning(CalendarFeed::as_returning())
+            .get_result::<CalendarFeed>(conn)
+    }
+
+    pub fn find(conn: &mut PgConnection, token: &str) -> QueryResult<Option<CalendarFeed>> {
+        calendar_feeds::table
+            .filter(calendar_feeds::token.eq(token))
+            .select(CalendarFeed::as_select())
+            .first::<CalendarFeed>(conn)
+            .optional()
+    }
+
+    /// Returns every date held by the cards of the boards the user belongs to,
+    /// or of the given board only, sorted by date
+    pub fn entries(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        board_id: Option<Uuid>,
+    ) -> QueryResult<Vec<CalendarEntry>> {
+        let mut board_ids = board_users_relation::table
+            .filter(board_users_relation::user_id.eq(user_id))
+            .select(board_users_relation::board_id)
+            .into_boxed();
+        if let Some(board_id) = board_id {
+            board_ids = board_ids.filter(board_users_relation::board_id.eq(board_id));
+        }
+        let board_ids = board_ids.load::<Uuid>(conn)?;
+
+        let cards = column_card::table
+            .inner_join(board_column::table.inner_join(boards::table))
+            .filter(boards::id.eq_any(&board_ids))
+            .select((
+                column_card::id,
+                column_card::name,
+                column_card::description,
+                column_card::completed_at,
+                boards::name,
+                board_column::name,
+            ))
+            .load::<CardRow>(conn)?
+            .into_iter()
+            .map(|card| (card.0, card))
+            .collect::<HashMap<Uuid, CardRow>>();
+
+        let values = card_field_values::table
+            .inner_join(board_custom_fields::table)
+            .filter(board_custom_fields::board_id.eq_any(&board_ids))
+            .filter(board_custom_fields::kind.eq(FieldKind::Date.as_str()))
+            .select((
+                card_field_values::card_id,
+                card_field_values::field_id,
+                board_custom_fields::name,
+                card_field_values::value,
+            ))
+            .load::<(Uuid, Uuid, String, Value)>(conn)?;
+
+        let mut entries = values
+            .into_iter()
+            .filter_map(|(card_id, field_id, field_name, value)| {
+                let date = NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok()?;
+                let card = cards.get(&card_id)?;
+                Some(CalendarEntry {
+                    card_id,
+                    card_name: card.1.clone(),
+                    description: card.2.clone(),
+                    completed_at: card.3,
+                    board_name: card.4.clone(),
+                    column_name: card.5.clone(),
+                    field_id,
+                    field_name,
+                    date,
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| (a.date, &a.card_name).cmp(&(b.date, &b.card_name)));
+        Ok(entries)
+    }
+}
@@ -0,0 +1,542 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::database::mention_queries::MentionQueries;
+use crate::errors::{ApiError, ApiErrorType};
+use crate::models::messages::{
+    ChatMessageDTO, ChatMessageEdit, Conversation, ConversationMember, ConversationRole,
+    ConversationSummary, MessageCursor, MessagePage, PubConversationMember, ReadReceipt,
+};
+use crate::models::notification::Notification;
+use crate::schema::{
+    board_users_relation, chat_message_edits, chat_messages, conversation_members,
+    conversation_reads, conversations, mentions, users,
+};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+pub struct ChatQueries;
+
+impl ChatQueries {
+    /// Returns who can read and write in the conversation
+    ///
+    /// The chat of a board has no members of its own, it follows the members of the board
+    pub fn members(conn: &mut PgConnection, conversation_id: Uuid) -> QueryResult<Vec<Uuid>> {
+        let Some(conversation) = conversations::table
+            .find(conversation_id)
+            .select(Conversation::as_select())
+            .first::<Conversation>(conn)
+            .optional()?
+        else {
+            return Ok(Vec::new());
+        };
+
+        match conversation.board_id {
+            Some(board_id) => board_users_relation::table
+                .filter(board_users_relation::board_id.eq(board_id))
+                .select(board_users_relation::user_id)
+                .load::<Uuid>(conn),
+            None => conversation_members::table
+                .filter(conversation_members::conversation_id.eq(conversation_id))
+                .select(conversation_members::user_id)
+                .load::<Uuid>(conn),
+        }
+    }
+
+    pub fn is_member(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> QueryResult<bool> {
+        Ok(Self::members(conn, conversation_id)?.contains(&user_id))
+    }
+
+    /// Returns the chat of the board, creating it on first use
+    pub fn board_conversation(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+    ) -> QueryResult<Conversation> {
+        diesel::insert_into(conversations::table)
+            .values(conversations::board_id.eq(board_id))
+            .on_conflict(conversations::board_id)
+            .do_nothing()
+            .execute(conn)?;
+        conversations::table
+            .filter(conversations::board_id.eq(board_id))
+            .select(Conversation::as_select())
+            .first::<Conversation>(conn)
+    }
+
+    /// Creates the conversation between two users along with their memberships
+    pub fn create_direct(
+        conn: &mut PgConnection,
+        member_one: Uuid,
+        member_two: Uuid,
+    ) -> QueryResult<Conversation> {
+        let conversation = diesel::insert_into(conversations::table)
+            .values((
+                conversations::member_one.eq(member_one),
+                conversations::member_two.eq(member_two),
+            ))
+            .returning(Conversation::as_returning())
+            .get_result::<Conversation>(conn)?;
+        for user_id in [member_one, member_two] {
+            Self::add_member(conn, conversation.id, user_id, ConversationRole::Member)?;
+        }
+        Ok(conversation)
+    }
+
+    /// Creates a group with its creator as `admin`
+    pub fn create_group(
+        conn: &mut PgConnection,
+        name: &str,
+        creator_id: Uuid,
+    ) -> QueryResult<Conversation> {
+        let conversation = diesel::insert_into(conversations::table)
+            .values(conversations::name.eq(name))
+            .returning(Conversation::as_returning())
+            .get_result::<Conversation>(conn)?;
+        Self::add_member(conn, conversation.id, creator_id, ConversationRole::Admin)?;
+        Ok(conversation)
+    }
+
+    pub fn add_member(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        role: ConversationRole,
+    ) -> QueryResult<ConversationMember> {
+        diesel::insert_into(conversation_members::table)
+            .values(ConversationMember {
+                conversation_id,
+                user_id,
+                role: role.as_str().to_string(),
+                joined_at: Utc::now().naive_utc(),
+            })
+            .get_result::<ConversationMember>(conn)
+    }
+
+    /// Returns the membership of the user, `NotFound` when they are not a member
+    ///
+    /// Only direct conversations and groups have memberships
+    pub fn membership(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> QueryResult<ConversationMember> {
+        conversation_members::table
+            .filter(
+                conversation_members::conversation_id
+                    .eq(conversation_id)
+                    .and(conversation_members::user_id.eq(user_id)),
+            )
+            .select(ConversationMember::as_select())
+            .first::<ConversationMember>(conn)
+    }
+
+    /// Returns the members of the conversation in the order they joined
+    pub fn member_list(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+    ) -> QueryResult<Vec<PubConversationMember>> {
+        let members = conversation_members::table
+            .inner_join(users::table)
+            .filter(conversation_members::conversation_id.eq(conversation_id))
+            .order(conversation_members::joined_at.asc())
+            .select((ConversationMember::as_select(), users::username))
+            .load::<(ConversationMember, String)>(conn)?;
+        Ok(members
+            .into_iter()
+            .map(|(member, username)| PubConversationMember {
+                user_id: member.user_id,
+                username,
+                role: member.role(),
+                joined_at: member.joined_at,
+            })
+            .collect())
+    }
+
+    /// Removes the member from the group
+    ///
+    /// The longest standing member becomes `admin` when the last admin leaves,
+    /// and the group is deleted along with its messages once nobody is left
+    pub fn leave_group(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> QueryResult<()> {
+        diesel::delete(conversation_members::table)
+            .filter(
+                conversation_members::conversation_id
+                    .eq(conversation_id)
+                    .and(conversation_members::user_id.eq(user_id)),
+            )
+            .execute(conn)?;
+
+        let remaining = conversation_members::table
+            .filter(conversation_members::conversation_id.eq(conversation_id))
+            .order(conversation_members::joined_at.asc())
+            .select(ConversationMember::as_select())
+            .load::<ConversationMember>(conn)?;
+        let Some(oldest) = remaining.first() else {
+            diesel::delete(conversations::table.find(conversation_id)).execute(conn)?;
+            return Ok(());
+        };
+        if !remaining
+            .iter()
+            .any(|member| member.role() == ConversationRole::Admin)
+        {
+            diesel::update(conversation_members::table)
+                .filter(
+                    conversation_members::conversation_id
+                        .eq(conversation_id)
+                        .and(conversation_members::user_id.eq(oldest.user_id)),
+                )
+                .set(conversation_members::role.eq(ConversationRole::Admin.as_str()))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Locks a message its sender is about to change and returns it with the members
+    /// of its conversation
+    ///
+    /// Fails with `NotFound` for users who can't read the conversation anymore
+    fn own_message(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(ChatMessageDTO, Vec<Uuid>), ApiError> {
+        let message = chat_messages::table
+            .find(message_id)
+            .select(ChatMessageDTO::as_select())
+            .for_update()
+            .first::<ChatMessageDTO>(conn)?;
+        let members = Self::members(conn, message.conversation_id)?;
+        if !members.contains(&user_id) {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+        if message.sender_id != user_id {
+            return Err(ApiError::from_type(ApiErrorType::NotMessageSender));
+        }
+        if message.deleted {
+            return Err(ApiError::from_type(ApiErrorType::InvalidMessage));
+        }
+        Ok((message, members))
+    }
+
+    /// Replaces the content of the message, the previous one goes to its edit history
+    ///
+    /// Returns the edited message, the members of the conversation and the notifications
+    /// of users the new content mentions for the first time
+    pub fn edit_message(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        user_id: Uuid,
+        content: &str,
+    ) -> Result<(ChatMessageDTO, Vec<Uuid>, Vec<Notification>), ApiError> {
+        if content.trim().is_empty() {
+            return Err(ApiError::from_type(ApiErrorType::InvalidMessage));
+        }
+        let (message, members) = Self::own_message(conn, message_id, user_id)?;
+        if message.content == content {
+            return Ok((message, members, Vec::new()));
+        }
+
+        let now = Utc::now().naive_utc();
+        diesel::insert_into(chat_message_edits::table)
+            .values(ChatMessageEdit {
+                id: Uuid::new_v4(),
+                message_id,
+                content: message.content,
+                edited_at: now,
+            })
+            .execute(conn)?;
+        let message = diesel::update(chat_messages::table.find(message_id))
+            .set((
+                chat_messages::content.eq(content),
+                chat_messages::updated_at.eq(now),
+            ))
+            .returning(ChatMessageDTO::as_returning())
+            .get_result::<ChatMessageDTO>(conn)?;
+        let notifications = MentionQueries::store_message_mentions(conn, &message, &members)?;
+
+        Ok((message, members, notifications))
+    }
+
+    /// Deletes the message, leaving a tombstone in its place
+    ///
+    /// The content is wiped along with the edit history and the mentions of the message.
+    /// Returns the tombstone and the members of the conversation
+    pub fn delete_message(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(ChatMessageDTO, Vec<Uuid>), ApiError> {
+        let (_, members) = Self::own_message(conn, message_id, user_id)?;
+
+        diesel::delete(chat_message_edits::table)
+            .filter(chat_message_edits::message_id.eq(message_id))
+            .execute(conn)?;
+        diesel::delete(mentions::table)
+            .filter(mentions::message_id.eq(message_id))
+            .execute(conn)?;
+        let message = diesel::update(chat_messages::table.find(message_id))
+            .set((
+                chat_messages::deleted.eq(true),
+                chat_messages::content.eq(""),
+                chat_messages::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(ChatMessageDTO::as_returning())
+            .get_result::<ChatMessageDTO>(conn)?;
+
+        Ok((message.tombstone(), members))
+    }
+
+    /// Returns the previous versions of the message, newest first, to members of its conversation
+    pub fn message_edits(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<ChatMessageEdit>, ApiError> {
+        let conversation_id = chat_messages::table
+            .find(message_id)
+            .select(chat_messages::conversation_id)
+            .first::<Uuid>(conn)?;
+        if !Self::is_member(conn, conversation_id, user_id)? {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+
+        Ok(chat_message_edits::table
+            .filter(chat_message_edits::message_id.eq(message_id))
+            .order(chat_message_edits::edited_at.desc())
+            .select(ChatMessageEdit::as_select())
+            .load::<ChatMessageEdit>(conn)?)
+    }
+
+    /// Turns a cursor into the `(created_at, id)` position it stands for
+    ///
+    /// A time is paired with `id`, the nil id for `before` and the max id for `after`,
+    /// so messages sent at that exact time are left out either way
+    fn position(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        cursor: MessageCursor,
+        id: Uuid,
+    ) -> Result<(NaiveDateTime, Uuid), ApiError> {
+        match cursor {
+            MessageCursor::Time(time) => Ok((time, id)),
+            MessageCursor::Message(message_id) => chat_messages::table
+                .find(message_id)
+                .filter(chat_messages::conversation_id.eq(conversation_id))
+                .select((chat_messages::created_at, chat_messages::id))
+                .first::<(NaiveDateTime, Uuid)>(conn)
+                .optional()?
+                .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidRequest)),
+        }
+    }
+
+    /// Returns a page of the history of the conversation, deleted messages as tombstones
+    ///
+    /// Without `after` the page holds the newest messages sent before `before`, or at all,
+    /// and `next_cursor` goes further back. With `after` the page holds the oldest messages
+    /// sent after it, up to `before` when both are given, and `next_cursor` goes forward
+    pub fn history(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        before: Option<MessageCursor>,
+        after: Option<MessageCursor>,
+        limit: i64,
+    ) -> Result<MessagePage, ApiError> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let before = before
+            .map(|cursor| Self::position(conn, conversation_id, cursor, Uuid::nil()))
+            .transpose()?;
+        let after = after
+            .map(|cursor| Self::position(conn, conversation_id, cursor, Uuid::max()))
+            .transpose()?;
+
+        let mut query = chat_messages::table
+            .filter(chat_messages::conversation_id.eq(conversation_id))
+            .select(ChatMessageDTO::as_select())
+            .into_boxed();
+        if let Some((time, id)) = before {
+            query = query.filter(chat_messages::created_at.le(time)).filter(
+                chat_messages::created_at
+                    .lt(time)
+                    .or(chat_messages::id.lt(id)),
+            );
+        }
+        if let Some((time, id)) = after {
+            query = query.filter(chat_messages::created_at.ge(time)).filter(
+                chat_messages::created_at
+                    .gt(time)
+                    .or(chat_messages::id.gt(id)),
+            );
+        }
+        let forward = after.is_some();
+        query = if forward {
+            query.order((chat_messages::created_at.asc(), chat_messages::id.asc()))
+        } else {
+            query.order((chat_messages::created_at.desc(), chat_messages::id.desc()))
+        };
+
+        let mut messages = query.limit(limit + 1).load::<ChatMessageDTO>(conn)?;
+        let next_cursor = if messages.len() as i64 > limit {
+            messages.truncate(limit as usize);
+            messages.last().map(|message| message.id)
+        } else {
+            None
+        };
+        if !forward {
+            messages.reverse();
+        }
+
+        Ok(MessagePage {
+            messages: messages.into_iter().map(ChatMessageDTO::visible).collect(),
+            next_cursor,
+        })
+    }
+
+    /// Moves the read position of the user in the conversation of the message up to it
+    ///
+    /// The position never moves back, acknowledging an older message leaves it as is.
+    /// Returns the current position, the members of the conversation and whether it moved
+    pub fn mark_read(
+        conn: &mut PgConnection,
+        message_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(ReadReceipt, Vec<Uuid>, bool), ApiError> {
+        let (conversation_id, created_at) = chat_messages::table
+            .find(message_id)
+            .select((chat_messages::conversation_id, chat_messages::created_at))
+            .first::<(Uuid, NaiveDateTime)>(conn)?;
+        let members = Self::members(conn, conversation_id)?;
+        if !members.contains(&user_id) {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+
+        let current = conversation_reads::table
+            .find((conversation_id, user_id))
+            .select(ReadReceipt::as_select())
+            .for_update()
+            .first::<ReadReceipt>(conn)
+            .optional()?;
+        if let Some(current) = current {
+            if (current.message_created_at, current.message_id) >= (created_at, message_id) {
+                return Ok((current, members, false));
+            }
+        }
+
+        let receipt = ReadReceipt {
+            conversation_id,
+            user_id,
+            message_id,
+            message_created_at: created_at,
+            read_at: Utc::now().naive_utc(),
+        };
+        diesel::insert_into(conversation_reads::table)
+            .values(&receipt)
+            .on_conflict((
+                conversation_reads::conversation_id,
+                conversation_reads::user_id,
+            ))
+            .do_update()
+            .set((
+                conversation_reads::message_id.eq(receipt.message_id),
+                conversation_reads::message_created_at.eq(receipt.message_created_at),
+                conversation_reads::read_at.eq(receipt.read_at),
+            ))
+            .execute(conn)?;
+        Ok((receipt, members, true))
+    }
+
+    /// Returns how far each member has read the conversation
+    pub fn reads(conn: &mut PgConnection, conversation_id: Uuid) -> QueryResult<Vec<ReadReceipt>> {
+        let members = Self::members(conn, conversation_id)?;
+        conversation_reads::table
+            .filter(conversation_reads::conversation_id.eq(conversation_id))
+            .filter(conversation_reads::user_id.eq_any(members))
+            .select(ReadReceipt::as_select())
+            .load::<ReadReceipt>(conn)
+    }
+
+    /// Returns the conversations of the user with their last message and unread count,
+    /// most recently active first
+    ///
+    /// Board chats are listed once someone opened them
+    pub fn conversations(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+    ) -> QueryResult<Vec<ConversationSummary>> {
+        let joined = conversation_members::table
+            .filter(conversation_members::user_id.eq(user_id))
+            .select(conversation_members::conversation_id)
+            .load::<Uuid>(conn)?;
+        let board_ids = board_users_relation::table
+            .filter(board_users_relation::user_id.eq(user_id))
+            .select(board_users_relation::board_id)
+            .load::<Uuid>(conn)?;
+        let conversations = conversations::table
+            .filter(
+                conversations::id
+                    .eq_any(joined)
+                    .or(conversations::board_id.eq_any(board_ids)),
+            )
+            .select(Conversation::as_select())
+            .load::<Conversation>(conn)?;
+
+        let mut summaries = Vec::with_capacity(conversations.len());
+        for conversation in conversations {
+            let last_message = chat_messages::table
+                .filter(chat_messages::conversation_id.eq(conversation.id))
+                .order((chat_messages::created_at.desc(), chat_messages::id.desc()))
+                .select(ChatMessageDTO::as_select())
+                .first::<ChatMessageDTO>(conn)
+                .optional()?;
+            let read = conversation_reads::table
+                .find((conversation.id, user_id))
+                .select(ReadReceipt::as_select())
+                .first::<ReadReceipt>(conn)
+                .optional()?;
+
+            let mut unread = chat_messages::table
+                .filter(chat_messages::conversation_id.eq(conversation.id))
+                .filter(chat_messages::sender_id.ne(user_id))
+                .filter(chat_messages::deleted.eq(false))
+                .into_boxed();
+            if let Some(read) = &read {
+                unread = unread
+                    .filter(chat_messages::created_at.ge(read.message_created_at))
+                    .filter(
+                        chat_messages::created_at
+                            .gt(read.message_created_at)
+                            .or(chat_messages::id.gt(read.message_id)),
+                    );
+            }
+            let unread = unread.count().get_result::<i64>(conn)?;
+
+            summaries.push(ConversationSummary {
+                conversation,
+                last_message: last_message.map(ChatMessageDTO::visible),
+                unread,
+                last_read_message_id: read.map(|read| read.message_id),
+            });
+        }
+        summaries.sort_by(|a, b| {
+            let sent_at = |summary: &ConversationSummary| {
+                summary
+                    .last_message
+                    .as_ref()
+                    .map(|message| (message.created_at, message.id))
+            };
+            sent_at(b).cmp(&sent_at(a))
+        });
+        Ok(summaries)
+    }
+}
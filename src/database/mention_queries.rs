@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::database::notification_queries::NotificationQueries;
+use crate::models::{
+    mention::{parse_mentions, Mention, PubMention},
+    messages::ChatMessageDTO,
+    notification::{Notification, NotificationKind},
+};
+use crate::schema::{board_users_relation, mentions, users};
+
+pub struct MentionQueries;
+
+impl MentionQueries {
+    /// Resolves the `@username` mentions of `text` among the `allowed` users
+    ///
+    /// A username that matches nobody in `allowed`, or more than one user,
+    /// is left as plain text
+    fn resolve(conn: &mut PgConnection, text: &str, allowed: &[Uuid]) -> QueryResult<Vec<Uuid>> {
+        let usernames = parse_mentions(text);
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+        let candidates = users::table
+            .filter(users::username.eq_any(&usernames))
+            .filter(users::id.eq_any(allowed))
+            .select((users::id, users::username))
+            .load::<(Uuid, String)>(conn)?;
+
+        let mut by_name: HashMap<&str, Vec<Uuid>> = HashMap::new();
+        for (id, username) in &candidates {
+            by_name.entry(username.as_str()).or_default().push(*id);
+        }
+        Ok(usernames
+            .iter()
+            .filter_map(|username| match by_name.get(username.as_str()) {
+                Some(ids) if ids.len() == 1 => Some(ids[0]),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        user_ids: &[Uuid],
+        author_id: Uuid,
+        card_id: Option<Uuid>,
+        message_id: Option<Uuid>,
+    ) -> QueryResult<()> {
+        let now = Utc::now().naive_utc();
+        let rows = user_ids
+            .iter()
+            .map(|user_id| Mention {
+                id: Uuid::new_v4(),
+                user_id: *user_id,
+                author_id,
+                card_id,
+                message_id,
+                created_at: now,
+            })
+            .collect::<Vec<Mention>>();
+        diesel::insert_into(mentions::table)
+            .values(&rows)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Replaces the mentions of the card with the ones found in its description
+    ///
+    /// Only members of the board can be mentioned, and only users that were not
+    /// mentioned before get notified so editing a description does not notify twice
+    pub fn store_card_mentions(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card_id: Uuid,
+        card_name: &str,
+        author_id: Uuid,
+        description: &str,
+    ) -> QueryResult<Vec<Notification>> {
+        let members = board_users_relation::table
+            .filter(board_users_relation::board_id.eq(board_id))
+            .select(board_users_relation::user_id)
+            .load::<Uuid>(conn)?;
+        let mentioned = Self::resolve(conn, description, &members)?;
+
+        let previous = diesel::delete(mentions::table)
+            .filter(mentions::card_id.eq(card_id))
+            .returning(mentions::user_id)
+            .get_results::<Uuid>(conn)?;
+        Self::insert(conn, &mentioned, author_id, Some(card_id), None)?;
+
+        mentioned
+            .into_iter()
+            .filter(|user_id| *user_id != author_id && !previous.contains(user_id))
+            .map(|user_id| {
+                NotificationQueries::create(
+                    conn,
+                    user_id,
+                    Some(author_id),
+                    NotificationKind::Mentioned,
+                    json!({ "board_id": board_id, "card_id": card_id, "card_name": card_name }),
+                )
+            })
+            .collect()
+    }
+
+    /// Replaces the mentions of a chat message among the members of its conversation
+    ///
+    /// Like for cards, editing a message only notifies users it did not mention before
+    pub fn store_message_mentions(
+        conn: &mut PgConnection,
+        message: &ChatMessageDTO,
+        members: &[Uuid],
+    ) -> QueryResult<Vec<Notification>> {
+        let mentioned = Self::resolve(conn, &message.content, members)?;
+
+        let previous = diesel::delete(mentions::table)
+            .filter(mentions::message_id.eq(message.id))
+            .returning(mentions::user_id)
+            .get_results::<Uuid>(conn)?;
+        Self::insert(conn, &mentioned, message.sender_id, None, Some(message.id))?;
+
+        mentioned
+            .into_iter()
+            .filter(|user_id| *user_id != message.sender_id && !previous.contains(user_id))
+            .map(|user_id| {
+                NotificationQueries::create(
+                    conn,
+                    user_id,
+                    Some(message.sender_id),
+                    NotificationKind::Mentioned,
+                    json!({
+                        "conversation_id": message.conversation_id,
+                        "message_id": message.id,
+                        "preview": message.content.chars().take(100).collect::<String>(),
+                    }),
+                )
+            })
+            .collect()
+    }
+
+    pub fn load_card_mentions(
+        conn: &mut PgConnection,
+        card_id: Uuid,
+    ) -> QueryResult<Vec<PubMention>> {
+        let user_ids = mentions::table
+            .filter(mentions::card_id.eq(card_id))
+            .select(mentions::user_id)
+            .load::<Uuid>(conn)?;
+        users::table
+            .filter(users::id.eq_any(user_ids))
+            .select((users::id, users::username))
+            .load::<(Uuid, String)>(conn)
+            .map(|users| {
+                users
+                    .into_iter()
+                    .map(|(user_id, username)| PubMention { user_id, username })
+                    .collect()
+            })
+    }
+}
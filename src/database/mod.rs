@@ -2,6 +2,18 @@ use rocket_sync_db_pools::database;
 
 pub mod user_queries;
 pub mod file_queries;
+pub mod analytics_queries;
+pub mod api_token_queries;
+pub mod automation_queries;
+pub mod calendar_queries;
+pub mod chat_queries;
+pub mod mention_queries;
+pub mod notification_queries;
+pub mod operation_queries;
+pub mod revision_queries;
+pub mod time_queries;
+pub mod webhook_queries;
+pub mod workspace_queries;
 
 #[database("pgsql")]
 pub struct Db(diesel::PgConnection);
\ No newline at end of file
@@ -0,0 +1,118 @@
+use chrono::Utc;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::database::Db;
+use crate::errors::{ApiError, ApiErrorType};
+use crate::models::notification::{Notification, NotificationKind, NotificationPage};
+use crate::schema::notifications;
+
+pub const MAX_PER_PAGE: i64 = 100;
+
+pub struct NotificationQueries;
+
+impl NotificationQueries {
+    /// Stores a notification for `user_id`
+    ///
+    /// Takes a plain connection so handlers can emit from inside their own transaction,
+    /// the returned row is then pushed with `WsState::notify` once the transaction commits
+    pub fn create(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        actor_id: Option<Uuid>,
+        kind: NotificationKind,
+        payload: Value,
+    ) -> QueryResult<Notification> {
+        diesel::insert_into(notifications::table)
+            .values(Notification {
+                id: Uuid::new_v4(),
+                user_id,
+                actor_id,
+                kind: kind.as_str().to_string(),
+                payload,
+                read_at: None,
+                created_at: Utc::now().naive_utc(),
+            })
+            .get_result::<Notification>(conn)
+    }
+
+    pub async fn load_page(
+        db: &Db,
+        user_id: Uuid,
+        unread_only: bool,
+        page: i64,
+        per_page: i64,
+    ) -> Result<NotificationPage, ApiError> {
+        let page = page.max(1);
+        let per_page = per_page.clamp(1, MAX_PER_PAGE);
+        let offset = (page - 1)
+            .checked_mul(per_page)
+            .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidRequest))?;
+        db.run(move |conn| {
+            let mut query = notifications::table
+                .filter(notifications::user_id.eq(user_id))
+                .into_boxed();
+            if unread_only {
+                query = query.filter(notifications::read_at.is_null());
+            }
+            let notifications = query
+                .order((notifications::created_at.desc(), notifications::id.desc()))
+                .offset(offset)
+                .limit(per_page)
+                .load::<Notification>(conn)?;
+
+            let total = notifications::table
+                .filter(notifications::user_id.eq(user_id))
+                .count()
+                .get_result::<i64>(conn)?;
+            let unread = notifications::table
+                .filter(notifications::user_id.eq(user_id))
+                .filter(notifications::read_at.is_null())
+                .count()
+                .get_result::<i64>(conn)?;
+
+            Ok(NotificationPage {
+                notifications,
+                page,
+                per_page,
+                total: if unread_only { unread } else { total },
+                unread,
+            })
+        })
+        .await
+    }
+
+    pub async fn mark_read(
+        db: &Db,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<Notification, ApiError> {
+        db.run(move |conn| {
+            let notification = notifications::table
+                .filter(notifications::id.eq(notification_id))
+                .filter(notifications::user_id.eq(user_id))
+                .first::<Notification>(conn)?;
+            if notification.read_at.is_some() {
+                return Ok(notification);
+            }
+            diesel::update(notifications::table.find(notification.id))
+                .set(notifications::read_at.eq(Utc::now().naive_utc()))
+                .get_result::<Notification>(conn)
+                .map_err(ApiError::from)
+        })
+        .await
+    }
+
+    pub async fn mark_all_read(db: &Db, user_id: Uuid) -> Result<usize, ApiError> {
+        db.run(move |conn| {
+            diesel::update(notifications::table)
+                .filter(notifications::user_id.eq(user_id))
+                .filter(notifications::read_at.is_null())
+                .set(notifications::read_at.eq(Utc::now().naive_utc()))
+                .execute(conn)
+                .map_err(ApiError::from)
+        })
+        .await
+    }
+}
@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use diesel::{
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::database::analytics_queries::AnalyticsQueries;
+use crate::models::file::UploadedFile;
+use crate::models::operation::{BoardOperation, CardSnapshot, ColumnSnapshot, OperationKind};
+use crate::models::{ReturnedCard, SELECT_CARD};
+use crate::schema::{
+    board_custom_fields, board_lane, board_operations, card_attachments, card_field_values,
+    column_card, files, sprints, users,
+};
+
+pub struct OperationQueries;
+
+impl OperationQueries {
+    /// Stores an operation so its author can undo it later
+    pub fn record(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        user_id: Uuid,
+        kind: OperationKind,
+        target_id: Uuid,
+        snapshot: Option<Value>,
+        result_version: Option<i32>,
+    ) -> QueryResult<Uuid> {
+        diesel::insert_into(board_operations::table)
+            .values(BoardOperation {
+                id: Uuid::new_v4(),
+                board_id,
+                user_id: Some(user_id),
+                kind: kind.as_str().to_string(),
+                target_id,
+                snapshot,
+                result_version,
+                created_at: Utc::now().naive_utc(),
+                undone_at: None,
+            })
+            .returning(board_operations::id)
+            .get_result::<Uuid>(conn)
+    }
+
+    /// Captures everything about a card that is about to be deleted
+    pub fn snapshot_card(conn: &mut PgConnection, card_id: Uuid) -> QueryResult<CardSnapshot> {
+        let card = column_card::table
+            .filter(column_card::id.eq(card_id))
+            .select(SELECT_CARD)
+            .first::<ReturnedCard>(conn)?;
+        let custom_fields = card_field_values::table
+            .filter(card_field_values::card_id.eq(card_id))
+            .select((card_field_values::field_id, card_field_values::value))
+            .load::<(Uuid, Value)>(conn)?
+            .into_iter()
+            .collect();
+        let attachments = card_attachments::table
+            .inner_join(files::table)
+            .filter(card_attachments::card_id.eq(card_id))
+            .select(UploadedFile::as_select())
+            .load::<UploadedFile>(conn)?;
+
+        Ok(CardSnapshot {
+            id: card.0,
+            name: card.1,
+            cover_attachment: card.2,
+            position: card.3,
+            description: card.4,
+            column_id: card.5,
+            parent_id: card.6,
+            completed_at: card.7,
+            assignee_id: card.8,
+            lane_id: card.9,
+            sprint_id: card.10,
+            custom_fields,
+            attachments,
+        })
+    }
+
+    /// Inserts a deleted card back at its position
+    ///
+    /// Lanes, sprints, fields and assignees that are gone by now are dropped, and so
+    /// are attachments whose file isn't on disk anymore. The parent is left out,
+    /// `restore_parents` sets it once every card of the operation is back
+    pub fn restore_card(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        card: &CardSnapshot,
+        restored_by: Uuid,
+    ) -> QueryResult<()> {
+        let lane_id = match card.lane_id {
+            Some(lane_id) => board_lane::table
+                .filter(board_lane::id.eq(lane_id))
+                .filter(board_lane::board_id.eq(board_id))
+                .select(board_lane::id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            None => None,
+        };
+        let sprint_id = match card.sprint_id {
+            Some(sprint_id) => sprints::table
+                .filter(sprints::id.eq(sprint_id))
+                .filter(sprints::board_id.eq(board_id))
+                .select(sprints::id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            None => None,
+        };
+        let assignee_id = match card.assignee_id {
+            Some(assignee_id) => users::table
+                .find(assignee_id)
+                .select(users::id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            None => None,
+        };
+        let attachments = card
+            .attachments
+            .iter()
+            .filter(|file| Path::new(&file.path()).exists())
+            .collect::<Vec<&UploadedFile>>();
+        let cover_attachment = card
+            .cover_attachment
+            .clone()
+            .filter(|cover| attachments.iter().any(|file| &file.name == cover));
+
+        diesel::update(column_card::table)
+            .filter(column_card::column_id.eq(card.column_id))
+            .filter(column_card::position.ge(card.position))
+            .set(column_card::position.eq(column_card::position + 1))
+            .execute(conn)?;
+        diesel::insert_into(column_card::table)
+            .values((
+                column_card::id.eq(card.id),
+                column_card::column_id.eq(card.column_id),
+                column_card::name.eq(&card.name),
+                column_card::description.eq(&card.description),
+                column_card::position.eq(card.position),
+                column_card::cover_attachment.eq(cover_attachment),
+                column_card::completed_at.eq(card.completed_at),
+                column_card::assignee_id.eq(assignee_id),
+                column_card::lane_id.eq(lane_id),
+                column_card::sprint_id.eq(sprint_id),
+            ))
+            .execute(conn)?;
+
+        let fields = board_custom_fields::table
+            .filter(board_custom_fields::board_id.eq(board_id))
+            .filter(board_custom_fields::id.eq_any(card.custom_fields.keys()))
+            .select(board_custom_fields::id)
+            .load::<Uuid>(conn)?;
+        for field_id in fields {
+            diesel::insert_into(card_field_values::table)
+                .values((
+                    card_field_values::card_id.eq(card.id),
+                    card_field_values::field_id.eq(field_id),
+                    card_field_values::value.eq(&card.custom_fields[&field_id]),
+                ))
+                .execute(conn)?;
+        }
+
+        for file in attachments {
+            diesel::insert_into(files::table)
+                .values(file)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            diesel::insert_into(card_attachments::table)
+                .values((
+                    card_attachments::file_id.eq(file.id),
+                    card_attachments::card_id.eq(card.id),
+                ))
+                .execute(conn)?;
+        }
+
+        AnalyticsQueries::record_movement(
+            conn,
+            board_id,
+            card.id,
+            None,
+            Some(card.column_id),
+            Some(restored_by),
+        )
+    }
+
+    /// Links restored cards to their parents again, as long as the parent exists
+    pub fn restore_parents(conn: &mut PgConnection, cards: &[CardSnapshot]) -> QueryResult<()> {
+        for card in cards {
+            let Some(parent_id) = card.parent_id else {
+                continue;
+            };
+            let parent_exists = column_card::table
+                .filter(column_card::id.eq(parent_id))
+                .count()
+                .get_result::<i64>(conn)?
+                > 0;
+            if parent_exists {
+                diesel::update(column_card::table)
+                    .filter(column_card::id.eq(card.id))
+                    .set(column_card::parent_id.eq(parent_id))
+                    .execute(conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Files the operation keeps on disk so the cards it deleted can be restored
+    pub fn kept_files(operation: &BoardOperation) -> Vec<UploadedFile> {
+        let Some(snapshot) = operation.snapshot.clone() else {
+            return Vec::new();
+        };
+        let cards = match operation.kind.parse::<OperationKind>() {
+            Ok(OperationKind::CardDeleted) => {
+                serde_json::from_value::<Vec<CardSnapshot>>(snapshot).unwrap_or_default()
+            }
+            Ok(OperationKind::ColumnDeleted) => serde_json::from_value::<ColumnSnapshot>(snapshot)
+                .map(|column| column.cards)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        cards
+            .into_iter()
+            .flat_map(|card| card.attachments)
+            .collect()
+    }
+
+    /// Drops the operations older than the undo window
+    ///
+    /// Returns the paths of the files of deleted cards nobody brought back, the caller
+    /// removes them once the purge is committed
+    pub fn purge_expired(conn: &mut PgConnection, window: Duration) -> QueryResult<Vec<String>> {
+        let expired = board_operations::table
+            .filter(board_operations::created_at.lt(Utc::now().naive_utc() - window))
+            .select(BoardOperation::as_select())
+            .load::<BoardOperation>(conn)?;
+        Self::discard(conn, expired)
+    }
+
+    /// Drops every operation of the board, used when the board itself is deleted
+    ///
+    /// Returns the paths of the files the operations kept on disk, the caller removes
+    /// them once the deletion is committed
+    pub fn discard_board(conn: &mut PgConnection, board_id: Uuid) -> QueryResult<Vec<String>> {
+        let operations = board_operations::table
+            .filter(board_operations::board_id.eq(board_id))
+            .select(BoardOperation::as_select())
+            .load::<BoardOperation>(conn)?;
+        diesel::delete(board_operations::table)
+            .filter(board_operations::board_id.eq(board_id))
+            .execute(conn)?;
+        Ok(Self::pending_files(&operations))
+    }
+
+    /// Paths of the files kept for operations that were not undone
+    fn pending_files(operations: &[BoardOperation]) -> Vec<String> {
+        operations
+            .iter()
+            .filter(|op| op.undone_at.is_none())
+            .flat_map(Self::kept_files)
+            .map(|file| file.path())
+            .collect()
+    }
+
+    fn discard(
+        conn: &mut PgConnection,
+        operations: Vec<BoardOperation>,
+    ) -> QueryResult<Vec<String>> {
+        diesel::delete(board_operations::table)
+            .filter(board_operations::id.eq_any(operations.iter().map(|op| op.id)))
+            .execute(conn)?;
+        Ok(Self::pending_files(&operations))
+    }
+}
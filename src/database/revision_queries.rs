@@ -0,0 +1,95 @@
+use chrono::Utc;
+use diesel::{
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::models::revision::CardRevision;
+use crate::schema::{card_revisions, column_card};
+
+pub struct RevisionQueries;
+
+impl RevisionQueries {
+    /// Stores the current name and description of the card as its next revision
+    ///
+    /// Nothing is stored when neither changed since the last revision. The first
+    /// edit of a card also stores what it looked like before, so every history
+    /// starts from the original text
+    pub fn record(
+        conn: &mut PgConnection,
+        card_id: Uuid,
+        previous_name: &str,
+        previous_description: Option<&str>,
+        author_id: Uuid,
+    ) -> QueryResult<Option<CardRevision>> {
+        let (name, description) = column_card::table
+            .filter(column_card::id.eq(card_id))
+            .select((column_card::name, column_card::description))
+            .first::<(String, Option<String>)>(conn)?;
+        let latest = Self::latest(conn, card_id)?;
+
+        let revision = match latest {
+            Some(latest) if latest.name == name && latest.description == description => {
+                return Ok(None)
+            }
+            Some(latest) => latest.revision + 1,
+            None if previous_name == name && previous_description == description.as_deref() => {
+                return Ok(None)
+            }
+            None => {
+                Self::insert(
+                    conn,
+                    card_id,
+                    1,
+                    previous_name.to_string(),
+                    previous_description.map(str::to_string),
+                    None,
+                )?;
+                2
+            }
+        };
+        Self::insert(conn, card_id, revision, name, description, Some(author_id)).map(Some)
+    }
+
+    /// Returns the newest revision of the card
+    pub fn latest(conn: &mut PgConnection, card_id: Uuid) -> QueryResult<Option<CardRevision>> {
+        card_revisions::table
+            .filter(card_revisions::card_id.eq(card_id))
+            .order(card_revisions::revision.desc())
+            .select(CardRevision::as_select())
+            .first::<CardRevision>(conn)
+            .optional()
+    }
+
+    /// Returns the given revision of the card
+    pub fn get(conn: &mut PgConnection, card_id: Uuid, revision: i32) -> QueryResult<CardRevision> {
+        card_revisions::table
+            .filter(card_revisions::card_id.eq(card_id))
+            .filter(card_revisions::revision.eq(revision))
+            .select(CardRevision::as_select())
+            .first::<CardRevision>(conn)
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        card_id: Uuid,
+        revision: i32,
+        name: String,
+        description: Option<String>,
+        author_id: Option<Uuid>,
+    ) -> QueryResult<CardRevision> {
+        diesel::insert_into(card_revisions::table)
+            .values(CardRevision {
+                id: Uuid::new_v4(),
+                card_id,
+                revision,
+                name,
+                description,
+                author_id,
+                created_at: Utc::now().naive_utc(),
+            })
+            .returning(CardRevision::as_returning())
+            .get_result::<CardRevision>(conn)
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate, Utc};
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::models::time_entry::{ReportedTimeEntry, TimeEntry, TimeSummary, TimeTotal};
+use crate::schema::{column_card, time_entries, users};
+
+/// Which entries a time summary covers
+#[derive(Debug, Clone, Copy)]
+pub enum TimeScope {
+    Card(Uuid),
+    Board(Uuid),
+    User(Uuid),
+}
+
+pub struct TimeQueries;
+
+fn totals(totals: HashMap<Uuid, (String, i64)>) -> Vec<TimeTotal> {
+    let mut totals = totals
+        .into_iter()
+        .map(|(id, (name, seconds))| TimeTotal { id, name, seconds })
+        .collect::<Vec<TimeTotal>>();
+    totals.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+    totals
+}
+
+impl TimeQueries {
+    /// Sums up the entries of the scope that started between `from` and `to`, both days included
+    pub fn summary(
+        conn: &mut PgConnection,
+        scope: TimeScope,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> QueryResult<TimeSummary> {
+        let start = from.and_hms_opt(0, 0, 0).unwrap_or_default();
+        let end = (to + Days::new(1)).and_hms_opt(0, 0, 0).unwrap_or_default();
+
+        let mut query = time_entries::table
+            .inner_join(users::table)
+            .filter(time_entries::started_at.ge(start))
+            .filter(time_entries::started_at.lt(end))
+            .into_boxed();
+        query = match scope {
+            TimeScope::Card(card_id) => query.filter(time_entries::card_id.eq(card_id)),
+            TimeScope::Board(board_id) => query.filter(time_entries::board_id.eq(board_id)),
+            TimeScope::User(user_id) => query.filter(time_entries::user_id.eq(user_id)),
+        };
+        let entries = query
+            .order(time_entries::started_at.asc())
+            .select((
+                (
+                    time_entries::id,
+                    time_entries::board_id,
+                    time_entries::card_id,
+                    time_entries::user_id,
+                    time_entries::started_at,
+                    time_entries::ended_at,
+                    time_entries::note,
+                    time_entries::created_at,
+                ),
+                users::username,
+            ))
+            .load::<(TimeEntry, String)>(conn)?;
+
+        let card_names = column_card::table
+            .filter(column_card::id.eq_any(entries.iter().map(|(entry, _)| entry.card_id)))
+            .select((column_card::id, column_card::name))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .collect::<HashMap<Uuid, String>>();
+
+        let now = Utc::now().naive_utc();
+        let mut by_user: HashMap<Uuid, (String, i64)> = HashMap::new();
+        let mut by_card: HashMap<Uuid, (String, i64)> = HashMap::new();
+        let entries = entries
+            .into_iter()
+            .map(|(entry, username)| {
+                let card_name = card_names.get(&entry.card_id).cloned().unwrap_or_default();
+                let duration_seconds =
+                    (entry.ended_at.unwrap_or(now) - entry.started_at).num_seconds();
+                by_user
+                    .entry(entry.user_id)
+                    .or_insert_with(|| (username.clone(), 0))
+                    .1 += duration_seconds;
+                by_card
+                    .entry(entry.card_id)
+                    .or_insert_with(|| (card_name.clone(), 0))
+                    .1 += duration_seconds;
+                ReportedTimeEntry {
+                    id: entry.id,
+                    board_id: entry.board_id,
+                    card_id: entry.card_id,
+                    card_name,
+                    user_id: entry.user_id,
+                    username,
+                    started_at: entry.started_at,
+                    ended_at: entry.ended_at,
+                    duration_seconds,
+                    note: entry.note,
+                }
+            })
+            .collect::<Vec<ReportedTimeEntry>>();
+
+        Ok(TimeSummary {
+            from,
+            to,
+            total_seconds: entries.iter().map(|entry| entry.duration_seconds).sum(),
+            by_user: totals(by_user),
+            by_card: totals(by_card),
+            entries,
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{Duration, Utc};
+use diesel::{
+    ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl, SelectableHelper,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::models::webhook::{backoff, DeliveryStatus, DueDelivery, Webhook, WebhookDelivery};
+use crate::schema::{webhook_deliveries, webhooks};
+
+/// How long a claimed delivery stays hidden from other workers
+const LEASE: Duration = Duration::seconds(60);
+
+pub struct WebhookQueries;
+
+impl WebhookQueries {
+    /// Records a change made through a board route in the delivery queue
+    ///
+    /// Board routes call it inside their transaction, so the deliveries are stored
+    /// together with the change and vanish with it when it is rolled back.
+    /// `event` is the name of the route without its `boards_` prefix
+    pub fn emit<T: Serialize>(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        event: &str,
+        actor_id: Uuid,
+        data: &T,
+    ) -> QueryResult<usize> {
+        let payload = json!({
+            "event": event,
+            "board_id": board_id,
+            "actor_id": actor_id,
+            "data": data,
+            "occurred_at": Utc::now().naive_utc(),
+        });
+        Self::enqueue(conn, board_id, event, &payload)
+    }
+
+    /// Queues a delivery of the event for every active webhook of the board that wants it
+    ///
+    /// Returns how many deliveries were queued
+    fn enqueue(
+        conn: &mut PgConnection,
+        board_id: Uuid,
+        event: &str,
+        payload: &Value,
+    ) -> QueryResult<usize> {
+        let now = Utc::now().naive_utc();
+        let deliveries = webhooks::table
+            .filter(webhooks::board_id.eq(board_id))
+            .filter(webhooks::active.eq(true))
+            .select(Webhook::as_select())
+            .load::<Webhook>(conn)?
+            .into_iter()
+            .filter(|webhook| webhook.wants(event))
+            .map(|webhook| WebhookDelivery::new(webhook.id, event, payload.clone(), now))
+            .collect::<Vec<_>>();
+        if deliveries.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .execute(conn)
+    }
+
+    /// Claims the pending deliveries that are due, oldest first
+    ///
+    /// Claimed deliveries are pushed back by a lease, so a worker that dies
+    /// mid-delivery only delays them
+    pub fn claim_due(conn: &mut PgConnection, limit: i64) -> QueryResult<Vec<DueDelivery>> {
+        let now = Utc::now().naive_utc();
+        let due = webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .filter(webhook_deliveries::status.eq(DeliveryStatus::Pending.as_str()))
+            .filter(webhook_deliveries::next_attempt_at.le(now))
+            .filter(webhooks::active.eq(true))
+            .order(webhook_deliveries::next_attempt_at.asc())
+            .limit(limit)
+            .select((
+                WebhookDelivery::as_select(),
+                webhooks::url,
+                webhooks::secret,
+            ))
+            .for_update()
+            .skip_locked()
+            .load::<(WebhookDelivery, String, String)>(conn)?;
+
+        diesel::update(webhook_deliveries::table)
+            .filter(webhook_deliveries::id.eq_any(due.iter().map(|row| row.0.id)))
+            .set(webhook_deliveries::next_attempt_at.eq(now + LEASE))
+            .execute(conn)?;
+
+        Ok(due
+            .into_iter()
+            .map(|(delivery, url, secret)| DueDelivery {
+                delivery,
+                url,
+                secret,
+            })
+            .collect())
+    }
+
+    pub fn mark_delivered(
+        conn: &mut PgConnection,
+        delivery_id: Uuid,
+        status_code: i32,
+    ) -> QueryResult<usize> {
+        let now = Utc::now().naive_utc();
+        diesel::update(webhook_deliveries::table)
+            .filter(webhook_deliveries::id.eq(delivery_id))
+            .set((
+                webhook_deliveries::status.eq(DeliveryStatus::Delivered.as_str()),
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::last_status_code.eq(status_code),
+                webhook_deliveries::last_error.eq(None::<String>),
+                webhook_deliveries::delivered_at.eq(now),
+            ))
+            .execute(conn)
+    }
+
+    /// Records a failed attempt and schedules the next one after its `backoff`
+    pub fn mark_failed(
+        conn: &mut PgConnection,
+        delivery: &WebhookDelivery,
+        status_code: Option<i32>,
+        error: &str,
+    ) -> QueryResult<usize> {
+        let attempts = delivery.attempts + 1;
+        let status = DeliveryStatus::after_failure(attempts);
+        diesel::update(webhook_deliveries::table)
+            .filter(webhook_deliveries::id.eq(delivery.id))
+            .set((
+                webhook_deliveries::status.eq(status.as_str()),
+                webhook_deliveries::attempts.eq(attempts),
+                webhook_deliveries::next_attempt_at.eq(Utc::now().naive_utc() + backoff(attempts)),
+                webhook_deliveries::last_status_code.eq(status_code),
+                webhook_deliveries::last_error.eq(error),
+            ))
+            .execute(conn)
+    }
+
+    /// Queues the payload of an earlier delivery again, as a new delivery
+    pub fn redeliver(
+        conn: &mut PgConnection,
+        delivery: &WebhookDelivery,
+    ) -> QueryResult<WebhookDelivery> {
+        diesel::insert_into(webhook_deliveries::table)
+            .values(delivery.redelivery(Utc::now().naive_utc()))
+            .returning(WebhookDelivery::as_returning())
+            .get_result::<WebhookDelivery>(conn)
+    }
+}
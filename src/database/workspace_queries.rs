@@ -0,0 +1,172 @@
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::models::workspace::{Workspace, WorkspaceMember, WorkspaceRole};
+use crate::models::BoardUsersRelation;
+use crate::schema::{board_users_relation, boards, workspace_members, workspaces};
+
+/// Workspace members reach the boards of the workspace through `board_users_relation`
+/// rows flagged `via_workspace`, these queries keep them in sync with the membership.
+/// Rows of users invited to the board directly are left alone
+pub struct WorkspaceQueries;
+
+impl WorkspaceQueries {
+    /// Returns the personal workspace of the user, creating it on first use
+    pub fn personal_workspace(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Uuid> {
+        let existing = workspaces::table
+            .filter(workspaces::owner_id.eq(user_id))
+            .filter(workspaces::personal.eq(true))
+            .select(workspaces::id)
+            .first::<Uuid>(conn)
+            .optional()?;
+        if let Some(workspace_id) = existing {
+            return Ok(workspace_id);
+        }
+
+        let workspace = Self::create(conn, user_id, "Personal", true)?;
+        Ok(workspace.id)
+    }
+
+    /// Creates a workspace with the user as its owner
+    pub fn create(
+        conn: &mut PgConnection,
+        owner_id: Uuid,
+        name: &str,
+        personal: bool,
+    ) -> QueryResult<Workspace> {
+        let now = Utc::now().naive_utc();
+        let workspace = diesel::insert_into(workspaces::table)
+            .values(Workspace {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                owner_id,
+                personal,
+                created_at: now,
+            })
+            .get_result::<Workspace>(conn)?;
+        diesel::insert_into(workspace_members::table)
+            .values(WorkspaceMember {
+                workspace_id: workspace.id,
+                user_id: owner_id,
+                role: WorkspaceRole::Owner.as_str().to_string(),
+                joined_at: now,
+            })
+            .execute(conn)?;
+        Ok(workspace)
+    }
+
+    /// Returns the membership of the user, `NotFound` when they are not a member
+    pub fn membership(
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        user_id: Uuid,
+    ) -> QueryResult<WorkspaceMember> {
+        workspace_members::table
+            .filter(
+                workspace_members::workspace_id
+                    .eq(workspace_id)
+                    .and(workspace_members::user_id.eq(user_id)),
+            )
+            .select(WorkspaceMember::as_select())
+            .first::<WorkspaceMember>(conn)
+    }
+
+    /// Gives the users access to every board of the workspace
+    pub fn grant_boards(
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> QueryResult<()> {
+        let board_ids = boards::table
+            .filter(boards::workspace_id.eq(workspace_id))
+            .select(boards::id)
+            .load::<Uuid>(conn)?;
+        Self::grant(conn, &board_ids, user_ids)
+    }
+
+    /// Gives every member of the workspace access to the board
+    pub fn grant_members(
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        board_id: Uuid,
+    ) -> QueryResult<()> {
+        let user_ids = workspace_members::table
+            .filter(workspace_members::workspace_id.eq(workspace_id))
+            .select(workspace_members::user_id)
+            .load::<Uuid>(conn)?;
+        Self::grant(conn, &[board_id], &user_ids)
+    }
+
+    /// Takes away the access the user had to the boards of the workspace through it,
+    /// they keep the boards they created or were invited to
+    pub fn revoke_boards(
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        user_id: Uuid,
+    ) -> QueryResult<()> {
+        let board_ids = boards::table
+            .filter(boards::workspace_id.eq(workspace_id))
+            .select(boards::id)
+            .load::<Uuid>(conn)?;
+        Self::revoke(conn, &board_ids, &[user_id])
+    }
+
+    /// Takes away the access the members of the workspace had to the board through it,
+    /// used when the board leaves the workspace
+    pub fn revoke_members(
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        board_id: Uuid,
+    ) -> QueryResult<()> {
+        let user_ids = workspace_members::table
+            .filter(workspace_members::workspace_id.eq(workspace_id))
+            .select(workspace_members::user_id)
+            .load::<Uuid>(conn)?;
+        Self::revoke(conn, &[board_id], &user_ids)
+    }
+
+    fn grant(conn: &mut PgConnection, board_ids: &[Uuid], user_ids: &[Uuid]) -> QueryResult<()> {
+        let relations = board_ids
+            .iter()
+            .flat_map(|board_id| {
+                user_ids.iter().map(|user_id| BoardUsersRelation {
+                    board_id: *board_id,
+                    user_id: *user_id,
+                    direct: false,
+                    via_workspace: true,
+                })
+            })
+            .collect::<Vec<BoardUsersRelation>>();
+        if relations.is_empty() {
+            return Ok(());
+        }
+        diesel::insert_into(board_users_relation::table)
+            .values(relations)
+            .on_conflict((
+                board_users_relation::board_id,
+                board_users_relation::user_id,
+            ))
+            .do_update()
+            .set(board_users_relation::via_workspace.eq(true))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn revoke(conn: &mut PgConnection, board_ids: &[Uuid], user_ids: &[Uuid]) -> QueryResult<()> {
+        diesel::delete(board_users_relation::table)
+            .filter(board_users_relation::board_id.eq_any(board_ids))
+            .filter(board_users_relation::user_id.eq_any(user_ids))
+            .filter(board_users_relation::direct.eq(false))
+            .execute(conn)?;
+        diesel::update(board_users_relation::table)
+            .filter(board_users_relation::board_id.eq_any(board_ids))
+            .filter(board_users_relation::user_id.eq_any(user_ids))
+            .set(board_users_relation::via_workspace.eq(false))
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -21,6 +21,36 @@ pub enum ApiErrorType {
     AlreadyFriends,
     WrongPassword,
     EmptyFields,
+    CardHasChildren,
+    InvalidParentCard,
+    InvalidRecurrenceRule,
+    InvalidDateRange,
+    InvalidLane,
+    InvalidCustomField,
+    InvalidFieldValue,
+    TimerAlreadyRunning,
+    NoRunningTimer,
+    InvalidTimeEntry,
+    InvalidSprint,
+    SprintAlreadyActive,
+    InvalidSprintState,
+    InvalidWorkspaceRole,
+    InsufficientWorkspaceRole,
+    WorkspaceNotEmpty,
+    PersonalWorkspace,
+    PreconditionRequired,
+    InvalidETag,
+    OperationExpired,
+    OperationAlreadyUndone,
+    OperationConflict,
+    InvalidRule,
+    InvalidWebhook,
+    InsufficientScope,
+    InvalidApiToken,
+    InvalidGroup,
+    InsufficientGroupRole,
+    InvalidMessage,
+    NotMessageSender,
     Other(String),
 }
 
@@ -43,6 +73,38 @@ impl ToString for ApiErrorType {
             ApiErrorType::AlreadyFriends => "Already Friends".to_string(),
             ApiErrorType::EmptyFields => "Empty Fields".to_string(),
             ApiErrorType::WrongPassword => "Wrong Password".to_string(),
+            ApiErrorType::CardHasChildren => "Card has children".to_string(),
+            ApiErrorType::InvalidParentCard => "Invalid parent card".to_string(),
+            ApiErrorType::InvalidRecurrenceRule => "Invalid recurrence rule".to_string(),
+            ApiErrorType::InvalidDateRange => "Invalid date range".to_string(),
+            ApiErrorType::InvalidLane => "Invalid lane".to_string(),
+            ApiErrorType::InvalidCustomField => "Invalid custom field".to_string(),
+            ApiErrorType::InvalidFieldValue => "Invalid field value".to_string(),
+            ApiErrorType::TimerAlreadyRunning => "Timer already running".to_string(),
+            ApiErrorType::NoRunningTimer => "No running timer".to_string(),
+            ApiErrorType::InvalidTimeEntry => "Invalid time entry".to_string(),
+            ApiErrorType::InvalidSprint => "Invalid sprint".to_string(),
+            ApiErrorType::SprintAlreadyActive => "Sprint already active".to_string(),
+            ApiErrorType::InvalidSprintState => "Invalid sprint state".to_string(),
+            ApiErrorType::InvalidWorkspaceRole => "Invalid workspace role".to_string(),
+            ApiErrorType::InsufficientWorkspaceRole => "Insufficient workspace role".to_string(),
+            ApiErrorType::WorkspaceNotEmpty => "Workspace is not empty".to_string(),
+            ApiErrorType::PersonalWorkspace => "Personal workspace".to_string(),
+            ApiErrorType::PreconditionRequired => "Precondition required".to_string(),
+            ApiErrorType::InvalidETag => "Invalid ETag".to_string(),
+            ApiErrorType::OperationExpired => "Operation can no longer be undone".to_string(),
+            ApiErrorType::OperationAlreadyUndone => "Operation already undone".to_string(),
+            ApiErrorType::OperationConflict => {
+                "Affected rows changed since the operation".to_string()
+            }
+            ApiErrorType::InvalidRule => "Invalid rule".to_string(),
+            ApiErrorType::InvalidWebhook => "Invalid webhook".to_string(),
+            ApiErrorType::InsufficientScope => "The API token lacks the scope".to_string(),
+            ApiErrorType::InvalidApiToken => "Invalid API token".to_string(),
+            ApiErrorType::InvalidGroup => "Invalid group".to_string(),
+            ApiErrorType::InsufficientGroupRole => "Insufficient group role".to_string(),
+            ApiErrorType::InvalidMessage => "Invalid message".to_string(),
+            ApiErrorType::NotMessageSender => "Only the sender can change the message".to_string(),
             ApiErrorType::Other(error) => error.to_string(),
         }
     }
@@ -63,6 +125,36 @@ impl From<String> for ApiErrorType {
             "Invalid User Id" => ApiErrorType::InvalidUserId,
             "Invalid Request" => ApiErrorType::InvalidRequest,
             "Already Friends" => ApiErrorType::AlreadyFriends,
+            "Card has children" => ApiErrorType::CardHasChildren,
+            "Invalid parent card" => ApiErrorType::InvalidParentCard,
+            "Invalid recurrence rule" => ApiErrorType::InvalidRecurrenceRule,
+            "Invalid date range" => ApiErrorType::InvalidDateRange,
+            "Invalid lane" => ApiErrorType::InvalidLane,
+            "Invalid custom field" => ApiErrorType::InvalidCustomField,
+            "Invalid field value" => ApiErrorType::InvalidFieldValue,
+            "Timer already running" => ApiErrorType::TimerAlreadyRunning,
+            "No running timer" => ApiErrorType::NoRunningTimer,
+            "Invalid time entry" => ApiErrorType::InvalidTimeEntry,
+            "Invalid sprint" => ApiErrorType::InvalidSprint,
+            "Sprint already active" => ApiErrorType::SprintAlreadyActive,
+            "Invalid sprint state" => ApiErrorType::InvalidSprintState,
+            "Invalid workspace role" => ApiErrorType::InvalidWorkspaceRole,
+            "Insufficient workspace role" => ApiErrorType::InsufficientWorkspaceRole,
+            "Workspace is not empty" => ApiErrorType::WorkspaceNotEmpty,
+            "Personal workspace" => ApiErrorType::PersonalWorkspace,
+            "Precondition required" => ApiErrorType::PreconditionRequired,
+            "Invalid ETag" => ApiErrorType::InvalidETag,
+            "Operation can no longer be undone" => ApiErrorType::OperationExpired,
+            "Operation already undone" => ApiErrorType::OperationAlreadyUndone,
+            "Affected rows changed since the operation" => ApiErrorType::OperationConflict,
+            "Invalid rule" => ApiErrorType::InvalidRule,
+            "Invalid webhook" => ApiErrorType::InvalidWebhook,
+            "The API token lacks the scope" => ApiErrorType::InsufficientScope,
+            "Invalid API token" => ApiErrorType::InvalidApiToken,
+            "Invalid group" => ApiErrorType::InvalidGroup,
+            "Insufficient group role" => ApiErrorType::InsufficientGroupRole,
+            "Invalid message" => ApiErrorType::InvalidMessage,
+            "Only the sender can change the message" => ApiErrorType::NotMessageSender,
             _ => ApiErrorType::Other(error),
         }
     }
@@ -124,6 +216,36 @@ impl ApiError {
             ApiErrorType::UserNotFound => Status::NotFound,
             ApiErrorType::YouDoNotOwnThisFile => Status::Forbidden,
             ApiErrorType::FailedToParseUUID => Status::BadRequest,
+            ApiErrorType::CardHasChildren => Status::Conflict,
+            ApiErrorType::InvalidParentCard => Status::BadRequest,
+            ApiErrorType::InvalidRecurrenceRule => Status::BadRequest,
+            ApiErrorType::InvalidDateRange => Status::BadRequest,
+            ApiErrorType::InvalidLane => Status::BadRequest,
+            ApiErrorType::InvalidCustomField => Status::BadRequest,
+            ApiErrorType::InvalidFieldValue => Status::BadRequest,
+            ApiErrorType::TimerAlreadyRunning => Status::Conflict,
+            ApiErrorType::NoRunningTimer => Status::NotFound,
+            ApiErrorType::InvalidTimeEntry => Status::BadRequest,
+            ApiErrorType::InvalidSprint => Status::BadRequest,
+            ApiErrorType::SprintAlreadyActive => Status::Conflict,
+            ApiErrorType::InvalidSprintState => Status::Conflict,
+            ApiErrorType::InvalidWorkspaceRole => Status::BadRequest,
+            ApiErrorType::InsufficientWorkspaceRole => Status::Forbidden,
+            ApiErrorType::WorkspaceNotEmpty => Status::Conflict,
+            ApiErrorType::PersonalWorkspace => Status::Conflict,
+            ApiErrorType::PreconditionRequired => Status::PreconditionRequired,
+            ApiErrorType::InvalidETag => Status::BadRequest,
+            ApiErrorType::OperationExpired => Status::Gone,
+            ApiErrorType::OperationAlreadyUndone => Status::Conflict,
+            ApiErrorType::OperationConflict => Status::Conflict,
+            ApiErrorType::InvalidRule => Status::BadRequest,
+            ApiErrorType::InvalidWebhook => Status::BadRequest,
+            ApiErrorType::InsufficientScope => Status::Forbidden,
+            ApiErrorType::InvalidApiToken => Status::BadRequest,
+            ApiErrorType::InvalidGroup => Status::BadRequest,
+            ApiErrorType::InsufficientGroupRole => Status::Forbidden,
+            ApiErrorType::InvalidMessage => Status::BadRequest,
+            ApiErrorType::NotMessageSender => Status::Forbidden,
             _ => Status::InternalServerError,
         }
     }
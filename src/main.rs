@@ -2,6 +2,7 @@ use catchers::Catcher;
 use config::from_env;
 use database::Db;
 use routes::AuthorizationRoutes;
+use scheduler::Scheduler;
 
 mod catchers;
 mod config;
@@ -11,6 +12,7 @@ mod models;
 mod routes;
 mod schema;
 mod jwt;
+mod scheduler;
 
 #[macro_use]
 extern crate rocket;
@@ -26,6 +28,7 @@ async fn main() -> Result<(), rocket::Error> {
         .mount_auth_routes()
         .mount_board_routes()
         .manage_state()
+        .attach_schedulers()
         .mount_metrics()
         .launch()
         .await?;
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One step in the history of a card
+///
+/// `from_column_id` is `None` when the card was created and `to_column_id`
+/// is `None` when it was deleted
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::card_movements)]
+pub struct CardMovement {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub card_id: Uuid,
+    pub from_column_id: Option<Uuid>,
+    pub to_column_id: Option<Uuid>,
+    pub moved_by: Option<Uuid>,
+    pub moved_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyThroughput {
+    pub date: NaiveDate,
+    pub created: i64,
+    pub completed: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnTimes {
+    pub column_id: Uuid,
+    pub name: Option<String>,
+    /// Average time, in hours, cards stayed in the column
+    pub average_cycle_hours: Option<f64>,
+    /// Average time, in hours, between the creation of a card and its arrival in the column
+    pub average_lead_hours: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlowPoint {
+    pub date: NaiveDate,
+    /// Number of cards in each column at the end of the day
+    pub columns: HashMap<Uuid, i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BoardAnalytics {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub throughput: Vec<DailyThroughput>,
+    pub columns: Vec<ColumnTimes>,
+    pub cumulative_flow: Vec<FlowPoint>,
+}
+
+/// A stretch of time a card spent in a column, `left_at` is `None` while it is still there
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnStay {
+    pub column_id: Uuid,
+    /// `None` once the column has been deleted
+    pub column_name: Option<String>,
+    pub entered_at: NaiveDateTime,
+    pub left_at: Option<NaiveDateTime>,
+    /// Who moved the card into the column
+    pub moved_by: Option<Uuid>,
+    pub hours: f64,
+}
+
+/// Time a card spent in a column over all its visits
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnTotal {
+    pub column_id: Uuid,
+    pub column_name: Option<String>,
+    pub visits: i64,
+    pub hours: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CardTimeline {
+    pub card_id: Uuid,
+    /// `None` when the card predates the movement history
+    pub created_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub stays: Vec<ColumnStay>,
+    /// In the order the columns were first entered
+    pub columns: Vec<ColumnTotal>,
+}
+
+/// A card sitting in the same column for too long
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaleCard {
+    pub card_id: Uuid,
+    pub name: String,
+    pub column_id: Uuid,
+    pub column_name: Option<String>,
+    /// `None` when the card has not moved since the history started being recorded
+    pub entered_at: Option<NaiveDateTime>,
+    pub idle_days: Option<f64>,
+}
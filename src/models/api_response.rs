@@ -7,10 +7,13 @@ use rocket::{
 
 use crate::errors::{ApiError, ApiErrorType};
 
+use super::concurrency::etag;
+
 #[derive(Debug)]
 pub struct ApiResponse<T = ApiError> {
     status: Status,
     data: T,
+    etag: Option<String>,
 }
 
 impl ApiResponse {
@@ -23,6 +26,7 @@ impl ApiResponse {
         ApiResponse {
             status: error.status(),
             data: error,
+            etag: None,
         }
     }
     pub fn from_error_type(error_type: ApiErrorType) -> Self {
@@ -35,6 +39,7 @@ impl From<ApiError> for ApiResponse {
         ApiResponse {
             status: error.status(),
             data: error,
+            etag: None,
         }
     }
 }
@@ -47,6 +52,23 @@ where
         ApiResponse::<T> {
             status: Status::Ok,
             data,
+            etag: None,
+        }
+    }
+
+    /// Sends the version of the returned row as the `ETag` header
+    pub fn with_etag(mut self, version: i32) -> ApiResponse<T> {
+        self.etag = Some(etag(version));
+        self
+    }
+
+    /// Answers a conditional update whose `If-Match` didn't match the current
+    /// version, `data` is the current state of the row
+    pub fn precondition_failed(data: T, version: i32) -> ApiResponse<T> {
+        ApiResponse::<T> {
+            status: Status::PreconditionFailed,
+            data,
+            etag: Some(etag(version)),
         }
     }
 }
@@ -57,9 +79,11 @@ where
 {
     fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
         let json_data = json!(self.data);
-        response::Response::build_from(json_data.respond_to(&req)?)
-            .status(self.status)
-            .header(ContentType::JSON)
-            .ok()
+        let mut response = response::Response::build_from(json_data.respond_to(&req)?);
+        response.status(self.status).header(ContentType::JSON);
+        if let Some(etag) = self.etag {
+            response.raw_header("ETag", etag);
+        }
+        response.ok()
     }
 }
@@ -0,0 +1,150 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use rocket::http::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Marks API tokens apart from session JWTs in the `Authorization` header
+pub const TOKEN_PREFIX: &str = "pat_";
+
+/// What an API token is allowed to do, a `write` scope also grants the matching `read`
+/// and `boards:admin` grants `boards:write`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "boards:read")]
+    BoardsRead,
+    #[serde(rename = "boards:write")]
+    BoardsWrite,
+    /// Deleting boards and managing who can see them and where their events are sent
+    #[serde(rename = "boards:admin")]
+    BoardsAdmin,
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+}
+
+impl Scope {
+    /// Returns the scope a request needs, `None` for routes that only take a session,
+    /// like managing tokens, chat, friends and the account itself
+    pub fn required_for(method: Method, path: &str) -> Option<Scope> {
+        let read = method == Method::Get;
+        let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+        let first = *segments.first()?;
+        match (first, path) {
+            ("boards" | "workspaces", _) if !read && Self::is_admin(&method, &segments) => {
+                Some(Scope::BoardsAdmin)
+            }
+            ("boards" | "workspaces" | "time", _) => match read {
+                true => Some(Scope::BoardsRead),
+                false => Some(Scope::BoardsWrite),
+            },
+            ("uploads", _) => Some(Scope::FilesRead),
+            ("api", path) if path.starts_with("/api/file") => match read {
+                true => Some(Scope::FilesRead),
+                false => Some(Scope::FilesWrite),
+            },
+            // Looking users up, to add them to boards
+            ("api", path) if read && path.starts_with("/api/user") => Some(Scope::BoardsRead),
+            _ => None,
+        }
+    }
+
+    /// Whether a change is board administration: deleting a board, moving it between
+    /// workspaces, managing workspaces and their members, collaborators or webhooks
+    fn is_admin(method: &Method, segments: &[&str]) -> bool {
+        match segments {
+            ["workspaces", ..] => true,
+            ["boards", _] => *method == Method::Delete,
+            ["boards", _, "workspace" | "collaborators" | "webhooks", ..] => true,
+            _ => false,
+        }
+    }
+
+    pub fn grants(&self, required: Scope) -> bool {
+        *self == required
+            || matches!(
+                (self, required),
+                (Scope::BoardsWrite, Scope::BoardsRead)
+                    | (Scope::BoardsAdmin, Scope::BoardsWrite | Scope::BoardsRead)
+                    | (Scope::FilesWrite, Scope::FilesRead)
+            )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::api_tokens)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub prefix: String,
+    pub scopes: Value,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> Vec<Scope> {
+        serde_json::from_value(self.scopes.clone()).unwrap_or_default()
+    }
+
+    pub fn allows(&self, required: Scope) -> bool {
+        self.scopes().iter().any(|scope| scope.grants(required))
+    }
+
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Returns the hex SHA-256 of the token, the only form it is stored in
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// A token as listed to its owner, without its hash
+#[derive(Debug, Serialize)]
+pub struct PubApiToken {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<ApiToken> for PubApiToken {
+    fn from(token: ApiToken) -> Self {
+        PubApiToken {
+            id: token.id,
+            scopes: token.scopes(),
+            name: token.name,
+            prefix: token.prefix,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+/// A token that was just created, the only time the token itself is returned
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub info: PubApiToken,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Never expires when left out
+    pub expires_at: Option<NaiveDateTime>,
+}
@@ -6,9 +6,13 @@ use rocket::request::*;
 
 use crate::errors::ApiErrorType;
 use crate::jwt::Token;
-use crate::{database::Db, schema::users};
+use crate::{
+    database::{api_token_queries::ApiTokenQueries, Db},
+    schema::users,
+};
 
 use super::api_response::ApiResponse;
+use super::api_token::{Scope, TOKEN_PREFIX};
 use super::user::User;
 
 #[derive(Debug, PartialEq)]
@@ -40,6 +44,26 @@ impl AuthResult {
     }
 }
 
+impl AuthResult {
+    /// Accepts a personal API token if it has the scope the requested route needs
+    async fn from_api_token(req: &Request<'_>, token: String) -> AuthResult {
+        let Some(required) = Scope::required_for(req.method(), req.uri().path().as_str()) else {
+            return AuthResult::Failure(ApiErrorType::InsufficientScope);
+        };
+        let db = req.guard::<Db>().await.unwrap();
+        let api_token = db
+            .run(move |conn| ApiTokenQueries::authenticate(conn, &token))
+            .await;
+        match api_token {
+            Ok(Some(api_token)) if api_token.allows(required) => AuthResult::Success(Auth {
+                id: api_token.user_id,
+            }),
+            Ok(Some(_)) => AuthResult::Failure(ApiErrorType::InsufficientScope),
+            _ => AuthResult::Failure(ApiErrorType::InvalidToken),
+        }
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for AuthResult {
     type Error = Infallible;
@@ -53,6 +77,9 @@ impl<'r> FromRequest<'r> for AuthResult {
                 } else {
                     return Outcome::Success(AuthResult::Failure(ApiErrorType::InvalidToken));
                 };
+                if token.starts_with(TOKEN_PREFIX) {
+                    return Outcome::Success(Self::from_api_token(req, token.to_string()).await);
+                }
                 let user_data = match Token::decode_token(token.to_string()) {
                     Ok(token) => token.claims.user,
                     Err(_) => {
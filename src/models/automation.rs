@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// What makes a rule run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    /// A card is created, in the given column or anywhere on the board
+    CardCreated {
+        #[serde(default)]
+        column_id: Option<Uuid>,
+    },
+    /// A card is moved into the column from another one
+    CardEnteredColumn { column_id: Uuid },
+    /// The date a card holds in a `date` custom field is in the past, checked every minute
+    DatePassed { field_id: Uuid },
+}
+
+/// Something the card has to satisfy for the rule to apply, evaluated after the trigger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    InColumn {
+        column_id: Uuid,
+    },
+    /// `null` matches cards outside of any lane
+    InLane {
+        lane_id: Option<Uuid>,
+    },
+    FieldEquals {
+        field_id: Uuid,
+        value: Value,
+    },
+    Assigned {
+        assigned: bool,
+    },
+    Completed {
+        completed: bool,
+    },
+    /// Case insensitive
+    NameContains {
+        text: String,
+    },
+}
+
+/// A change the rule makes to the card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    SetCompleted {
+        completed: bool,
+    },
+    Assign {
+        user_id: Uuid,
+    },
+    Unassign,
+    /// `null` clears the value
+    SetField {
+        field_id: Uuid,
+        value: Value,
+    },
+    /// Appends the card to the end of the column
+    MoveToColumn {
+        column_id: Uuid,
+    },
+}
+
+/// What happened to a card, matched against the triggers of the board's rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleEvent {
+    CardCreated { column_id: Uuid },
+    CardEnteredColumn { column_id: Uuid },
+    DatePassed { field_id: Uuid },
+}
+
+impl RuleEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleEvent::CardCreated { .. } => "card_created",
+            RuleEvent::CardEnteredColumn { .. } => "card_entered_column",
+            RuleEvent::DatePassed { .. } => "date_passed",
+        }
+    }
+}
+
+impl Trigger {
+    pub fn fires_on(&self, event: &RuleEvent) -> bool {
+        match (self, event) {
+            (Trigger::CardCreated { column_id }, RuleEvent::CardCreated { column_id: created }) => {
+                column_id.is_none_or(|column_id| column_id == *created)
+            }
+            (
+                Trigger::CardEnteredColumn { column_id },
+                RuleEvent::CardEnteredColumn { column_id: entered },
+            ) => column_id == entered,
+            (Trigger::DatePassed { field_id }, RuleEvent::DatePassed { field_id: passed }) => {
+                field_id == passed
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the card is in a state the trigger could have fired for, used by dry runs
+    pub fn could_fire_for(&self, card: &RuleCard, today: NaiveDate) -> bool {
+        match self {
+            Trigger::CardCreated { column_id } => {
+                column_id.is_none_or(|column_id| column_id == card.column_id)
+            }
+            Trigger::CardEnteredColumn { column_id } => *column_id == card.column_id,
+            Trigger::DatePassed { field_id } => card.date_passed(*field_id, today),
+        }
+    }
+}
+
+/// The state of a card conditions are evaluated against
+#[derive(Debug, Clone)]
+pub struct RuleCard {
+    pub id: Uuid,
+    pub name: String,
+    pub column_id: Uuid,
+    pub lane_id: Option<Uuid>,
+    pub assignee_id: Option<Uuid>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub fields: HashMap<Uuid, Value>,
+}
+
+impl RuleCard {
+    /// Whether the date the card holds in the field is before `today`
+    pub fn date_passed(&self, field_id: Uuid, today: NaiveDate) -> bool {
+        self.fields
+            .get(&field_id)
+            .and_then(Value::as_str)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .is_some_and(|date| date < today)
+    }
+}
+
+impl Condition {
+    pub fn matches(&self, card: &RuleCard) -> bool {
+        match self {
+            Condition::InColumn { column_id } => card.column_id == *column_id,
+            Condition::InLane { lane_id } => card.lane_id == *lane_id,
+            Condition::FieldEquals { field_id, value } => card.fields.get(field_id) == Some(value),
+            Condition::Assigned { assigned } => card.assignee_id.is_some() == *assigned,
+            Condition::Completed { completed } => card.completed_at.is_some() == *completed,
+            Condition::NameContains { text } => {
+                card.name.to_lowercase().contains(&text.to_lowercase())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::board_rules)]
+pub struct BoardRule {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub name: String,
+    pub trigger: Value,
+    pub conditions: Value,
+    pub actions: Value,
+    pub enabled: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+impl BoardRule {
+    /// Parses the stored definition, `None` if it no longer deserializes
+    pub fn definition(&self) -> Option<RuleDefinition> {
+        Some(RuleDefinition {
+            trigger: serde_json::from_value(self.trigger.clone()).ok()?,
+            conditions: serde_json::from_value(self.conditions.clone()).ok()?,
+            actions: serde_json::from_value(self.actions.clone()).ok()?,
+        })
+    }
+}
+
+/// The trigger, conditions and actions of a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleDefinition {
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+impl RuleDefinition {
+    pub fn matches(&self, card: &RuleCard) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(card))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewRule {
+    pub name: String,
+    #[serde(flatten)]
+    pub definition: RuleDefinition,
+    #[serde(default = "NewRule::default_enabled")]
+    pub enabled: bool,
+}
+
+impl NewRule {
+    fn default_enabled() -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionStatus {
+    Applied,
+    Failed,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionStatus::Applied => "applied",
+            ExecutionStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::rule_executions)]
+pub struct RuleExecution {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub board_id: Uuid,
+    pub card_id: Uuid,
+    pub event: String,
+    pub status: String,
+    pub context: Option<Value>,
+    pub detail: Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// A card a dry run found the rule would apply to
+#[derive(Debug, Serialize)]
+pub struct DryRunMatch {
+    pub card_id: Uuid,
+    pub card_name: String,
+    pub actions: Vec<Action>,
+}
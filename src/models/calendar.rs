@@ -0,0 +1,180 @@
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Lines of an iCalendar file are folded past this many bytes
+const MAX_LINE: usize = 75;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::calendar_feeds)]
+pub struct CalendarFeed {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub board_id: Option<Uuid>,
+    pub token: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A feed as shown to its owner, `url` is relative to the server
+#[derive(Debug, Serialize)]
+pub struct PubCalendarFeed {
+    pub id: Uuid,
+    pub board_id: Option<Uuid>,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<CalendarFeed> for PubCalendarFeed {
+    fn from(feed: CalendarFeed) -> Self {
+        PubCalendarFeed {
+            id: feed.id,
+            board_id: feed.board_id,
+            url: format!("/calendar/{}.ics", feed.token),
+            created_at: feed.created_at,
+        }
+    }
+}
+
+/// A card holding a date in one of its `date` custom fields
+#[derive(Debug, Clone)]
+pub struct CalendarEntry {
+    pub card_id: Uuid,
+    pub card_name: String,
+    pub description: Option<String>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub board_name: String,
+    pub column_name: Option<String>,
+    pub field_id: Uuid,
+    pub field_name: String,
+    pub date: NaiveDate,
+}
+
+/// How entries are written, calendar apps show `VEVENT`s while task apps read `VTODO`s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarComponent {
+    Event,
+    Todo,
+}
+
+impl CalendarComponent {
+    pub fn from_param(kind: Option<&str>) -> Self {
+        match kind {
+            Some("todo") => CalendarComponent::Todo,
+            _ => CalendarComponent::Event,
+        }
+    }
+}
+
+/// Escapes a TEXT value (RFC 5545, 3.3.11)
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends the content line, folded so no line is longer than 75 bytes (RFC 5545, 3.1)
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn utc(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// Writes the entries as an iCalendar file
+///
+/// Dates are all-day values, which calendar apps keep on the same day whatever their
+/// timezone. Timestamps are stored in UTC and written as such, with the `Z` suffix
+pub fn render(
+    name: &str,
+    entries: &[CalendarEntry],
+    component: CalendarComponent,
+    now: NaiveDateTime,
+) -> String {
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//board-app//Card dates//EN");
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "METHOD:PUBLISH");
+    push_line(&mut ics, &format!("X-WR-CALNAME:{}", escape(name)));
+
+    for entry in entries {
+        let summary = format!("{} ({})", entry.card_name, entry.field_name);
+        let mut description = format!("Board: {}", entry.board_name);
+        if let Some(column_name) = &entry.column_name {
+            description.push_str(&format!("\nColumn: {}", column_name));
+        }
+        if let Some(card_description) = entry.description.as_deref().filter(|d| !d.is_empty()) {
+            description.push_str(&format!("\n\n{}", card_description));
+        }
+
+        let (begin, end) = match component {
+            CalendarComponent::Event => ("BEGIN:VEVENT", "END:VEVENT"),
+            CalendarComponent::Todo => ("BEGIN:VTODO", "END:VTODO"),
+        };
+        push_line(&mut ics, begin);
+        push_line(
+            &mut ics,
+            &format!("UID:{}-{}@board-app", entry.card_id, entry.field_id),
+        );
+        push_line(&mut ics, &format!("DTSTAMP:{}", utc(now)));
+        match component {
+            CalendarComponent::Event => {
+                push_line(
+                    &mut ics,
+                    &format!("DTSTART;VALUE=DATE:{}", date(entry.date)),
+                );
+                let next_day = entry
+                    .date
+                    .checked_add_days(Days::new(1))
+                    .unwrap_or(entry.date);
+                push_line(&mut ics, &format!("DTEND;VALUE=DATE:{}", date(next_day)));
+                push_line(&mut ics, "TRANSP:TRANSPARENT");
+            }
+            CalendarComponent::Todo => {
+                push_line(&mut ics, &format!("DUE;VALUE=DATE:{}", date(entry.date)));
+                match entry.completed_at {
+                    Some(completed_at) => {
+                        push_line(&mut ics, "STATUS:COMPLETED");
+                        push_line(&mut ics, &format!("COMPLETED:{}", utc(completed_at)));
+                    }
+                    None => push_line(&mut ics, "STATUS:NEEDS-ACTION"),
+                }
+            }
+        }
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&summary)));
+        push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&description)));
+        push_line(
+            &mut ics,
+            &format!("CATEGORIES:{}", escape(&entry.board_name)),
+        );
+        push_line(&mut ics, end);
+    }
+
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
@@ -0,0 +1,77 @@
+use std::convert::Infallible;
+
+use rocket::request::*;
+
+use crate::errors::ApiErrorType;
+
+use super::api_response::ApiResponse;
+
+/// Formats the version of a row as a strong entity tag
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// The `If-Match` header of a conditional update
+#[derive(Debug, PartialEq)]
+pub enum IfMatch {
+    Success(Precondition),
+    Failure(ApiErrorType),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Precondition {
+    /// The client edited the row at one of these versions
+    Versions(Vec<i32>),
+    /// `If-Match: *`, the update applies to whatever version is current
+    Any,
+}
+
+impl Precondition {
+    /// Whether the update may be applied to the row at its `current` version
+    pub fn matches(&self, current: i32) -> bool {
+        match self {
+            Precondition::Versions(versions) => versions.contains(&current),
+            Precondition::Any => true,
+        }
+    }
+}
+
+impl IfMatch {
+    pub fn unpack(self) -> Result<Precondition, ApiResponse> {
+        match self {
+            IfMatch::Success(precondition) => Ok(precondition),
+            IfMatch::Failure(error) => Err(ApiResponse::from_error_type(error)),
+        }
+    }
+
+    fn parse(header: &str) -> IfMatch {
+        if header.trim() == "*" {
+            return IfMatch::Success(Precondition::Any);
+        }
+        // Weak tags compare like strong ones, the version is all there is to compare
+        let versions = header
+            .split(',')
+            .map(|tag| {
+                let tag = tag.trim();
+                let tag = tag.strip_prefix("W/").unwrap_or(tag);
+                tag.trim_matches('"').parse::<i32>()
+            })
+            .collect::<Result<Vec<i32>, _>>();
+        match versions {
+            Ok(versions) => IfMatch::Success(Precondition::Versions(versions)),
+            Err(_) => IfMatch::Failure(ApiErrorType::InvalidETag),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.headers().get_one("If-Match") {
+            None => Outcome::Success(IfMatch::Failure(ApiErrorType::PreconditionRequired)),
+            Some(header) => Outcome::Success(IfMatch::parse(header)),
+        }
+    }
+}
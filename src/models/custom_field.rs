@@ -0,0 +1,130 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use chrono::NaiveDate;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, ApiErrorType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    Number,
+    Date,
+    SingleSelect,
+    Checkbox,
+}
+
+impl FieldKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::Number => "number",
+            FieldKind::Date => "date",
+            FieldKind::SingleSelect => "single_select",
+            FieldKind::Checkbox => "checkbox",
+        }
+    }
+
+    /// Checks that `value` can be stored in a field of this kind
+    ///
+    /// Dates are `YYYY-MM-DD` strings and single-select values must be one of `options`
+    pub fn validate(&self, value: &Value, options: &[String]) -> Result<(), ApiError> {
+        let valid = match (self, value) {
+            (FieldKind::Text, Value::String(_)) => true,
+            (FieldKind::Number, Value::Number(_)) => true,
+            (FieldKind::Date, Value::String(date)) => {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+            }
+            (FieldKind::SingleSelect, Value::String(option)) => options.contains(option),
+            (FieldKind::Checkbox, Value::Bool(_)) => true,
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(ApiError::from_type(ApiErrorType::InvalidFieldValue))
+        }
+    }
+}
+
+impl FromStr for FieldKind {
+    type Err = ApiError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "text" => Ok(FieldKind::Text),
+            "number" => Ok(FieldKind::Number),
+            "date" => Ok(FieldKind::Date),
+            "single_select" => Ok(FieldKind::SingleSelect),
+            "checkbox" => Ok(FieldKind::Checkbox),
+            _ => Err(ApiError::from_type(ApiErrorType::InvalidCustomField)),
+        }
+    }
+}
+
+/// Orders two values of the same field, numbers numerically and everything else
+/// by its natural order, which for `YYYY-MM-DD` dates is chronological
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::board_custom_fields)]
+pub struct CustomField {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub name: String,
+    pub kind: String,
+    pub options: Value,
+    pub position: i32,
+}
+
+impl CustomField {
+    pub fn kind(&self) -> Result<FieldKind, ApiError> {
+        self.kind.parse()
+    }
+
+    pub fn options(&self) -> Vec<String> {
+        serde_json::from_value(self.options.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewCustomField {
+    pub name: String,
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub position: i32,
+}
+
+/// The kind of a field cannot change once cards hold values for it
+#[derive(Serialize, Deserialize)]
+pub struct CustomFieldInfo {
+    pub name: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub position: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::card_field_values)]
+pub struct CardFieldValue {
+    pub card_id: Uuid,
+    pub field_id: Uuid,
+    pub value: Value,
+}
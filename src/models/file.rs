@@ -3,7 +3,7 @@ use rocket::{fs::TempFile, tokio::io::AsyncReadExt};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Insertable, Queryable, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::files)]
 pub struct UploadedFile {
     pub id: uuid::Uuid,
@@ -20,6 +20,14 @@ pub struct UploadRequest<'r> {
 }
 
 impl UploadedFile {
+    /// Where the file lives on disk
+    pub fn path(&self) -> String {
+        if self.private {
+            format!("tmp/{}/{}", self.user_id, self.name)
+        } else {
+            format!("tmp/{}", self.name)
+        }
+    }
     pub async fn write_file_to_disk(&self, file: &TempFile<'_>) {
         let mut file = file.open().await.unwrap();
         let mut buf = Vec::new();
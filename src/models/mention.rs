@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::mentions)]
+pub struct Mention {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub author_id: Uuid,
+    pub card_id: Option<Uuid>,
+    pub message_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PubMention {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Extracts the usernames written as `@username`, in order and without duplicates
+///
+/// An `@` only starts a mention at the beginning of the text or after a character
+/// that cannot be part of a username, so e-mail addresses are not picked up
+pub fn parse_mentions(text: &str) -> Vec<String> {
+    let mut usernames: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let starts_mention = c == '@' && !previous.is_some_and(is_username_char);
+        previous = Some(c);
+        if !starts_mention {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(idx, next)) = chars.peek() {
+            if !is_username_char(next) {
+                break;
+            }
+            end = idx + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+        // Punctuation closing a sentence is not part of the username
+        let username = text[start + 1..end].trim_end_matches(['.', '-']);
+        if !username.is_empty() && !usernames.iter().any(|name| name == username) {
+            usernames.push(username.to_string());
+        }
+    }
+    usernames
+}
@@ -1,8 +1,14 @@
+use std::str::FromStr;
+
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use diesel::{Insertable, Queryable, QueryableByName, Selectable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::errors::{ApiError, ApiErrorType};
+
+use super::user::PubUser;
+
 #[derive(
     Debug,
     Clone,
@@ -26,14 +32,32 @@ pub struct ChatMessageDTO {
     pub updated_at: NaiveDateTime,
 }
 
+impl ChatMessageDTO {
+    /// What is left of a deleted message, its content and attachment are not shown anymore
+    pub fn tombstone(self) -> Self {
+        ChatMessageDTO {
+            content: String::new(),
+            file_id: None,
+            ..self
+        }
+    }
+
+    /// Returns the message as members get to see it
+    pub fn visible(self) -> Self {
+        if self.deleted {
+            self.tombstone()
+        } else {
+            self
+        }
+    }
+}
+
+/// The server stamps the time, histories and unread counts are ordered on it
 impl From<ClientMessage> for ChatMessageDTO {
     fn from(value: ClientMessage) -> Self {
         let sender_id = Uuid::parse_str(&value.sender_id).unwrap_or_default();
         let conversation_id = Uuid::parse_str(&value.conversation_id).unwrap_or_default();
-        let timestamp = Utc
-            .timestamp_millis_opt(value.created_at)
-            .unwrap()
-            .naive_utc();
+        let timestamp = Utc::now().naive_utc();
 
         ChatMessageDTO {
             id: Uuid::new_v4(),
@@ -48,19 +72,209 @@ impl From<ClientMessage> for ChatMessageDTO {
     }
 }
 
+/// Where a page of history starts, parsed from a message id, a timestamp in milliseconds
+/// like `ClientMessage::created_at`, or a `YYYY-MM-DDTHH:MM:SS` UTC time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageCursor {
+    Message(Uuid),
+    Time(NaiveDateTime),
+}
+
+impl FromStr for MessageCursor {
+    type Err = ApiError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        if let Ok(message_id) = Uuid::try_parse(cursor) {
+            return Ok(MessageCursor::Message(message_id));
+        }
+        let time = match cursor.parse::<i64>() {
+            Ok(millis) => Utc
+                .timestamp_millis_opt(millis)
+                .single()
+                .map(|time| time.naive_utc()),
+            Err(_) => NaiveDateTime::parse_from_str(cursor, "%Y-%m-%dT%H:%M:%S%.f").ok(),
+        };
+        time.map(MessageCursor::Time)
+            .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidRequest))
+    }
+}
+
+/// A page of the history of a conversation, oldest message first
+///
+/// `next_cursor` is `None` once there is nothing more in the direction of the request
+#[derive(Serialize, Debug)]
+pub struct MessagePage {
+    pub messages: Vec<ChatMessageDTO>,
+    pub next_cursor: Option<Uuid>,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct ClientMessage {
     pub content: String,
     pub sender_id: String,
     pub conversation_id: String,
-    pub created_at: i64,
     pub file_id: Option<Uuid>,
 }
 
+/// Changes to an existing message sent over the chat socket instead of a `ClientMessage`
+/// ```json
+/// { "action": "edit", "message_id": <message_id>, "content": <new_content> }
+/// { "action": "delete", "message_id": <message_id> }
+/// { "action": "read", "message_id": <message_id> }
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientAction {
+    Edit {
+        message_id: Uuid,
+        content: String,
+    },
+    Delete {
+        message_id: Uuid,
+    },
+    /// Marks the conversation of the message as read up to it
+    Read {
+        message_id: Uuid,
+    },
+}
+
+/// How far a user has read a conversation, also sent to the other members as a read receipt
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::conversation_reads)]
+pub struct ReadReceipt {
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    /// The last message read
+    pub message_id: Uuid,
+    pub message_created_at: NaiveDateTime,
+    pub read_at: NaiveDateTime,
+}
+
+/// A conversation of the user as shown in their list of chats
+#[derive(Serialize, Debug)]
+pub struct ConversationSummary {
+    pub conversation: Conversation,
+    pub last_message: Option<ChatMessageDTO>,
+    /// Messages from other members sent after `last_read_message_id`, deleted ones aside
+    pub unread: i64,
+    pub last_read_message_id: Option<Uuid>,
+}
+
+/// A previous version of an edited message, replaced at `edited_at`
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::chat_message_edits)]
+pub struct ChatMessageEdit {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub content: String,
+    pub edited_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageUpdate {
+    pub content: String,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug, Queryable, QueryableByName, Selectable)]
 #[diesel(table_name = crate::schema::conversations)]
 pub struct Conversation {
     pub id: Uuid,
-    pub member_one: Uuid,
-    pub member_two: Uuid,
-}
\ No newline at end of file
+    /// `None` for the chat of a board
+    pub member_one: Option<Uuid>,
+    pub member_two: Option<Uuid>,
+    /// Set for the chat of a board, whose members are the members of the board
+    pub board_id: Option<Uuid>,
+    /// Set for groups, whose members are listed in `conversation_members`
+    pub name: Option<String>,
+}
+
+impl Conversation {
+    pub fn is_group(&self) -> bool {
+        self.name.is_some()
+    }
+}
+
+/// The chat of a board along with who can currently read it
+#[derive(Serialize, Debug)]
+pub struct BoardChat {
+    pub conversation: Conversation,
+    pub members: Vec<PubUser>,
+}
+
+/// What a member is allowed to do in a group, ordered from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationRole {
+    Member,
+    Admin,
+}
+
+impl ConversationRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversationRole::Member => "member",
+            ConversationRole::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for ConversationRole {
+    type Err = ApiError;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "member" => Ok(ConversationRole::Member),
+            "admin" => Ok(ConversationRole::Admin),
+            _ => Err(ApiError::from_type(ApiErrorType::InvalidGroup)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::conversation_members)]
+pub struct ConversationMember {
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub joined_at: NaiveDateTime,
+}
+
+impl ConversationMember {
+    pub fn role(&self) -> ConversationRole {
+        self.role.parse().unwrap_or(ConversationRole::Member)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewGroup {
+    pub name: String,
+    /// The creator joins as `admin` and does not need to be listed
+    pub member_ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GroupUpdate {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewGroupMember {
+    pub user_id: Uuid,
+    /// `member` when left out
+    pub role: Option<ConversationRole>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PubConversationMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: ConversationRole,
+    pub joined_at: NaiveDateTime,
+}
+
+/// A group along with its members
+#[derive(Serialize, Debug)]
+pub struct GroupChat {
+    pub conversation: Conversation,
+    pub members: Vec<PubConversationMember>,
+}
@@ -1,31 +1,54 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
 use diesel::{Insertable, Queryable, QueryableByName, Selectable};
 use rocket::fs::TempFile;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::schema::column_card;
+use crate::schema::{board_column, column_card};
+pub mod analytics;
 pub mod api_response;
+pub mod api_token;
 pub mod auth;
+pub mod automation;
+pub mod calendar;
+pub mod concurrency;
+pub mod custom_field;
 pub mod file;
 pub mod friends;
+pub mod mention;
 pub mod messages;
+pub mod notification;
+pub mod operation;
+pub mod presence;
+pub mod recurrence;
+pub mod revision;
+pub mod sprint;
+pub mod time_entry;
 pub mod user;
+pub mod webhook;
+pub mod workspace;
 pub mod ws_state;
 
 #[derive(Serialize, Deserialize)]
 pub struct PubBoard {
     pub id: uuid::Uuid,
     pub name: String,
+    pub workspace_id: uuid::Uuid,
 }
 #[derive(Serialize, Deserialize)]
 pub struct NewBoard {
     pub name: String,
+    /// The personal workspace of the creator when left out
+    pub workspace_id: Option<uuid::Uuid>,
 }
 #[derive(Serialize, Deserialize)]
 pub struct BoardInfo {
     pub id: uuid::Uuid,
     pub name: String,
     pub columns: Vec<PubColumn>,
+    pub lanes: Vec<PubLane>,
     pub cards: Vec<PubCard>,
 }
 #[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
@@ -34,6 +57,7 @@ pub struct Board {
     pub id: Option<uuid::Uuid>,
     pub name: String,
     pub creator_id: uuid::Uuid,
+    pub workspace_id: uuid::Uuid,
 }
 
 #[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
@@ -41,6 +65,10 @@ pub struct Board {
 pub struct BoardUsersRelation {
     pub board_id: uuid::Uuid,
     pub user_id: uuid::Uuid,
+    /// Invited to the board itself, or its creator
+    pub direct: bool,
+    /// Member of the workspace of the board
+    pub via_workspace: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,13 +76,35 @@ pub struct PubColumn {
     pub id: uuid::Uuid,
     pub name: Option<String>,
     pub position: i32,
+    pub version: i32,
 }
 #[derive(Serialize, Deserialize)]
 pub struct NewColumn {
     pub name: Option<String>,
     pub position: i32,
 }
-pub type ReturnedColumn = (uuid::Uuid, Option<String>, i32);
+pub const SELECT_COLUMN: (
+    board_column::id,
+    board_column::name,
+    board_column::position,
+    board_column::version,
+) = (
+    board_column::id,
+    board_column::name,
+    board_column::position,
+    board_column::version,
+);
+pub type ReturnedColumn = (uuid::Uuid, Option<String>, i32, i32);
+impl From<ReturnedColumn> for PubColumn {
+    fn from(column: ReturnedColumn) -> Self {
+        PubColumn {
+            id: column.0,
+            name: column.1,
+            position: column.2,
+            version: column.3,
+        }
+    }
+}
 #[derive(QueryableByName, Insertable, Queryable, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::board_column)]
 pub struct BoardColumn {
@@ -64,6 +114,28 @@ pub struct BoardColumn {
     pub board_id: uuid::Uuid,
 }
 
+/// A horizontal group of cards spanning every column of the board
+#[derive(Serialize, Deserialize)]
+pub struct PubLane {
+    pub id: uuid::Uuid,
+    pub name: Option<String>,
+    pub position: i32,
+}
+#[derive(Serialize, Deserialize)]
+pub struct NewLane {
+    pub name: Option<String>,
+    pub position: i32,
+}
+pub type ReturnedLane = (uuid::Uuid, Option<String>, i32);
+#[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::board_lane)]
+pub struct BoardLane {
+    pub id: Option<uuid::Uuid>,
+    pub name: Option<String>,
+    pub position: i32,
+    pub board_id: uuid::Uuid,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PubCard {
     pub id: uuid::Uuid,
@@ -72,17 +144,34 @@ pub struct PubCard {
     pub position: i32,
     pub description: Option<String>,
     pub column_id: uuid::Uuid,
+    pub parent_id: Option<uuid::Uuid>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub assignee_id: Option<uuid::Uuid>,
+    pub lane_id: Option<uuid::Uuid>,
+    pub sprint_id: Option<uuid::Uuid>,
+    pub version: i32,
+    /// Values of the custom fields of the board, by field id
+    pub custom_fields: HashMap<uuid::Uuid, serde_json::Value>,
 }
 #[derive(Serialize, Deserialize)]
 pub struct NewCard {
     pub name: String,
     pub position: i32,
     pub description: Option<String>,
+    pub parent_id: Option<uuid::Uuid>,
+    pub lane_id: Option<uuid::Uuid>,
 }
 #[derive(Serialize, Deserialize)]
 pub struct CardInfo {
     pub name: String,
     pub description: String,
+    pub completed: Option<bool>,
+}
+/// Completion of the sub-cards of a card
+#[derive(Serialize, Deserialize)]
+pub struct CardProgress {
+    pub completed: i64,
+    pub total: i64,
 }
 pub const SELECT_CARD: (
     column_card::id,
@@ -91,6 +180,12 @@ pub const SELECT_CARD: (
     column_card::position,
     column_card::description,
     column_card::column_id,
+    column_card::parent_id,
+    column_card::completed_at,
+    column_card::assignee_id,
+    column_card::lane_id,
+    column_card::sprint_id,
+    column_card::version,
 ) = (
     column_card::id,
     column_card::name,
@@ -98,8 +193,46 @@ pub const SELECT_CARD: (
     column_card::position,
     column_card::description,
     column_card::column_id,
+    column_card::parent_id,
+    column_card::completed_at,
+    column_card::assignee_id,
+    column_card::lane_id,
+    column_card::sprint_id,
+    column_card::version,
+);
+pub type ReturnedCard = (
+    Uuid,
+    String,
+    Option<String>,
+    i32,
+    Option<String>,
+    Uuid,
+    Option<Uuid>,
+    Option<NaiveDateTime>,
+    Option<Uuid>,
+    Option<Uuid>,
+    Option<Uuid>,
+    i32,
 );
-pub type ReturnedCard = (Uuid, String, Option<String>, i32, Option<String>, Uuid);
+impl From<ReturnedCard> for PubCard {
+    fn from(card: ReturnedCard) -> Self {
+        PubCard {
+            id: card.0,
+            name: card.1,
+            cover_attachment: card.2,
+            position: card.3,
+            description: card.4,
+            column_id: card.5,
+            parent_id: card.6,
+            completed_at: card.7,
+            assignee_id: card.8,
+            lane_id: card.9,
+            sprint_id: card.10,
+            version: card.11,
+            custom_fields: HashMap::new(),
+        }
+    }
+}
 #[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = crate::schema::column_card)]
 pub struct ColumnCard {
@@ -108,6 +241,8 @@ pub struct ColumnCard {
     pub column_id: uuid::Uuid,
     pub position: i32,
     pub description: Option<String>,
+    pub parent_id: Option<uuid::Uuid>,
+    pub lane_id: Option<uuid::Uuid>,
 }
 
 #[derive(FromForm)]
@@ -0,0 +1,53 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// What happened to the recipient of a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    AddedToBoard,
+    CardAssigned,
+    FriendAdded,
+    ChatMessage,
+    Mentioned,
+    AddedToWorkspace,
+    AddedToGroup,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::AddedToBoard => "added_to_board",
+            NotificationKind::CardAssigned => "card_assigned",
+            NotificationKind::FriendAdded => "friend_added",
+            NotificationKind::ChatMessage => "chat_message",
+            NotificationKind::Mentioned => "mentioned",
+            NotificationKind::AddedToWorkspace => "added_to_workspace",
+            NotificationKind::AddedToGroup => "added_to_group",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::notifications)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub kind: String,
+    pub payload: Value,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NotificationPage {
+    pub notifications: Vec<Notification>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub unread: i64,
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, ApiErrorType};
+
+use super::file::UploadedFile;
+
+/// The kinds of board operations that can be undone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    CardCreated,
+    CardUpdated,
+    CardMoved,
+    CardDeleted,
+    ColumnCreated,
+    ColumnUpdated,
+    ColumnDeleted,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::CardCreated => "card_created",
+            OperationKind::CardUpdated => "card_updated",
+            OperationKind::CardMoved => "card_moved",
+            OperationKind::CardDeleted => "card_deleted",
+            OperationKind::ColumnCreated => "column_created",
+            OperationKind::ColumnUpdated => "column_updated",
+            OperationKind::ColumnDeleted => "column_deleted",
+        }
+    }
+}
+
+impl FromStr for OperationKind {
+    type Err = ApiError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "card_created" => Ok(OperationKind::CardCreated),
+            "card_updated" => Ok(OperationKind::CardUpdated),
+            "card_moved" => Ok(OperationKind::CardMoved),
+            "card_deleted" => Ok(OperationKind::CardDeleted),
+            "column_created" => Ok(OperationKind::ColumnCreated),
+            "column_updated" => Ok(OperationKind::ColumnUpdated),
+            "column_deleted" => Ok(OperationKind::ColumnDeleted),
+            _ => Err(ApiError::from_type(ApiErrorType::InternalServerError)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::board_operations)]
+pub struct BoardOperation {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub kind: String,
+    pub target_id: Uuid,
+    pub snapshot: Option<Value>,
+    pub result_version: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub undone_at: Option<NaiveDateTime>,
+}
+
+/// An operation its author can still undo
+#[derive(Debug, Serialize)]
+pub struct PubOperation {
+    pub id: Uuid,
+    pub kind: String,
+    pub target_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub undone_at: Option<NaiveDateTime>,
+}
+
+impl PubOperation {
+    pub fn new(operation: BoardOperation, window: Duration) -> Self {
+        PubOperation {
+            id: operation.id,
+            kind: operation.kind,
+            target_id: operation.target_id,
+            created_at: operation.created_at,
+            expires_at: operation.created_at + window,
+            undone_at: operation.undone_at,
+        }
+    }
+}
+
+/// The fields of a card `boards_update_card` changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardEdit {
+    pub name: String,
+    pub description: Option<String>,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+/// Where a card sits on the board
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardPlacement {
+    pub column_id: Uuid,
+    pub position: i32,
+    pub lane_id: Option<Uuid>,
+}
+
+/// The fields of a column `boards_update_column` changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnEdit {
+    pub name: Option<String>,
+    pub position: i32,
+}
+
+/// Everything needed to bring a deleted card back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardSnapshot {
+    pub id: Uuid,
+    pub column_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub position: i32,
+    pub cover_attachment: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub assignee_id: Option<Uuid>,
+    pub lane_id: Option<Uuid>,
+    pub sprint_id: Option<Uuid>,
+    pub custom_fields: HashMap<Uuid, Value>,
+    /// The files stay on disk until the operation can't be undone anymore
+    pub attachments: Vec<UploadedFile>,
+}
+
+/// A deleted column with the cards it held
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnSnapshot {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub position: i32,
+    pub cards: Vec<CardSnapshot>,
+}
+
+/// How long operations can be undone, `UNDO_WINDOW_MINUTES` in the environment
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct UndoConfig {
+    #[serde(default = "UndoConfig::default_window")]
+    pub undo_window_minutes: i64,
+}
+
+impl UndoConfig {
+    fn default_window() -> i64 {
+        30
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::minutes(self.undo_window_minutes)
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Whether a user is around, derived from their chat sockets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    /// Connected but idle for a while
+    Away,
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presence {
+    pub user_id: Uuid,
+    pub status: PresenceStatus,
+    /// Last activity of the user since the server started, `null` when never seen
+    pub last_seen: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypingEvent {
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// Signals about the chat socket itself, they are never stored
+///
+/// A socket only receives the messages of the conversations it subscribed to
+/// ```json
+/// { "action": "subscribe", "conversation_id": <conversation_id> }
+/// { "action": "unsubscribe", "conversation_id": <conversation_id> }
+/// { "action": "typing", "conversation_id": <conversation_id> }
+/// { "action": "stopped_typing", "conversation_id": <conversation_id> }
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientSignal {
+    /// Answered with `Subscribed`, or an `Error` when the user is not a member
+    Subscribe {
+        conversation_id: Uuid,
+    },
+    Unsubscribe {
+        conversation_id: Uuid,
+    },
+    /// Sent while the user types, the indicator expires unless it is repeated
+    Typing {
+        conversation_id: Uuid,
+    },
+    StoppedTyping {
+        conversation_id: Uuid,
+    },
+}
@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Duration, Months, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::{ApiError, ApiErrorType};
+
+pub const MAX_INTERVAL: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// The subset of RFC 5545 RRULE supported for recurring cards
+///
+/// Only `FREQ` (`DAILY`, `WEEKLY` or `MONTHLY`) and `INTERVAL` (at most
+/// `MAX_INTERVAL`) are understood, e.g. `FREQ=WEEKLY;INTERVAL=2`. The bare words
+/// `daily`, `weekly` and `monthly` are accepted as shorthands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+}
+
+impl RecurrenceRule {
+    /// Returns the occurrence that follows `from`, or `None` past the end of the calendar
+    pub fn next_after(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.frequency {
+            Frequency::Daily => from.checked_add_signed(Duration::days(self.interval as i64)),
+            Frequency::Weekly => from.checked_add_signed(Duration::weeks(self.interval as i64)),
+            Frequency::Monthly => from.checked_add_months(Months::new(self.interval)),
+        }
+    }
+
+    /// Returns the first occurrence after `from` that is later than `now`
+    pub fn next_after_now(&self, from: NaiveDateTime, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = self.next_after(from)?;
+        while next <= now {
+            next = self.next_after(next)?;
+        }
+        Some(next)
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = ApiError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = || ApiError::from_type(ApiErrorType::InvalidRecurrenceRule);
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = match rule.to_lowercase().as_str() {
+            "daily" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            _ => None,
+        };
+        let mut interval = 1;
+        if frequency.is_none() {
+            for part in rule.split(';').filter(|part| !part.is_empty()) {
+                let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+                match key.to_uppercase().as_str() {
+                    "FREQ" => {
+                        frequency = Some(match value.to_uppercase().as_str() {
+                            "DAILY" => Frequency::Daily,
+                            "WEEKLY" => Frequency::Weekly,
+                            "MONTHLY" => Frequency::Monthly,
+                            _ => return Err(invalid()),
+                        })
+                    }
+                    "INTERVAL" => interval = value.parse::<u32>().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                }
+            }
+        }
+
+        match frequency {
+            Some(frequency) if (1..=MAX_INTERVAL).contains(&interval) => Ok(RecurrenceRule {
+                frequency,
+                interval,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={};INTERVAL={}", frequency, self.interval)
+    }
+}
+
+#[derive(Insertable, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::card_recurrences)]
+pub struct CardRecurrence {
+    pub id: Uuid,
+    pub card_id: Uuid,
+    pub column_id: Uuid,
+    pub rule: String,
+    pub next_run_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewRecurrence {
+    pub rule: String,
+    pub column_id: Uuid,
+    pub starts_at: Option<NaiveDateTime>,
+}
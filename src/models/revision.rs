@@ -0,0 +1,220 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The name and description of a card at one point of its history
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::card_revisions)]
+pub struct CardRevision {
+    pub id: Uuid,
+    pub card_id: Uuid,
+    pub revision: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub author_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub line: String,
+}
+
+/// The changes between two revisions of a card
+#[derive(Debug, Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub name_from: String,
+    pub name_to: String,
+    pub lines: Vec<DiffLine>,
+}
+
+impl RevisionDiff {
+    pub fn new(from: &CardRevision, to: &CardRevision) -> Self {
+        RevisionDiff {
+            from: from.revision,
+            to: to.revision,
+            name_from: from.name.clone(),
+            name_to: to.name.clone(),
+            lines: diff_lines(
+                from.description.as_deref().unwrap_or_default(),
+                to.description.as_deref().unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+/// Largest longest common subsequence table `diff_lines` builds, about 16 MB
+pub const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Line based diff of two texts, built from their longest common subsequence
+///
+/// Deletions come before insertions wherever a block of lines was replaced. When the
+/// changed parts are too large for the table, they are shown as one replaced block
+pub fn diff_lines(from: &str, to: &str) -> Vec<DiffLine> {
+    let from = from.lines().collect::<Vec<&str>>();
+    let to = to.lines().collect::<Vec<&str>>();
+
+    // Common prefix and suffix don't need the table
+    let prefix = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &from[prefix..from.len() - suffix];
+    let new = &to[prefix..to.len() - suffix];
+
+    let line = |op: DiffOp, line: &str| DiffLine {
+        op,
+        line: line.to_string(),
+    };
+    let mut lines = from[..prefix]
+        .iter()
+        .map(|l| line(DiffOp::Equal, l))
+        .collect::<Vec<DiffLine>>();
+    let suffix_lines = from[from.len() - suffix..]
+        .iter()
+        .map(|l| line(DiffOp::Equal, l));
+
+    let cells = (old.len() + 1).saturating_mul(new.len() + 1);
+    if cells > MAX_DIFF_CELLS {
+        lines.extend(old.iter().map(|l| line(DiffOp::Delete, l)));
+        lines.extend(new.iter().map(|l| line(DiffOp::Insert, l)));
+        lines.extend(suffix_lines);
+        return lines;
+    }
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(line(DiffOp::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(line(DiffOp::Delete, old[i]));
+            i += 1;
+        } else {
+            lines.push(line(DiffOp::Insert, new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(suffix_lines);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(lines: &[DiffLine]) -> Vec<(DiffOp, &str)> {
+        lines
+            .iter()
+            .map(|line| (line.op, line.line.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_common_prefix_and_suffix() {
+        let lines = diff_lines("a\nb\nc\nd", "a\nb\nx\nd");
+        assert_eq!(
+            ops(&lines),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Equal, "b"),
+                (DiffOp::Delete, "c"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Equal, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn replaced_block_deletes_before_inserting() {
+        let lines = diff_lines("a\nb\nc\nz", "a\nx\ny\nz");
+        assert_eq!(
+            ops(&lines),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Delete, "c"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Insert, "y"),
+                (DiffOp::Equal, "z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_lines_common_to_both_sides_of_a_change() {
+        let lines = diff_lines("a\nb\nc", "b\nc\nd");
+        assert_eq!(
+            ops(&lines),
+            vec![
+                (DiffOp::Delete, "a"),
+                (DiffOp::Equal, "b"),
+                (DiffOp::Equal, "c"),
+                (DiffOp::Insert, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn handles_empty_texts() {
+        assert!(diff_lines("", "").is_empty());
+        assert_eq!(
+            ops(&diff_lines("", "a\nb")),
+            vec![(DiffOp::Insert, "a"), (DiffOp::Insert, "b")]
+        );
+        assert_eq!(
+            ops(&diff_lines("a\nb", "")),
+            vec![(DiffOp::Delete, "a"), (DiffOp::Delete, "b")]
+        );
+    }
+
+    #[test]
+    fn large_changes_become_one_replaced_block() {
+        let from = (0..3000)
+            .map(|i| format!("old {}\n", i))
+            .collect::<String>();
+        let to = (0..3000)
+            .map(|i| format!("new {}\n", i))
+            .collect::<String>();
+        let lines = diff_lines(&format!("head\n{}tail", from), &format!("head\n{}tail", to));
+
+        assert_eq!(lines.len(), 6002);
+        assert_eq!(
+            ops(&lines[..2]),
+            vec![(DiffOp::Equal, "head"), (DiffOp::Delete, "old 0")]
+        );
+        assert!(lines[1..3001].iter().all(|line| line.op == DiffOp::Delete));
+        assert!(lines[3001..6001]
+            .iter()
+            .all(|line| line.op == DiffOp::Insert));
+        assert_eq!(ops(&lines[6001..]), vec![(DiffOp::Equal, "tail")]);
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SprintState {
+    Planned,
+    Active,
+    Completed,
+}
+
+impl SprintState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SprintState::Planned => "planned",
+            SprintState::Active => "active",
+            SprintState::Completed => "completed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::sprints)]
+pub struct Sprint {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub name: String,
+    pub goal: Option<String>,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    pub state: String,
+    pub carried_over: i32,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewSprint {
+    pub name: String,
+    pub goal: Option<String>,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+}
+
+/// Where the unfinished cards of a sprint go when it is completed
+///
+/// They join `next_sprint_id` when it is set, otherwise they leave the sprint
+/// and are moved to the end of `backlog_column_id`
+#[derive(Serialize, Deserialize)]
+pub struct CompleteSprint {
+    pub next_sprint_id: Option<Uuid>,
+    pub backlog_column_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompletedSprint {
+    pub sprint: Sprint,
+    /// The unfinished cards that were moved
+    pub moved_cards: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BurndownPoint {
+    pub date: NaiveDate,
+    /// Cards of the sprint still open at the end of the day, `None` for days to come
+    pub remaining: Option<i64>,
+    /// Where `remaining` would be if cards were completed at a steady pace
+    pub ideal: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Burndown {
+    pub sprint_id: Uuid,
+    /// Cards committed to the sprint, including the ones carried over when it was completed
+    pub total: i64,
+    pub completed: i64,
+    pub points: Vec<BurndownPoint>,
+}
@@ -0,0 +1,119 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use rocket::http::ContentType;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::api_response::ApiResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::time_entries)]
+pub struct TimeEntry {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub card_id: Uuid,
+    pub user_id: Uuid,
+    pub started_at: NaiveDateTime,
+    /// `None` while the timer is running
+    pub ended_at: Option<NaiveDateTime>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewTimeEntry {
+    pub started_at: NaiveDateTime,
+    pub ended_at: NaiveDateTime,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TimerNote {
+    pub note: Option<String>,
+}
+
+/// A time entry with the names needed to bill it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportedTimeEntry {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub card_id: Uuid,
+    pub card_name: String,
+    pub user_id: Uuid,
+    pub username: String,
+    pub started_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+    /// Running timers count up to the time of the report
+    pub duration_seconds: i64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeTotal {
+    pub id: Uuid,
+    pub name: String,
+    pub seconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_seconds: i64,
+    pub by_user: Vec<TimeTotal>,
+    pub by_card: Vec<TimeTotal>,
+    pub entries: Vec<ReportedTimeEntry>,
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl TimeSummary {
+    /// One line per entry, for spreadsheets and invoicing tools
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "entry_id,board_id,card_id,card_name,user_id,username,started_at,ended_at,duration_seconds,note\n",
+        );
+        for entry in &self.entries {
+            let line = [
+                entry.id.to_string(),
+                entry.board_id.to_string(),
+                entry.card_id.to_string(),
+                csv_field(&entry.card_name),
+                entry.user_id.to_string(),
+                csv_field(&entry.username),
+                entry.started_at.to_string(),
+                entry
+                    .ended_at
+                    .map(|ended_at| ended_at.to_string())
+                    .unwrap_or_default(),
+                entry.duration_seconds.to_string(),
+                csv_field(entry.note.as_deref().unwrap_or_default()),
+            ];
+            csv.push_str(&line.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// A time summary as JSON or, with `format=csv`, as a CSV file
+#[derive(Responder)]
+pub enum TimeReport {
+    Json(ApiResponse<TimeSummary>),
+    Csv((ContentType, String)),
+}
+
+impl TimeReport {
+    pub fn new(summary: TimeSummary, format: Option<&str>) -> Self {
+        match format {
+            Some("csv") => TimeReport::Csv((ContentType::CSV, summary.to_csv())),
+            _ => TimeReport::Json(ApiResponse::new(summary)),
+        }
+    }
+}
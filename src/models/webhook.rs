@@ -0,0 +1,181 @@
+use chrono::{Duration, NaiveDateTime};
+use diesel::{Insertable, Queryable, Selectable};
+use hmac::{Hmac, Mac};
+use rocket::tokio::sync::Notify;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhooks)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Value,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Webhook {
+    /// Whether the webhook wants the event, an empty filter takes every event
+    pub fn wants(&self, event: &str) -> bool {
+        match self.events.as_array() {
+            Some(events) if !events.is_empty() => {
+                events.iter().any(|name| name.as_str() == Some(event))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A webhook as shown to the board creator, the secret is never sent back
+#[derive(Debug, Serialize)]
+pub struct PubWebhook {
+    pub id: Uuid,
+    pub board_id: Uuid,
+    pub url: String,
+    pub events: Value,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<Webhook> for PubWebhook {
+    fn from(webhook: Webhook) -> Self {
+        PubWebhook {
+            id: webhook.id,
+            board_id: webhook.board_id,
+            url: webhook.url,
+            events: webhook.events,
+            active: webhook.active,
+            created_by: webhook.created_by,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    /// Names of the events to deliver, every event when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default = "NewWebhook::default_active")]
+    pub active: bool,
+}
+
+impl NewWebhook {
+    fn default_active() -> bool {
+        true
+    }
+}
+
+/// A failing delivery is given up after this many attempts
+pub const MAX_ATTEMPTS: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    /// The status of a delivery whose latest of `attempts` attempts failed
+    pub fn after_failure(attempts: i32) -> Self {
+        match attempts >= MAX_ATTEMPTS {
+            true => DeliveryStatus::Failed,
+            false => DeliveryStatus::Pending,
+        }
+    }
+}
+
+/// How long to wait before retrying a delivery that failed `attempts` times,
+/// `10s * 2^attempts` and at most an hour
+pub fn backoff(attempts: i32) -> Duration {
+    Duration::seconds(10 << attempts.clamp(0, 9)).min(Duration::hours(1))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl WebhookDelivery {
+    pub fn new(webhook_id: Uuid, event: &str, payload: Value, now: NaiveDateTime) -> Self {
+        WebhookDelivery {
+            id: Uuid::new_v4(),
+            webhook_id,
+            event: event.to_string(),
+            payload,
+            status: DeliveryStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_status_code: None,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+        }
+    }
+
+    /// A new pending delivery of the same payload, it starts over from the first attempt
+    pub fn redelivery(&self, now: NaiveDateTime) -> Self {
+        WebhookDelivery::new(self.webhook_id, &self.event, self.payload.clone(), now)
+    }
+}
+
+/// A delivery the worker claimed, with where and how to send it
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub delivery: WebhookDelivery,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Returns the `X-Webhook-Signature` header value of the body, `sha256=<hex HMAC>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Wakes the delivery worker up when new deliveries are queued
+#[derive(Default)]
+pub struct WebhookQueue {
+    notify: Notify,
+}
+
+impl WebhookQueue {
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    pub async fn woken(&self) {
+        self.notify.notified().await;
+    }
+}
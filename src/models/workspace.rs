@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::{ApiError, ApiErrorType};
+
+use super::PubBoard;
+
+/// What a member is allowed to do in a workspace, ordered from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl WorkspaceRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Member => "member",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for WorkspaceRole {
+    type Err = ApiError;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "member" => Ok(WorkspaceRole::Member),
+            "admin" => Ok(WorkspaceRole::Admin),
+            "owner" => Ok(WorkspaceRole::Owner),
+            _ => Err(ApiError::from_type(ApiErrorType::InvalidWorkspaceRole)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::workspaces)]
+pub struct Workspace {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub personal: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::workspace_members)]
+pub struct WorkspaceMember {
+    pub workspace_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub joined_at: NaiveDateTime,
+}
+
+impl WorkspaceMember {
+    pub fn role(&self) -> WorkspaceRole {
+        self.role.parse().unwrap_or(WorkspaceRole::Member)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewWorkspace {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NewWorkspaceMember {
+    pub user_id: Uuid,
+    /// `member` when left out, a workspace has a single `owner`
+    pub role: Option<WorkspaceRole>,
+}
+
+/// A workspace together with the role of the current user in it
+#[derive(Serialize, Deserialize)]
+pub struct PubWorkspace {
+    pub id: Uuid,
+    pub name: String,
+    pub personal: bool,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PubWorkspaceMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: WorkspaceRole,
+    pub joined_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub personal: bool,
+    pub members: Vec<PubWorkspaceMember>,
+    pub boards: Vec<PubBoard>,
+}
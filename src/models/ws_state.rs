@@ -2,27 +2,70 @@ use std::{
     collections::{HashMap, HashSet},
     fmt,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
-use rocket::{futures::stream::SplitSink, tokio::sync::RwLock};
+use chrono::{NaiveDateTime, Utc};
+use rocket::{
+    futures::stream::SplitSink,
+    tokio::sync::{Mutex, RwLock},
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use ws::{stream::DuplexStream, Message};
 
 use rocket::futures::SinkExt;
 
-use super::messages::ChatMessageDTO;
+use super::{
+    messages::{ChatMessageDTO, ReadReceipt},
+    notification::Notification,
+    presence::{Presence, PresenceStatus, TypingEvent},
+};
+
+/// Typing indicators are forwarded at most once per this delay for a member and conversation
+pub const TYPING_THROTTLE: Duration = Duration::from_secs(3);
+/// Typing indicators that are not repeated within this delay stop on their own
+pub const TYPING_EXPIRY: Duration = Duration::from_secs(6);
+/// Connected members without any activity for this long are away
+pub const AWAY_AFTER_MINUTES: i64 = 5;
 
+/// Sent to the sockets tagged with the name of the variant, like `{ "Chat": <message> }`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WsMessage {
     Chat(ChatMessageDTO),
+    Notification(Notification),
+    /// The message with its new content
+    MessageEdited(ChatMessageDTO),
+    /// The tombstone of the message
+    MessageDeleted(ChatMessageDTO),
+    ReadReceipt(ReadReceipt),
+    Typing(TypingEvent),
+    StoppedTyping(TypingEvent),
+    Presence(Presence),
+    /// The socket now follows the conversation
+    Subscribed(Uuid),
+    /// The socket no longer follows the conversation
+    Unsubscribed(Uuid),
+    Error(String),
     Close,
 }
 
 impl std::fmt::Display for WsMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            WsMessage::Chat(msg) => write!(f, "{}", serde_json::to_string(msg).unwrap()),
+            WsMessage::Chat(_)
+            | WsMessage::Notification(_)
+            | WsMessage::MessageEdited(_)
+            | WsMessage::MessageDeleted(_)
+            | WsMessage::ReadReceipt(_)
+            | WsMessage::Typing(_)
+            | WsMessage::StoppedTyping(_)
+            | WsMessage::Presence(_)
+            | WsMessage::Subscribed(_)
+            | WsMessage::Unsubscribed(_)
+            | WsMessage::Error(_) => {
+                write!(f, "{}", serde_json::to_string(self).unwrap())
+            }
             WsMessage::Close => write!(f, "Close"),
         }
     }
@@ -45,16 +88,38 @@ impl std::fmt::Display for WsError {
     }
 }
 
+/// The sending half of a socket, locked on its own so that sending doesn't hold the map
+type Sender = Arc<Mutex<SplitSink<DuplexStream, Message>>>;
+
+/// One socket of a member, a member can have several at once, one per tab or device
 struct Connection {
-    sender: SplitSink<DuplexStream, Message>,
+    member_id: MemberId,
+    sender: Sender,
+    conversations: HashSet<ConversationId>,
 }
 
 impl fmt::Debug for Connection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Connection")
+        f.debug_struct("Connection")
+            .field("member_id", &self.member_id)
+            .field("conversations", &self.conversations)
+            .finish()
     }
 }
 
+#[derive(Debug)]
+struct Typing {
+    announced_at: Instant,
+    expires_at: Instant,
+}
+
+#[derive(Debug)]
+struct Activity {
+    last_seen: NaiveDateTime,
+    status: PresenceStatus,
+}
+
+pub type ConnectionId = Uuid;
 type ConversationId = Uuid;
 type MemberId = Uuid;
 
@@ -62,127 +127,462 @@ type MemberId = Uuid;
 ///
 /// This state is used to store the connections and conversations
 ///
-/// The connections are stored in a HashMap with the member id as the key
+/// Every socket is registered as its own connection, so a member can be connected from
+/// several tabs or devices at once. Each connection subscribes to any number of
+/// conversations and only receives the messages of those. Notifications go to every
+/// connection of the member
+///
+/// When a connection is unregistered, it leaves all its conversations. The member goes
+/// offline once their last connection is gone
 ///
-/// The conversations are stored in a HashMap with the conversation id as the key
-/// First the member is registered to the connections, then the member is added to the conversation
-/// When the member is unregistered, the member is removed from the conversation
-/// When the member is removed from the conversation, the member is removed from the connections
+/// Typing indicators and presence are kept next to the connections, they are only
+/// broadcast to the other members following the same conversations
 ///
 /// ```
 /// let ws_state = WsState::new();
-///  // add member to connections
-/// ws_state.register_member(&member_id, &member);
-///  // add member to conversation
-/// ws_state.add_to_conversation(&conversation_id, &member_id);
-///  // send message to conversation
-/// ws_state.send(&conversation_id, &message);
-///  // remove member from conversation
-/// ws_state.unregister(&member_id);
+///  // add a connection of the member
+/// let connection_id = ws_state.register_member(&member_id, sender);
+///  // follow a conversation on that connection
+/// ws_state.subscribe(&connection_id, &conversation_id);
+///  // send message to the members of the conversation
+/// ws_state.send_to_members(&conversation_id, &members, message);
+///  // close the connection
+/// ws_state.unregister(&connection_id);
 /// ```
-///
-
 #[derive(Debug)]
 pub struct WsState {
-    connections: RwLock<HashMap<MemberId, Connection>>,
-    conversations: RwLock<HashMap<ConversationId, HashSet<MemberId>>>,
-    user_conversations: RwLock<HashMap<MemberId, ConversationId>>,
+    connections: RwLock<HashMap<ConnectionId, Connection>>,
+    member_connections: RwLock<HashMap<MemberId, HashSet<ConnectionId>>>,
+    conversations: RwLock<HashMap<ConversationId, HashSet<ConnectionId>>>,
+    typing: RwLock<HashMap<(ConversationId, MemberId), Typing>>,
+    activity: RwLock<HashMap<MemberId, Activity>>,
 }
 
 impl WsState {
     pub fn new() -> Arc<Self> {
         let state = Arc::new(Self {
             connections: RwLock::new(HashMap::new()),
+            member_connections: RwLock::new(HashMap::new()),
             conversations: RwLock::new(HashMap::new()),
-            user_conversations: RwLock::new(HashMap::new()),
+            typing: RwLock::new(HashMap::new()),
+            activity: RwLock::new(HashMap::new()),
         });
 
         state
     }
 
+    /// Adds a connection of the member, their other connections stay open
     pub async fn register_member(
         &self,
         member_id: &Uuid,
         member: SplitSink<DuplexStream, Message>,
-    ) {
-        self.connections
+    ) -> ConnectionId {
+        let connection_id = Uuid::new_v4();
+        self.connections.write().await.insert(
+            connection_id,
+            Connection {
+                member_id: *member_id,
+                sender: Arc::new(Mutex::new(member)),
+                conversations: HashSet::new(),
+            },
+        );
+        self.member_connections
+            .write()
+            .await
+            .entry(*member_id)
+            .or_default()
+            .insert(connection_id);
+        connection_id
+    }
+
+    /// Makes the connection follow the conversation, membership is checked by the caller
+    pub async fn subscribe(&self, connection_id: &Uuid, conv_id: &Uuid) {
+        {
+            let mut guard = self.connections.write().await;
+            let Some(connection) = guard.get_mut(connection_id) else {
+                return;
+            };
+            connection.conversations.insert(*conv_id);
+        }
+        self.conversations
             .write()
             .await
-            .insert(member_id.clone(), Connection { sender: member });
+            .entry(*conv_id)
+            .or_default()
+            .insert(*connection_id);
+    }
+
+    /// Stops following the conversation on the connection
+    pub async fn unsubscribe(&self, connection_id: &Uuid, conv_id: &Uuid) {
+        let member_id = {
+            let mut guard = self.connections.write().await;
+            let Some(connection) = guard.get_mut(connection_id) else {
+                return;
+            };
+            connection.conversations.remove(conv_id);
+            connection.member_id
+        };
+        self.leave_conversation(connection_id, conv_id).await;
+        if !self.user_in_conversation(conv_id, &member_id).await {
+            self.stop_typing(conv_id, &member_id).await;
+        }
+    }
+
+    /// Unsubscribes every connection of the member, once they are no longer part of
+    /// the conversation
+    pub async fn unsubscribe_member(&self, member_id: &Uuid, conv_id: &Uuid) {
+        let followed = {
+            let connection_ids = self.connection_ids(member_id).await;
+            let guard = self.connections.read().await;
+            connection_ids
+                .into_iter()
+                .filter(|connection_id| {
+                    guard
+                        .get(connection_id)
+                        .is_some_and(|connection| connection.conversations.contains(conv_id))
+                })
+                .collect::<Vec<ConnectionId>>()
+        };
+        for connection_id in followed {
+            self.unsubscribe(&connection_id, conv_id).await;
+            self.send_to_connection(&connection_id, WsMessage::Unsubscribed(*conv_id))
+                .await;
+        }
     }
 
-    pub async fn add_to_conversation(&self, conv_id: &Uuid, member_id: &Uuid) {
+    async fn leave_conversation(&self, connection_id: &Uuid, conv_id: &Uuid) {
         let mut guard = self.conversations.write().await;
-        if guard.contains_key(conv_id) {
-            guard.get_mut(conv_id).unwrap().insert(member_id.clone());
-        } else {
-            guard.insert(conv_id.clone(), HashSet::from([member_id.clone()]));
+        if let Some(connections) = guard.get_mut(conv_id) {
+            connections.remove(connection_id);
+            if connections.is_empty() {
+                guard.remove(conv_id);
+            }
         }
-        let mut user_guard = self.user_conversations.write().await;
-        user_guard.insert(member_id.clone(), conv_id.clone());
     }
 
+    /// Whether any connection of the member follows the conversation
     pub async fn user_in_conversation(&self, conv_id: &Uuid, member_id: &Uuid) -> bool {
-        let guard = self.conversations.read().await;
-        if let Some(members) = guard.get(conv_id) {
-            members.contains(member_id)
-        } else {
-            false
-        }
+        let connection_ids = self.connection_ids(member_id).await;
+        let guard = self.connections.read().await;
+        connection_ids.iter().any(|connection_id| {
+            guard
+                .get(connection_id)
+                .is_some_and(|connection| connection.conversations.contains(conv_id))
+        })
+    }
+
+    async fn connection_ids(&self, member_id: &Uuid) -> Vec<ConnectionId> {
+        self.member_connections
+            .read()
+            .await
+            .get(member_id)
+            .map(|connections| connections.iter().copied().collect())
+            .unwrap_or_default()
     }
 
-    pub async fn unregister(&self, member_id: &Uuid) -> WsResult<()> {
-        let mut connections_guard = self.connections.write().await;
-        if let Some(mut conn) = connections_guard.remove(member_id) {
-            let res = conn.sender.flush().await;
-            let _ = conn.sender.close().await;
-            dbg!("Closing connection: ", &res);
+    /// Closes the connection and drops its subscriptions
+    ///
+    /// The member goes offline when it was their last connection
+    pub async fn unregister(&self, connection_id: &Uuid) -> WsResult<()> {
+        let member_id = match self.connections.read().await.get(connection_id) {
+            Some(connection) => connection.member_id,
+            None => return Ok(()),
+        };
+        let last = self.connection_ids(&member_id).await.len() <= 1;
+        if last {
+            // Announced while the connection still follows its conversations
+            self.set_status(&member_id, PresenceStatus::Offline).await;
         }
 
-        let conv_id = {
-            let mut user_guard = self.user_conversations.write().await;
-            user_guard.remove(member_id)
+        let removed = self.connections.write().await.remove(connection_id);
+        let Some(conn) = removed else {
+            return Ok(());
         };
-        if let Some(conv_id) = conv_id {
-            let mut guard = self.conversations.write().await;
-            if let Some(members) = guard.get_mut(&conv_id) {
-                members.remove(member_id);
-                if members.is_empty() {
-                    guard.remove(&conv_id);
+        {
+            let mut guard = self.member_connections.write().await;
+            if let Some(connections) = guard.get_mut(&member_id) {
+                connections.remove(connection_id);
+                if connections.is_empty() {
+                    guard.remove(&member_id);
                 }
             }
         }
-        dbg!(&self);
+        for conv_id in &conn.conversations {
+            self.leave_conversation(connection_id, conv_id).await;
+        }
+        let _ = conn.sender.lock().await.close().await;
+
+        let typing = self
+            .typing
+            .read()
+            .await
+            .keys()
+            .filter(|(_, typist)| *typist == member_id)
+            .map(|(conv_id, _)| *conv_id)
+            .collect::<Vec<_>>();
+        for conv_id in typing {
+            if !self.user_in_conversation(&conv_id, &member_id).await {
+                self.stop_typing(&conv_id, &member_id).await;
+            }
+        }
         Ok(())
     }
 
-    pub async fn send(&self, conv_id: &Uuid, message: WsMessage) -> WsResult<()> {
-        dbg!(&self.conversations.read().await);
-        dbg!(&self.connections.read().await);
-        println!(
-            "sending message: {:?}\n to conversation: {}",
-            message, conv_id
-        );
+    /// Sends the message to the connections following the conversation whose member is
+    /// also in `members`
+    pub async fn send_to_members(
+        &self,
+        conv_id: &Uuid,
+        members: &[Uuid],
+        message: WsMessage,
+    ) -> WsResult<()> {
+        let connection_ids = self.subscribers(conv_id).await;
+        self.send_to_connections(
+            &connection_ids,
+            |member_id| members.contains(member_id),
+            message,
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Sends the message to every connection of the member
+    pub async fn send_to_member(&self, member_id: &Uuid, message: WsMessage) -> WsResult<()> {
+        let connection_ids = self.connection_ids(member_id).await;
+        self.send_to_connections(&connection_ids, |_| true, message)
+            .await;
+        Ok(())
+    }
 
-        let members = {
-            let guard = self.conversations.read().await;
-            if let Some(members) = guard.get(conv_id) {
-                members.clone()
-            } else {
-                return Ok(());
+    /// Sends the message to this connection only
+    pub async fn send_to_connection(&self, connection_id: &Uuid, message: WsMessage) {
+        self.send_to_connections(&[*connection_id], |_| true, message)
+            .await;
+    }
+
+    async fn subscribers(&self, conv_id: &Uuid) -> Vec<ConnectionId> {
+        self.conversations
+            .read()
+            .await
+            .get(conv_id)
+            .map(|connections| connections.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Sends the message to the connections whose member passes `to_member` and drops
+    /// the connections whose socket failed
+    async fn send_to_connections(
+        &self,
+        connection_ids: &[ConnectionId],
+        to_member: impl Fn(&MemberId) -> bool,
+        message: WsMessage,
+    ) {
+        for connection_id in self.deliver(connection_ids, to_member, message).await {
+            let _ = self.unregister(&connection_id).await;
+        }
+    }
+
+    /// Sends the message without holding the map of connections, so a slow socket only
+    /// delays its own messages
+    ///
+    /// Returns the connections whose socket failed. Presence and typing go through here
+    /// directly since `unregister` sends them, failed sockets are dropped by the next message
+    async fn deliver(
+        &self,
+        connection_ids: &[ConnectionId],
+        to_member: impl Fn(&MemberId) -> bool,
+        message: WsMessage,
+    ) -> Vec<ConnectionId> {
+        let senders = {
+            let guard = self.connections.read().await;
+            connection_ids
+                .iter()
+                .filter_map(|connection_id| {
+                    let connection = guard.get(connection_id)?;
+                    to_member(&connection.member_id).then(|| {
+                        (
+                            *connection_id,
+                            connection.member_id,
+                            connection.sender.clone(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let text = message.to_string();
+        let mut failed = Vec::new();
+        for (connection_id, member_id, sender) in senders {
+            if let Err(e) = sender.lock().await.send(Message::Text(text.clone())).await {
+                eprintln!("Failed to send message to {}: {}", member_id, e);
+                failed.push(connection_id);
             }
+        }
+        failed
+    }
+
+    /// Pushes freshly stored notifications to the recipients that are online
+    pub async fn notify(&self, notifications: Vec<Notification>) {
+        for notification in notifications {
+            let user_id = notification.user_id;
+            let _ = self
+                .send_to_member(&user_id, WsMessage::Notification(notification))
+                .await;
+        }
+    }
+
+    /// Sends the message to the connections following the conversation, except those
+    /// of `member_id`
+    async fn send_to_others(&self, conv_id: &Uuid, member_id: &Uuid, message: WsMessage) {
+        let connection_ids = self.subscribers(conv_id).await;
+        self.deliver(&connection_ids, |id| id != member_id, message)
+            .await;
+    }
+
+    /// Shows the member as typing in the conversation they follow
+    ///
+    /// Repeated indicators only push the expiry back until `TYPING_THROTTLE` has passed
+    pub async fn start_typing(&self, conv_id: &Uuid, member_id: &Uuid) {
+        if !self.user_in_conversation(conv_id, member_id).await {
+            return;
+        }
+        let now = Instant::now();
+        {
+            let mut guard = self.typing.write().await;
+            let typing = guard.entry((*conv_id, *member_id)).or_insert(Typing {
+                announced_at: now - TYPING_THROTTLE,
+                expires_at: now,
+            });
+            typing.expires_at = now + TYPING_EXPIRY;
+            if now.duration_since(typing.announced_at) < TYPING_THROTTLE {
+                return;
+            }
+            typing.announced_at = now;
+        }
+        let event = TypingEvent {
+            conversation_id: *conv_id,
+            user_id: *member_id,
         };
-        let mut connections = self.connections.write().await;
+        self.send_to_others(conv_id, member_id, WsMessage::Typing(event))
+            .await;
+    }
 
-        for member_id in members {
-            if let Some(connection) = connections.get_mut(&member_id) {
-                let message_str = message.to_string();
-                if let Err(e) = connection.sender.send(Message::Text(message_str)).await {
-                    eprintln!("Failed to send message to {}: {}", member_id, e);
-                }
+    /// Clears the typing indicator of the member, sending a message does it too
+    pub async fn stop_typing(&self, conv_id: &Uuid, member_id: &Uuid) {
+        let removed = self.typing.write().await.remove(&(*conv_id, *member_id));
+        if removed.is_some() {
+            self.announce_stopped_typing(conv_id, member_id).await;
+        }
+    }
+
+    async fn announce_stopped_typing(&self, conv_id: &Uuid, member_id: &Uuid) {
+        let event = TypingEvent {
+            conversation_id: *conv_id,
+            user_id: *member_id,
+        };
+        self.send_to_others(conv_id, member_id, WsMessage::StoppedTyping(event))
+            .await;
+    }
+
+    /// Records activity of a connected member, bringing them back online when they were not
+    pub async fn touch(&self, member_id: &Uuid) {
+        self.set_status(member_id, PresenceStatus::Online).await;
+    }
+
+    async fn set_status(&self, member_id: &Uuid, status: PresenceStatus) {
+        let changed = {
+            let mut guard = self.activity.write().await;
+            let activity = guard.entry(*member_id).or_insert(Activity {
+                last_seen: Utc::now().naive_utc(),
+                status: PresenceStatus::Offline,
+            });
+            if status != PresenceStatus::Away {
+                activity.last_seen = Utc::now().naive_utc();
             }
+            let changed = activity.status != status;
+            activity.status = status;
+            changed
+        };
+        if !changed {
+            return;
         }
 
-        Ok(())
+        // Everyone following a conversation with the member hears about it, once
+        let followed = {
+            let connection_ids = self.connection_ids(member_id).await;
+            let guard = self.connections.read().await;
+            connection_ids
+                .iter()
+                .filter_map(|connection_id| guard.get(connection_id))
+                .flat_map(|connection| connection.conversations.iter().copied())
+                .collect::<HashSet<_>>()
+        };
+        let mut watchers = HashSet::new();
+        for conv_id in followed {
+            watchers.extend(self.subscribers(&conv_id).await);
+        }
+        let watchers = watchers.into_iter().collect::<Vec<_>>();
+        let presence = self.presence(&[*member_id]).await.remove(0);
+        self.deliver(
+            &watchers,
+            |id| id != member_id,
+            WsMessage::Presence(presence),
+        )
+        .await;
+    }
+
+    /// Returns the presence of the users, in the same order
+    pub async fn presence(&self, user_ids: &[Uuid]) -> Vec<Presence> {
+        let guard = self.activity.read().await;
+        user_ids
+            .iter()
+            .map(|user_id| match guard.get(user_id) {
+                Some(activity) => Presence {
+                    user_id: *user_id,
+                    status: activity.status,
+                    last_seen: Some(activity.last_seen),
+                },
+                None => Presence {
+                    user_id: *user_id,
+                    status: PresenceStatus::Offline,
+                    last_seen: None,
+                },
+            })
+            .collect()
+    }
+
+    /// Stops the typing indicators that were not repeated in time and marks idle members away
+    pub async fn expire(&self) {
+        let now = Instant::now();
+        let expired = {
+            let mut guard = self.typing.write().await;
+            let expired = guard
+                .iter()
+                .filter(|(_, typing)| typing.expires_at <= now)
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+            for key in &expired {
+                guard.remove(key);
+            }
+            expired
+        };
+        for (conv_id, member_id) in expired {
+            self.announce_stopped_typing(&conv_id, &member_id).await;
+        }
+
+        let idle_since = Utc::now().naive_utc() - chrono::Duration::minutes(AWAY_AFTER_MINUTES);
+        let idle = self
+            .activity
+            .read()
+            .await
+            .iter()
+            .filter(|(_, activity)| {
+                activity.status == PresenceStatus::Online && activity.last_seen <= idle_since
+            })
+            .map(|(member_id, _)| *member_id)
+            .collect::<Vec<_>>();
+        for member_id in idle {
+            self.set_status(&member_id, PresenceStatus::Away).await;
+        }
     }
 }
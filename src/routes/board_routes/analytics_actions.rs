@@ -0,0 +1,201 @@
+use chrono::{Days, Utc};
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::{
+    database::{analytics_queries::AnalyticsQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        analytics::{BoardAnalytics, CardTimeline, StaleCard},
+        api_response::ApiResponse,
+        auth::AuthResult,
+        BoardUsersRelation,
+    },
+    schema::board_users_relation,
+};
+
+use super::helpers::parse_date_range;
+
+/// Cards idle for this many days are stale unless the request says otherwise
+const DEFAULT_STALE_DAYS: i64 = 7;
+
+/// # GET /boards/<board_id>/analytics?<from>&<to>
+/// Returns the metrics of the board over a range of days, built from the movement history of its cards
+///
+/// The range defaults to the last 30 days and cannot exceed a year
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `from` - The first day of the range, as `YYYY-MM-DD`
+/// * `to` - The last day of the range, as `YYYY-MM-DD`
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `analytics` - Cards created and completed per day, the average cycle and lead time
+///   of each column and the number of cards in each column at the end of each day
+/// ```json
+/// {
+///     "from": "2025-02-01",
+///     "to": "2025-02-28",
+///     "throughput": [{ "date": "2025-02-01", "created": 3, "completed": 1 }, ...],
+///     "columns": [
+///         {
+///             "column_id": <column_id>,
+///             "name": <column_name>,
+///             "average_cycle_hours": 12.5,
+///             "average_lead_hours": 30.0
+///         },
+///         ...
+///     ],
+///     "cumulative_flow": [{ "date": "2025-02-01", "columns": { <column_id>: 4, ... } }, ...]
+/// }
+/// ```
+#[get("/<board_id>/analytics?<from>&<to>")]
+pub async fn boards_get_analytics(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<ApiResponse<BoardAnalytics>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let (from, to) = parse_date_range(from, to)?;
+
+    db.run(move |conn| {
+        let board_id = Uuid::try_parse(&board_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let analytics = AnalyticsQueries::board_analytics(conn, board_id, from, to)?;
+        Ok::<BoardAnalytics, ApiError>(analytics)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/cards/<card_id>/timeline
+/// Returns the columns the card went through and how long it stayed in each of them
+///
+/// Deleted cards keep their timeline, the last stay then has a `left_at`
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `timeline` - Each stay of the card in a column, oldest first, and the total per column.
+///   The current stay has no `left_at` and counts up to now
+/// ```json
+/// {
+///     "card_id": <card_id>,
+///     "created_at": <creation_time>,
+///     "deleted_at": null,
+///     "stays": [
+///         {
+///             "column_id": <column_id>,
+///             "column_name": <column_name>,
+///             "entered_at": <entry_time>,
+///             "left_at": <exit_time>,
+///             "moved_by": <user_id>,
+///             "hours": 26.5
+///         },
+///         ...
+///     ],
+///     "columns": [
+///         { "column_id": <column_id>, "column_name": <column_name>, "visits": 2, "hours": 30.0 },
+///         ...
+///     ]
+/// }
+/// ```
+#[get("/<board_id>/cards/<card_id>/timeline")]
+pub async fn boards_get_card_timeline(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+) -> Result<ApiResponse<CardTimeline>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = Uuid::try_parse(&card_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let timeline =
+            AnalyticsQueries::card_timeline(conn, board_id, card_id, Utc::now().naive_utc())?
+                .ok_or_else(|| ApiError::from_type(ApiErrorType::NotFound))?;
+        Ok::<CardTimeline, ApiError>(timeline)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/stale_cards?<days>
+/// Returns the open cards that have been sitting in their column for at least `days` days
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `days` - How long a card must have been idle, 7 by default
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `cards` - The stale cards, longest idle first. Cards that have not moved since the
+///   history started being recorded come last, without `entered_at` nor `idle_days`
+/// ```json
+/// [
+///     {
+///         "card_id": <card_id>,
+///         "name": <card_name>,
+///         "column_id": <column_id>,
+///         "column_name": <column_name>,
+///         "entered_at": <entry_time>,
+///         "idle_days": 12.3
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/stale_cards?<days>")]
+pub async fn boards_get_stale_cards(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    days: Option<i64>,
+) -> Result<ApiResponse<Vec<StaleCard>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let now = Utc::now().naive_utc();
+    // Negative counts and counts reaching before the start of the calendar are refused
+    let threshold = u64::try_from(days.unwrap_or(DEFAULT_STALE_DAYS))
+        .ok()
+        .and_then(|days| now.checked_sub_days(Days::new(days)))
+        .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidRequest))?;
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let cards = AnalyticsQueries::stale_cards(conn, board_id, threshold, now)?;
+        Ok::<Vec<StaleCard>, ApiError>(cards)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -5,21 +5,36 @@ use rocket::serde::json::Json;
 use uuid::Uuid;
 
 use crate::{
-    database::Db,
+    database::{
+        operation_queries::OperationQueries, webhook_queries::WebhookQueries,
+        workspace_queries::WorkspaceQueries, Db,
+    },
     errors::{ApiError, ApiErrorType},
     models::{
         api_response::ApiResponse, auth::AuthResult, Board, BoardInfo, BoardUsersRelation,
-        NewBoard, PubBoard, PubCard, PubColumn, ReturnedCard, ReturnedColumn,
+        NewBoard, PubBoard, PubCard, PubColumn, PubLane, ReturnedCard, ReturnedColumn, SELECT_COLUMN,
+        ReturnedLane, SELECT_CARD,
+    },
+    schema::{
+        board_column, board_lane, board_users_relation, boards, card_attachments, column_card,
+        files,
     },
-    schema::{board_column, board_users_relation, boards, card_attachments, column_card, files},
 };
 
+use super::helpers::attach_field_values;
+
 // TODO: extract complicated functions
 
 /// # POST /boards
-/// Creates a new board
+/// Creates a new board, every member of its workspace gets access to it
 /// # Arguments
-/// * `board` - The name of the board
+/// * `board` - The name of the board and optionally the workspace it belongs to
+/// ```json
+/// {
+///     "name": <board_name>,
+///     "workspace_id": <workspace_id>
+/// }
+/// ```
 /// * `cookies` - Takes the token of the user
 /// # Returns
 /// * `board_id` - The id of the board
@@ -33,11 +48,18 @@ pub async fn boards_create_board_and_relation(
 
     db.run(move |conn| {
         conn.transaction(|conn| {
+            let workspace_id = match board.workspace_id {
+                Some(workspace_id) => {
+                    WorkspaceQueries::membership(conn, workspace_id, token)?.workspace_id
+                }
+                None => WorkspaceQueries::personal_workspace(conn, token)?,
+            };
             let board_id = diesel::insert_into(boards::table)
                 .values(Board {
                     id: None,
                     name: board.name.to_string(),
                     creator_id: token,
+                    workspace_id,
                 })
                 .returning(boards::id)
                 .get_result::<Uuid>(conn)?;
@@ -45,8 +67,11 @@ pub async fn boards_create_board_and_relation(
                 .values(BoardUsersRelation {
                     user_id: token,
                     board_id,
+                    direct: true,
+                    via_workspace: false,
                 })
                 .execute(conn)?;
+            WorkspaceQueries::grant_members(conn, workspace_id, board_id)?;
             Ok::<Uuid, Error>(board_id)
         })
     })
@@ -55,10 +80,11 @@ pub async fn boards_create_board_and_relation(
     .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
 }
 
-/// # GET /boards
+/// # GET /boards?<workspace_id>
 /// Returns all the boards of the user
 /// # Arguments
 /// * `cookies` - Takes the token of the user
+/// * `workspace_id` - Only returns the boards of this workspace
 /// # Returns
 /// * `boards` - A list of board id's of the user
 /// ```json
@@ -66,16 +92,22 @@ pub async fn boards_create_board_and_relation(
 ///     {
 ///         "id": <board_id>,
 ///         "name": <board_name>,
+///         "workspace_id": <workspace_id>
 ///     },
 ///     ...
 /// ]
 /// ```
-#[get("/")]
+#[get("/?<workspace_id>")]
 pub async fn boards_get_boards(
     db: Db,
     auth: AuthResult,
+    workspace_id: Option<String>,
 ) -> Result<ApiResponse<Vec<PubBoard>>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
+    let workspace_id = workspace_id
+        .map(|id| Uuid::try_parse(&id))
+        .transpose()
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
 
     db.run(move |conn| {
         let ids = board_users_relation::table
@@ -83,12 +115,19 @@ pub async fn boards_get_boards(
             .select(board_users_relation::board_id)
             .load::<Uuid>(conn)?;
 
-        let bds = boards::table
-            .filter(boards::id.eq_any(ids))
-            .select((boards::id, boards::name))
-            .load::<(Uuid, String)>(conn)?
+        let mut query = boards::table.filter(boards::id.eq_any(ids)).into_boxed();
+        if let Some(workspace_id) = workspace_id {
+            query = query.filter(boards::workspace_id.eq(workspace_id));
+        }
+        let bds = query
+            .select((boards::id, boards::name, boards::workspace_id))
+            .load::<(Uuid, String, Uuid)>(conn)?
             .into_iter()
-            .map(|(id, name)| PubBoard { id, name })
+            .map(|(id, name, workspace_id)| PubBoard {
+                id,
+                name,
+                workspace_id,
+            })
             .collect();
 
         Ok::<Vec<PubBoard>, Error>(bds)
@@ -117,10 +156,19 @@ pub async fn boards_get_boards(
 ///         },
 ///         ...
 ///     ],
+///     "lanes": [
+///         {
+///             "id": <lane_id>,
+///             "name": <lane_name>,
+///             "position": <lane_position>
+///         },
+///         ...
+///     ],
 ///     "cards": [
 ///         {
 ///             "id": <card_id>,
 ///             "column_id": <column_id>,
+///             "lane_id": <lane_id>,
 ///             "description": <card_description>,
 ///             "position": <card_position>
 ///         },
@@ -152,40 +200,36 @@ pub async fn boards_get_board(
             .first::<String>(conn)?;
         let columns = board_column::table
             .filter(board_column::board_id.eq(board_id))
-            .select((board_column::id, board_column::name, board_column::position))
+            .select(SELECT_COLUMN)
             .load::<ReturnedColumn>(conn)?
             .into_iter()
-            .map(|column| PubColumn {
-                id: column.0,
-                name: column.1,
-                position: column.2,
-            })
+            .map(PubColumn::from)
             .collect::<Vec<PubColumn>>();
-        let cards = column_card::table
+        let lanes = board_lane::table
+            .filter(board_lane::board_id.eq(board_id))
+            .order(board_lane::position.asc())
+            .select((board_lane::id, board_lane::name, board_lane::position))
+            .load::<ReturnedLane>(conn)?
+            .into_iter()
+            .map(|lane| PubLane {
+                id: lane.0,
+                name: lane.1,
+                position: lane.2,
+            })
+            .collect::<Vec<PubLane>>();
+        let mut cards = column_card::table
             .filter(column_card::column_id.eq_any(columns.iter().map(|column| column.id)))
-            .select((
-                column_card::id,
-                column_card::name,
-                column_card::cover_attachment,
-                column_card::position,
-                column_card::description,
-                column_card::column_id,
-            ))
+            .select(SELECT_CARD)
             .load::<ReturnedCard>(conn)?
             .into_iter()
-            .map(|card| PubCard {
-                id: card.0,
-                name: card.1,
-                cover_attachment: card.2,
-                position: card.3,
-                description: card.4,
-                column_id: card.5,
-            })
+            .map(PubCard::from)
             .collect::<Vec<PubCard>>();
+        attach_field_values(conn, &mut cards)?;
         let board = BoardInfo {
             name: board_name,
             id: board_id,
             columns,
+            lanes,
             cards,
         };
         Ok::<BoardInfo, diesel::result::Error>(board)
@@ -214,13 +258,71 @@ pub async fn boards_update_board(
         .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
 
     db.run(move |conn| {
-        let res = diesel::update(
-            boards::table.filter(boards::id.eq(board_id).and(boards::creator_id.eq(token))),
-        )
-        .set(boards::name.eq(board))
-        .returning(boards::id)
-        .get_result::<Uuid>(conn)?;
-        Ok::<Uuid, diesel::result::Error>(res)
+        conn.transaction(|conn| {
+            let res = diesel::update(
+                boards::table.filter(boards::id.eq(board_id).and(boards::creator_id.eq(token))),
+            )
+            .set(boards::name.eq(board))
+            .returning(boards::id)
+            .get_result::<Uuid>(conn)?;
+            WebhookQueries::emit(conn, board_id, "update_board", token, &res)?;
+            Ok::<Uuid, Error>(res)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+}
+
+/// # PUT /boards/<board_id>/workspace
+/// Moves the board to another workspace, only the board creator can do it and they
+/// have to be a member of the target workspace
+///
+/// Members of the previous workspace lose access unless they are members of the new one
+/// or were invited to the board directly
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `cookies` - Takes the token of the user
+/// * `workspace_id` - The id of the target workspace
+/// # Returns
+/// * `board` - The board
+#[put("/<board_id>/workspace", data = "<workspace_id>")]
+pub async fn boards_move_to_workspace(
+    db: Db,
+    auth: AuthResult,
+    board_id: &str,
+    workspace_id: Json<Uuid>,
+) -> Result<ApiResponse<PubBoard>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            // Check if current user is board creator
+            let (name, previous) = boards::table
+                .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                .select((boards::name, boards::workspace_id))
+                .first::<(String, Uuid)>(conn)?;
+            let workspace_id =
+                WorkspaceQueries::membership(conn, workspace_id.0, token)?.workspace_id;
+
+            if previous != workspace_id {
+                WorkspaceQueries::revoke_members(conn, previous, board_id)?;
+                diesel::update(boards::table.find(board_id))
+                    .set(boards::workspace_id.eq(workspace_id))
+                    .execute(conn)?;
+                WorkspaceQueries::grant_members(conn, workspace_id, board_id)?;
+            }
+
+            let board = PubBoard {
+                id: board_id,
+                name,
+                workspace_id,
+            };
+            WebhookQueries::emit(conn, board_id, "move_to_workspace", token, &board)?;
+            Ok::<PubBoard, Error>(board)
+        })
     })
     .await
     .map(ApiResponse::new)
@@ -244,53 +346,65 @@ pub async fn boards_delete_board(
     let board_id = Uuid::try_parse(board_id)
         .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
 
-    db.run(move |conn| {
-        let column_ids = board_column::table
-            .filter(board_column::board_id.eq(board_id))
-            .select(board_column::id)
-            .load::<Uuid>(conn)?;
-        for column_id in column_ids {
-            let cards = column_card::table
-                .filter(column_card::column_id.eq(column_id))
-                .select(column_card::id)
-                .load::<Uuid>(conn)?;
-            for idx in cards {
-                let attachments = card_attachments::table
-                    .filter(card_attachments::card_id.eq(idx))
-                    .inner_join(files::table)
-                    .select((card_attachments::file_id, files::name))
-                    .load::<(Uuid, String)>(conn)?;
+    let stale_files = db
+        .run(move |conn| {
+            conn.transaction::<_, Error, _>(|conn| {
+                // Only the creator can delete the board, nothing is touched before that
+                boards::table
+                    .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                    .select(boards::id)
+                    .for_update()
+                    .first::<Uuid>(conn)?;
 
-                for (attachment, file_name) in attachments {
-                    diesel::delete(card_attachments::table)
-                        .filter(card_attachments::card_id.eq(idx))
-                        .filter(card_attachments::file_id.eq(attachment))
-                        .execute(conn)?;
-                    diesel::delete(files::table)
-                        .filter(files::id.eq(attachment))
+                let mut stale_files = OperationQueries::discard_board(conn, board_id)?;
+                let column_ids = board_column::table
+                    .filter(board_column::board_id.eq(board_id))
+                    .select(board_column::id)
+                    .load::<Uuid>(conn)?;
+                for column_id in column_ids {
+                    let cards = column_card::table
+                        .filter(column_card::column_id.eq(column_id))
+                        .select(column_card::id)
+                        .load::<Uuid>(conn)?;
+                    for idx in cards {
+                        let attachments = card_attachments::table
+                            .filter(card_attachments::card_id.eq(idx))
+                            .inner_join(files::table)
+                            .select((card_attachments::file_id, files::name))
+                            .load::<(Uuid, String)>(conn)?;
+
+                        for (attachment, file_name) in attachments {
+                            diesel::delete(card_attachments::table)
+                                .filter(card_attachments::card_id.eq(idx))
+                                .filter(card_attachments::file_id.eq(attachment))
+                                .execute(conn)?;
+                            diesel::delete(files::table)
+                                .filter(files::id.eq(attachment))
+                                .execute(conn)?;
+                            stale_files.push(format!("tmp/{}", file_name));
+                        }
+                    }
+                    diesel::delete(column_card::table)
+                        .filter(column_card::column_id.eq(column_id))
                         .execute(conn)?;
-                    std::fs::remove_file(format!("tmp/{}", file_name)).unwrap();
                 }
-            }
-            diesel::delete(column_card::table)
-                .filter(column_card::column_id.eq(column_id))
+
+                diesel::delete(board_column::table.filter(board_column::board_id.eq(board_id)))
+                    .execute(conn)?;
+                diesel::delete(
+                    board_users_relation::table.filter(board_users_relation::board_id.eq(board_id)),
+                )
                 .execute(conn)?;
-        }
+                diesel::delete(boards::table.filter(boards::id.eq(board_id))).execute(conn)?;
+                Ok(stale_files)
+            })
+        })
+        .await
+        .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))?;
 
-        diesel::delete(board_column::table.filter(board_column::board_id.eq(board_id)))
-            .execute(conn)?;
-        diesel::delete(
-            board_users_relation::table.filter(board_users_relation::board_id.eq(board_id)),
-        )
-        .execute(conn)?;
-        let id = diesel::delete(
-            boards::table.filter(boards::id.eq(board_id).and(boards::creator_id.eq(token))),
-        )
-        .returning(boards::id)
-        .get_result::<Uuid>(conn)?;
-        Ok::<Uuid, diesel::result::Error>(id)
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+    // Files only leave the disk once the deletion is committed
+    for file in stale_files {
+        let _ = std::fs::remove_file(file);
+    }
+    Ok(ApiResponse::new(board_id))
 }
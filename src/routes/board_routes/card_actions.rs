@@ -1,17 +1,36 @@
-use diesel::{result::Error, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
-use rocket::serde::json::Json;
+use std::{cmp::Ordering, sync::Arc};
+
+use chrono::Utc;
+use diesel::{
+    result::Error, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use rocket::{serde::json::Json, State};
 use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::{
-    database::Db,
+    database::{
+        analytics_queries::AnalyticsQueries, automation_queries::AutomationQueries,
+        mention_queries::MentionQueries, operation_queries::OperationQueries,
+        revision_queries::RevisionQueries, webhook_queries::WebhookQueries, Db,
+    },
     errors::{ApiError, ApiErrorType},
     models::{
-        api_response::ApiResponse,
-        auth::AuthResult,
-        BoardUsersRelation, CardInfo, ColumnCard, NewCard, PubAttachment, PubCard, ReturnedCard,
+        api_response::ApiResponse, auth::AuthResult, automation::RuleEvent, concurrency::IfMatch,
+        custom_field::compare_values,
+        operation::{CardEdit, CardPlacement, OperationKind},
+        ws_state::WsState, BoardUsersRelation, CardInfo, ColumnCard,
+        NewCard, PubAttachment, PubCard, ReturnedCard, SELECT_CARD,
+    },
+    schema::{
+        board_column, board_custom_fields, board_users_relation, card_attachments, column_card,
+        files,
     },
-    schema::{board_column, board_users_relation, card_attachments, column_card, files},
+};
+
+use super::helpers::{
+    attach_field_values, delete_card_row, load_descendants, load_field_values, to_pub_card,
+    validate_lane, validate_parent,
 };
 
 /// # POST /boards/<board_id>/columns/<column_id>/cards
@@ -38,67 +57,114 @@ pub async fn boards_create_card(
     board_id: String,
     column_id: String,
     card: Json<NewCard>,
+    ws_state: &State<Arc<WsState>>,
 ) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
 
-    db.run(move |conn| {
-        conn.transaction(|conn| {
-            let board_id = Uuid::try_parse(&board_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-            let column_id = Uuid::try_parse(&column_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-
-            let _ = board_users_relation::table
-                .filter(
-                    board_users_relation::board_id
-                        .eq(board_id)
-                        .and(board_users_relation::user_id.eq(token)),
-                )
-                .first::<BoardUsersRelation>(conn)?;
-
-            let column = board_column::table
-                .filter(board_column::id.eq(column_id))
-                .select(board_column::id)
-                .first::<Uuid>(conn)?;
-
-            let card = diesel::insert_into(column_card::table)
-                .values(ColumnCard {
-                    id: None,
-                    name: card.name.clone(),
-                    column_id: column,
-                    position: card.position,
-                    description: card.description.clone(),
-                })
-                .returning((
-                    column_card::id,
-                    column_card::name,
-                    column_card::cover_attachment,
-                    column_card::position,
-                    column_card::description,
-                    column_card::column_id,
-                ))
-                .get_result::<ReturnedCard>(conn)?;
-
-            Ok::<PubCard, Error>(PubCard {
-                id: card.0,
-                name: card.1,
-                cover_attachment: card.2,
-                position: card.3,
-                description: card.4,
-                column_id: card.5,
+    let (card, notifications) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let board_id = Uuid::try_parse(&board_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+                let column_id = Uuid::try_parse(&column_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
+
+                let column = board_column::table
+                    .filter(board_column::id.eq(column_id))
+                    .select(board_column::id)
+                    .first::<Uuid>(conn)?;
+
+                if let Some(parent_id) = card.parent_id {
+                    validate_parent(conn, board_id, None, parent_id)?;
+                }
+                if let Some(lane_id) = card.lane_id {
+                    validate_lane(conn, board_id, lane_id)?;
+                }
+
+                let card = diesel::insert_into(column_card::table)
+                    .values(ColumnCard {
+                        id: None,
+                        name: card.name.clone(),
+                        column_id: column,
+                        position: card.position,
+                        description: card.description.clone(),
+                        parent_id: card.parent_id,
+                        lane_id: card.lane_id,
+                    })
+                    .returning(SELECT_CARD)
+                    .get_result::<ReturnedCard>(conn)?;
+                AnalyticsQueries::record_movement(
+                    conn,
+                    board_id,
+                    card.0,
+                    None,
+                    Some(column),
+                    Some(token),
+                )?;
+                AutomationQueries::fire(
+                    conn,
+                    board_id,
+                    card.0,
+                    RuleEvent::CardCreated { column_id: column },
+                )?;
+                // Rules may have changed the card
+                let card = column_card::table
+                    .filter(column_card::id.eq(card.0))
+                    .select(SELECT_CARD)
+                    .first::<ReturnedCard>(conn)?;
+                OperationQueries::record(
+                    conn,
+                    board_id,
+                    token,
+                    OperationKind::CardCreated,
+                    card.0,
+                    None,
+                    Some(card.11),
+                )?;
+
+                let notifications = MentionQueries::store_card_mentions(
+                    conn,
+                    board_id,
+                    card.0,
+                    &card.1,
+                    token,
+                    card.4.as_deref().unwrap_or_default(),
+                )?;
+
+                let card = to_pub_card(conn, card)?;
+                WebhookQueries::emit(conn, board_id, "create_card", token, &card)?;
+                Ok((card, notifications))
             })
         })
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(notifications).await;
+    Ok(ApiResponse::new(card))
 }
 
-/// # GET /boards/<board_id>/columns/<column_id>/cards
+/// # GET /boards/<board_id>/columns/<column_id>/cards?<field_id>&<value>&<sort_by>&<desc>
 /// Returns all the cards in the column with the given id
+///
+/// When `field_id` is given only the cards with a value for that custom field are returned,
+/// restricted to the ones equal to `value` if it is given too. `value` is read as JSON
+/// and falls back to a plain string, so `value=3`, `value=true` and `value=High` all work.
+/// `sort_by` orders the cards by the value of a custom field, cards without one come last
 /// # Arguments
 /// * `board_id` - The id of the board
 /// * `column_id` - The id of the column
+/// * `field_id` - The id of the custom field to filter on
+/// * `value` - The value the custom field must have
+/// * `sort_by` - The id of the custom field to sort on
+/// * `desc` - Whether to sort in descending order
 /// * `auth` - Takes the token of the user
 /// # Returns
 /// * `cards` - A list of cards in the column
@@ -108,21 +174,38 @@ pub async fn boards_create_card(
 ///         "id": <card_id>,
 ///         "column_id": <column_id>,
 ///         "description": <card_description>,
-///         "position": <card_position>
+///         "position": <card_position>,
+///         "custom_fields": { <field_id>: <value>, ... }
 ///     },
 ///     ...
 /// ]
-#[get("/<board_id>/columns/<column_id>/cards")]
+#[get("/<board_id>/columns/<column_id>/cards?<field_id>&<value>&<sort_by>&<desc>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn boards_get_cards(
     db: Db,
     auth: AuthResult,
     board_id: String,
     column_id: String,
+    field_id: Option<String>,
+    value: Option<String>,
+    sort_by: Option<String>,
+    desc: Option<bool>,
 ) -> Result<ApiResponse<Vec<PubCard>>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
+    let parse_field = |field_id: Option<String>| {
+        field_id
+            .map(|field_id| {
+                Uuid::try_parse(&field_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))
+            })
+            .transpose()
+    };
+    let field_id = parse_field(field_id)?;
+    let sort_by = parse_field(sort_by)?;
+    let value = value.map(|value| serde_json::from_str(&value).unwrap_or(Value::String(value)));
 
     db.run(move |conn| {
-        conn.transaction(|conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
             let board_id = Uuid::try_parse(&board_id)
                 .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
             let column_id = Uuid::try_parse(&column_id)
@@ -141,34 +224,51 @@ pub async fn boards_get_cards(
                 .select(board_column::id)
                 .first::<Uuid>(conn)?;
 
-            let cards = column_card::table
+            for field_id in field_id.iter().chain(sort_by.iter()) {
+                board_custom_fields::table
+                    .filter(board_custom_fields::id.eq(field_id))
+                    .filter(board_custom_fields::board_id.eq(board_id))
+                    .select(board_custom_fields::id)
+                    .first::<Uuid>(conn)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::InvalidCustomField))?;
+            }
+
+            let mut cards = column_card::table
                 .filter(column_card::column_id.eq(column))
-                .select((
-                    column_card::id,
-                    column_card::name,
-                    column_card::cover_attachment,
-                    column_card::position,
-                    column_card::description,
-                    column_card::column_id,
-                ))
+                .order(column_card::position.asc())
+                .select(SELECT_CARD)
                 .get_results::<ReturnedCard>(conn)?
                 .into_iter()
-                .map(|card| PubCard {
-                    id: card.0,
-                    name: card.1,
-                    cover_attachment: card.2,
-                    position: card.3,
-                    description: card.4,
-                    column_id: card.5,
-                })
-                .collect();
-
-            Ok::<Vec<PubCard>, Error>(cards)
+                .map(PubCard::from)
+                .collect::<Vec<PubCard>>();
+            attach_field_values(conn, &mut cards)?;
+
+            if let Some(field_id) = field_id {
+                cards.retain(|card| match (card.custom_fields.get(&field_id), &value) {
+                    (Some(card_value), Some(value)) => card_value == value,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                });
+            }
+            if let Some(sort_by) = sort_by {
+                let desc = desc.unwrap_or(false);
+                cards.sort_by(|a, b| {
+                    match (a.custom_fields.get(&sort_by), b.custom_fields.get(&sort_by)) {
+                        (Some(a), Some(b)) if desc => compare_values(b, a),
+                        (Some(a), Some(b)) => compare_values(a, b),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    }
+                });
+            }
+
+            Ok(cards)
         })
     })
     .await
     .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+    .map_err(ApiResponse::from_error)
 }
 
 /// # GET /boards/<board_id>/columns/<column_id>/cards/<card_id>
@@ -179,13 +279,14 @@ pub async fn boards_get_cards(
 /// * `card_id` - The id of the card
 /// * `auth` - Takes the token of the user
 /// # Returns
-/// * `card` - The card
+/// * `card` - The card, its version is also sent as the `ETag` header
 /// ```json
 /// {
 ///     "id": <card_id>,
 ///     "column_id": <column_id>,
 ///     "description": <card_description>,
-///     "position": <card_position>
+///     "position": <card_position>,
+///     "version": <card_version>
 /// }
 /// ```
 #[get("/<board_id>/columns/<column_id>/cards/<card_id>")]
@@ -223,14 +324,7 @@ pub async fn boards_get_card(
             let card = column_card::table
                 .filter(column_card::id.eq(card_id))
                 .filter(column_card::column_id.eq(column))
-                .select((
-                    column_card::id,
-                    column_card::name,
-                    column_card::cover_attachment,
-                    column_card::position,
-                    column_card::description,
-                    column_card::column_id,
-                ))
+                .select(SELECT_CARD)
                 .first::<ReturnedCard>(conn)?;
             let attachments = card_attachments::table
                 .filter(card_attachments::card_id.eq(card_id))
@@ -241,19 +335,29 @@ pub async fn boards_get_card(
                 .map(|(id, name)| PubAttachment { id, url: name })
                 .collect::<Vec<PubAttachment>>();
 
-            Ok::<Value, Error>(json!({
-                "id": card.0,
-                "name": card.1,
-                "cover_attachment": card.2,
-                "position": card.3,
-                "description": card.4,
-                "column_id": card.5,
-                "attachments": attachments
-            }))
+            Ok::<(Value, i32), Error>((
+                json!({
+                    "id": card.0,
+                    "name": card.1,
+                    "cover_attachment": card.2,
+                    "position": card.3,
+                    "description": card.4,
+                    "column_id": card.5,
+                    "parent_id": card.6,
+                    "completed_at": card.7,
+                    "assignee_id": card.8,
+                    "lane_id": card.9,
+                    "sprint_id": card.10,
+                    "version": card.11,
+                    "custom_fields": load_field_values(conn, card.0)?,
+                    "attachments": attachments
+                }),
+                card.11,
+            ))
         })
     })
     .await
-    .map(|value| ApiResponse::new(value))
+    .map(|(value, version)| ApiResponse::new(value).with_etag(version))
     .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
 }
 
@@ -265,87 +369,141 @@ pub async fn boards_get_card(
 /// * `card_id` - The id of the card
 /// * `auth` - Takes the token of the user
 /// * `card` - The card informatioe
+/// * `if_match` - The `ETag` of the card as last read
 /// # Returns
-/// * `card` - The card
+/// * `card` - The card, its new version is also sent as the `ETag` header. A stale
+///   `If-Match` is answered with `412 Precondition Failed` and the current card
 /// ```json
 /// {
 ///     "id": <card_id>,
 ///     "column_id": <column_id>,
 ///     "description": <card_description>,
-///     "position": <card_position>
+///     "position": <card_position>,
+///     "version": <card_version>
 /// }
 /// ```
 #[put("/<board_id>/columns/<column_id>/cards/<card_id>", data = "<card>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn boards_update_card(
     db: Db,
     auth: AuthResult,
+    if_match: IfMatch,
     board_id: String,
     column_id: String,
     card_id: String,
     card: Json<CardInfo>,
+    ws_state: &State<Arc<WsState>>,
 ) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
-
-    db.run(move |conn| {
-        conn.transaction(|conn| {
-            let board_id = Uuid::try_parse(&board_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-            let column_id = Uuid::try_parse(&column_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-            let card_id = Uuid::try_parse(&card_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-
-            let _ = board_users_relation::table
-                .filter(
-                    board_users_relation::board_id
-                        .eq(board_id)
-                        .and(board_users_relation::user_id.eq(token)),
-                )
-                .first::<BoardUsersRelation>(conn)?;
-
-            let column = board_column::table
-                .filter(board_column::id.eq(column_id))
-                .select(board_column::id)
-                .first::<Uuid>(conn)?;
-
-            let card = diesel::update(column_card::table)
-                .filter(column_card::id.eq(card_id))
-                .filter(column_card::column_id.eq(column))
-                .set((
-                    column_card::name.eq(card.name.clone()),
-                    column_card::description.eq(card.description.clone()),
-                ))
-                .returning((
-                    column_card::id,
-                    column_card::name,
-                    column_card::cover_attachment,
-                    column_card::position,
-                    column_card::description,
-                    column_card::column_id,
-                ))
-                .get_result::<ReturnedCard>(conn)?;
-
-            Ok::<PubCard, Error>(PubCard {
-                id: card.0,
-                name: card.1,
-                cover_attachment: card.2,
-                position: card.3,
-                description: card.4,
-                column_id: card.5,
+    let precondition = if_match.unpack()?;
+
+    let (card, notifications, applied) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let board_id = Uuid::try_parse(&board_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+                let column_id = Uuid::try_parse(&column_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+                let card_id = Uuid::try_parse(&card_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
+
+                let column = board_column::table
+                    .filter(board_column::id.eq(column_id))
+                    .select(board_column::id)
+                    .first::<Uuid>(conn)?;
+
+                let current = column_card::table
+                    .filter(column_card::id.eq(card_id))
+                    .filter(column_card::column_id.eq(column))
+                    .select(SELECT_CARD)
+                    .for_update()
+                    .first::<ReturnedCard>(conn)?;
+                if !precondition.matches(current.11) {
+                    return Ok((to_pub_card(conn, current)?, Vec::new(), false));
+                }
+
+                let completed_at = current.7;
+                let before = CardEdit {
+                    name: current.1.clone(),
+                    description: current.4.clone(),
+                    completed_at,
+                };
+                let completed_at = match card.completed {
+                    Some(true) => completed_at.or(Some(Utc::now().naive_utc())),
+                    Some(false) => None,
+                    None => completed_at,
+                };
+
+                let card = diesel::update(column_card::table)
+                    .filter(column_card::id.eq(card_id))
+                    .filter(column_card::column_id.eq(column))
+                    .set((
+                        column_card::name.eq(card.name.clone()),
+                        column_card::description.eq(card.description.clone()),
+                        column_card::completed_at.eq(completed_at),
+                        column_card::version.eq(column_card::version + 1),
+                    ))
+                    .returning(SELECT_CARD)
+                    .get_result::<ReturnedCard>(conn)?;
+                RevisionQueries::record(
+                    conn,
+                    card.0,
+                    &before.name,
+                    before.description.as_deref(),
+                    token,
+                )?;
+                OperationQueries::record(
+                    conn,
+                    board_id,
+                    token,
+                    OperationKind::CardUpdated,
+                    card.0,
+                    serde_json::to_value(before).ok(),
+                    Some(card.11),
+                )?;
+
+                let notifications = MentionQueries::store_card_mentions(
+                    conn,
+                    board_id,
+                    card.0,
+                    &card.1,
+                    token,
+                    card.4.as_deref().unwrap_or_default(),
+                )?;
+
+                let card = to_pub_card(conn, card)?;
+                WebhookQueries::emit(conn, board_id, "update_card", token, &card)?;
+                Ok((card, notifications, true))
             })
         })
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    let version = card.version;
+    if !applied {
+        return Ok(ApiResponse::precondition_failed(card, version));
+    }
+    ws_state.notify(notifications).await;
+    Ok(ApiResponse::new(card).with_etag(version))
 }
 
-/// # PUT /boards/<board_id>/columns/<from_column_id>/cards/<card_id>/reorder
+/// # PUT /boards/<board_id>/columns/<from_column_id>/cards/<card_id>/reorder/<to_column_id>/<to_pos>?<to_lane_id>
 /// Reorders the cards in the columns
+///
+/// The card keeps its lane unless `to_lane_id` is given, `none` moves it out of any lane
 /// # Arguments
 /// * `board_id` - The id of the board
 ///  * `from_column_id` - The id of the column
 /// * `card_id` - The id of the card
+/// * `to_lane_id` - The id of the lane the card moves to
 /// * `auth` - Takes the token of the user
 /// # Returns
 /// * `reordered_cards` - The reordered cards
@@ -360,7 +518,10 @@ pub async fn boards_update_card(
 ///     ...
 /// ]
 /// ```
-#[put("/<board_id>/columns/<from_column_id>/cards/<card_id>/reorder/<to_column_id>/<to_pos>")]
+#[put(
+    "/<board_id>/columns/<from_column_id>/cards/<card_id>/reorder/<to_column_id>/<to_pos>?<to_lane_id>"
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn boards_reorder_cards(
     db: Db,
     auth: AuthResult,
@@ -369,11 +530,12 @@ pub async fn boards_reorder_cards(
     card_id: String,
     to_column_id: String,
     to_pos: i32,
+    to_lane_id: Option<String>,
 ) -> Result<ApiResponse<Vec<PubCard>>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
 
     db.run(move |conn| {
-        conn.transaction(|conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
             let board_id = Uuid::try_parse(&board_id)
                 .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
             let from_column_id = Uuid::try_parse(&from_column_id)
@@ -391,11 +553,16 @@ pub async fn boards_reorder_cards(
                 )
                 .first::<BoardUsersRelation>(conn)?;
 
-            let (card_id, pos) = column_card::table
+            let (card_id, pos, lane_id) = column_card::table
                 .filter(column_card::id.eq(card_id))
                 .filter(column_card::column_id.eq(from_column_id))
-                .select((column_card::id, column_card::position))
-                .first::<(Uuid, i32)>(conn)?;
+                .select((column_card::id, column_card::position, column_card::lane_id))
+                .first::<(Uuid, i32, Option<Uuid>)>(conn)?;
+            let before = CardPlacement {
+                column_id: from_column_id,
+                position: pos,
+                lane_id,
+            };
 
             // Update positions in the source column
             diesel::update(column_card::table)
@@ -412,58 +579,101 @@ pub async fn boards_reorder_cards(
                 .execute(conn)?;
 
             // Move the card
-            let card = diesel::update(column_card::table)
+            let mut card = diesel::update(column_card::table)
                 .filter(column_card::id.eq(card_id))
                 .set((
                     column_card::column_id.eq(to_column_id),
                     column_card::position.eq(to_pos),
+                    column_card::version.eq(column_card::version + 1),
                 ))
-                .returning((
-                    column_card::id,
-                    column_card::name,
-                    column_card::cover_attachment,
-                    column_card::position,
-                    column_card::description,
-                    column_card::column_id,
-                ))
+                .returning(SELECT_CARD)
                 .get_result::<ReturnedCard>(conn)?;
 
-            Ok::<Vec<PubCard>, Error>(vec![PubCard {
-                id: card.0,
-                name: card.1,
-                cover_attachment: card.2,
-                position: card.3,
-                description: card.4,
-                column_id: card.5,
-            }])
+            if let Some(to_lane_id) = to_lane_id {
+                let lane_id = match to_lane_id.as_str() {
+                    "none" => None,
+                    lane_id => {
+                        let lane_id = Uuid::try_parse(lane_id)
+                            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+                        validate_lane(conn, board_id, lane_id)?;
+                        Some(lane_id)
+                    }
+                };
+                card = diesel::update(column_card::table)
+                    .filter(column_card::id.eq(card_id))
+                    .set(column_card::lane_id.eq(lane_id))
+                    .returning(SELECT_CARD)
+                    .get_result::<ReturnedCard>(conn)?;
+            }
+            if from_column_id != to_column_id {
+                AnalyticsQueries::record_movement(
+                    conn,
+                    board_id,
+                    card_id,
+                    Some(from_column_id),
+                    Some(to_column_id),
+                    Some(token),
+                )?;
+                AutomationQueries::fire(
+                    conn,
+                    board_id,
+                    card_id,
+                    RuleEvent::CardEnteredColumn {
+                        column_id: to_column_id,
+                    },
+                )?;
+                // Rules may have changed the card
+                card = column_card::table
+                    .filter(column_card::id.eq(card_id))
+                    .select(SELECT_CARD)
+                    .first::<ReturnedCard>(conn)?;
+            }
+            OperationQueries::record(
+                conn,
+                board_id,
+                token,
+                OperationKind::CardMoved,
+                card_id,
+                serde_json::to_value(before).ok(),
+                Some(card.11),
+            )?;
+
+            let cards = vec![to_pub_card(conn, card)?];
+            WebhookQueries::emit(conn, board_id, "reorder_cards", token, &cards)?;
+            Ok(cards)
         })
     })
     .await
     .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+    .map_err(ApiResponse::from_error)
 }
 
-/// # DELETE /boards/<board_id>/columns/<column_id>/cards/<card_id>
+/// # DELETE /boards/<board_id>/columns/<column_id>/cards/<card_id>?<cascade>
 /// Deletes the card with the given id
+///
+/// A card that still has sub-cards is only deleted when `cascade` is set,
+/// in which case all of its descendants are deleted as well
 /// # Arguments
 /// * `board_id` - The id of the board
 /// * `column_id` - The id of the column
 /// * `card_id` - The id of the card
+/// * `cascade` - Whether to delete the sub-cards too
 /// * `auth` - Takes the token of the user
 /// # Returns
 /// * `card_id` - card id
-#[delete("/<board_id>/columns/<column_id>/cards/<card_id>")]
+#[delete("/<board_id>/columns/<column_id>/cards/<card_id>?<cascade>")]
 pub async fn boards_delete_card(
     db: Db,
     auth: AuthResult,
     board_id: String,
     column_id: String,
     card_id: String,
+    cascade: Option<bool>,
 ) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
 
     db.run(move |conn| {
-        conn.transaction(|conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
             let board_id = Uuid::try_parse(&board_id)
                 .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
             let column_id = Uuid::try_parse(&column_id)
@@ -484,49 +694,40 @@ pub async fn boards_delete_card(
                 .select(board_column::id)
                 .first::<Uuid>(conn)?;
 
-            let (card_id, pos) = column_card::table
-                .filter(column_card::id.eq(card_id))
-                .select((column_card::id, column_card::position))
-                .first::<(Uuid, i32)>(conn)?;
-
-            diesel::update(column_card::table)
-                .filter(
-                    column_card::column_id
-                        .eq(column)
-                        .and(column_card::position.gt(pos)),
-                )
-                .set(column_card::position.eq(column_card::position - 1))
-                .execute(conn)?;
-
-            let attachments = card_attachments::table
-                .filter(card_attachments::card_id.eq(card_id))
-                .inner_join(files::table)
-                .select((card_attachments::file_id, files::name))
-                .load::<(Uuid, String)>(conn)?;
-
-            for (attachment, file_name) in attachments {
-                diesel::delete(card_attachments::table)
-                    .filter(card_attachments::card_id.eq(card_id))
-                    .filter(card_attachments::file_id.eq(attachment))
-                    .execute(conn)?;
-                diesel::delete(files::table)
-                    .filter(files::id.eq(attachment))
-                    .execute(conn)?;
-                std::fs::remove_file(format!("tmp/{}", file_name)).unwrap();
-            }
-            let card = diesel::delete(column_card::table)
+            let card_id = column_card::table
                 .filter(
                     column_card::id
                         .eq(card_id)
                         .and(column_card::column_id.eq(column)),
                 )
-                .returning(column_card::id)
-                .get_result::<Uuid>(conn)?;
+                .select(column_card::id)
+                .first::<Uuid>(conn)?;
 
-            Ok::<Uuid, Error>(card)
+            let descendants = load_descendants(conn, card_id)?;
+            if !descendants.is_empty() && !cascade.unwrap_or(false) {
+                return Err(ApiError::from_type(ApiErrorType::CardHasChildren));
+            }
+            // Deepest cards go first so no parent is removed before its children
+            let mut deleted = Vec::new();
+            for descendant in descendants.into_iter().rev() {
+                deleted.push(delete_card_row(conn, board_id, descendant, token)?);
+            }
+            deleted.push(delete_card_row(conn, board_id, card_id, token)?);
+            OperationQueries::record(
+                conn,
+                board_id,
+                token,
+                OperationKind::CardDeleted,
+                card_id,
+                serde_json::to_value(deleted).ok(),
+                None,
+            )?;
+
+            WebhookQueries::emit(conn, board_id, "delete_card", token, &card_id)?;
+            Ok(card_id)
         })
     })
     .await
-    .map(|card| ApiResponse::new(card))
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
 }
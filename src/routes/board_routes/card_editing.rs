@@ -1,18 +1,28 @@
+use std::sync::Arc;
+
 use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
-use rocket::{form::Form, tokio::io::AsyncReadExt};
+use rocket::{form::Form, serde::json::Json, tokio::io::AsyncReadExt, State};
 use serde_json::{json, Value};
 use uuid::Uuid;
 
 use crate::{
-    database::Db,
+    database::{
+        mention_queries::MentionQueries, notification_queries::NotificationQueries,
+        webhook_queries::WebhookQueries, Db,
+    },
     errors::{ApiError, ApiErrorType},
     models::{
-        api_response::ApiResponse, auth::AuthResult, file::UploadedFile, BoardUsersRelation,
-        PubAttachment, ReturnedCard, UploadAttachment, SELECT_CARD,
+        api_response::ApiResponse, auth::AuthResult, file::UploadedFile,
+        notification::NotificationKind, ws_state::WsState, BoardUsersRelation, CardProgress,
+        PubAttachment, PubCard, ReturnedCard, UploadAttachment, SELECT_CARD,
     },
     schema::*,
 };
 
+use super::helpers::{
+    attach_field_values, load_descendants, load_field_values, to_pub_card, validate_parent,
+};
+
 /// # GET /boards/<board_id>/cards/<card_id>
 /// Returns the card with the given id
 /// Used only to get the card modal
@@ -21,13 +31,23 @@ use crate::{
 /// * `card_id` - The id of the card
 /// * `auth` - Takes the token of the user
 /// # Returns
-/// * `card` - The card
+/// * `card` - The card with its direct sub-cards and the completion of all its descendants,
+///   its version is also sent as the `ETag` header
 /// ```json
 /// {
 ///     "id": <card_id>,
 ///     "column_id": <column_id>,
 ///     "description": <card_description>,
-///     "position": <card_position>
+///     "position": <card_position>,
+///     "version": <card_version>,
+///     "parent_id": <parent_card_id>,
+///     "completed_at": <completion_time>,
+///     "children": [<card>, ...],
+///     "progress": {
+///         "completed": <completed_descendants>,
+///         "total": <descendants>
+///     },
+///     "mentions": [{ "user_id": <user_id>, "username": <username> }, ...]
 /// }
 /// ```
 #[get("/<board_id>/cards/<card_id>")]
@@ -55,7 +75,9 @@ pub async fn boards_get_card_by_id(
                 .first::<BoardUsersRelation>(conn)?;
 
             let card = column_card::table
+                .inner_join(board_column::table)
                 .filter(column_card::id.eq(card_id))
+                .filter(board_column::board_id.eq(board_id))
                 .select(SELECT_CARD)
                 .first::<ReturnedCard>(conn)?;
             let attachments = card_attachments::table
@@ -69,23 +91,208 @@ pub async fn boards_get_card_by_id(
                     url: name.clone(),
                 })
                 .collect::<Vec<PubAttachment>>();
+            let mut children = column_card::table
+                .filter(column_card::parent_id.eq(card_id))
+                .order(column_card::position)
+                .select(SELECT_CARD)
+                .load::<ReturnedCard>(conn)?
+                .into_iter()
+                .map(PubCard::from)
+                .collect::<Vec<PubCard>>();
+            attach_field_values(conn, &mut children)?;
+            let descendants = load_descendants(conn, card_id)?;
+            let completed = column_card::table
+                .filter(column_card::id.eq_any(&descendants))
+                .filter(column_card::completed_at.is_not_null())
+                .count()
+                .get_result::<i64>(conn)?;
+            let progress = CardProgress {
+                completed,
+                total: descendants.len() as i64,
+            };
+            let mentions = MentionQueries::load_card_mentions(conn, card.0)?;
 
-            Ok::<Value, diesel::result::Error>(json!({
-                "id": card.0,
-                "name": card.1,
-                "cover_attachment": card.2,
-                "position": card.3,
-                "description": card.4,
-                "column_id": card.5,
-                "attachments": attachments
-            }))
+            Ok::<(Value, i32), diesel::result::Error>((
+                json!({
+                    "id": card.0,
+                    "name": card.1,
+                    "cover_attachment": card.2,
+                    "position": card.3,
+                    "description": card.4,
+                    "column_id": card.5,
+                    "parent_id": card.6,
+                    "completed_at": card.7,
+                    "assignee_id": card.8,
+                    "lane_id": card.9,
+                    "sprint_id": card.10,
+                    "version": card.11,
+                    "custom_fields": load_field_values(conn, card.0)?,
+                    "attachments": attachments,
+                    "children": children,
+                    "progress": progress,
+                    "mentions": mentions
+                }),
+                card.11,
+            ))
         })
     })
     .await
-    .map(ApiResponse::new)
+    .map(|(value, version)| ApiResponse::new(value).with_etag(version))
     .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
 }
 
+/// # PUT /boards/<board_id>/cards/<card_id>/parent
+/// Moves the card under another card of the same board
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `parent_id` - The id of the new parent card, `null` to detach the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `card` - The card
+#[put("/<board_id>/cards/<card_id>/parent", data = "<parent_id>")]
+pub async fn boards_set_card_parent(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    parent_id: Json<Option<Uuid>>,
+) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            let card_id = column_card::table
+                .inner_join(board_column::table)
+                .filter(column_card::id.eq(card_id))
+                .filter(board_column::board_id.eq(board_id))
+                .select(column_card::id)
+                .first::<Uuid>(conn)?;
+            if let Some(parent_id) = parent_id.0 {
+                validate_parent(conn, board_id, Some(card_id), parent_id)?;
+            }
+
+            let card = diesel::update(column_card::table)
+                .filter(column_card::id.eq(card_id))
+                .set((
+                    column_card::parent_id.eq(parent_id.0),
+                    column_card::version.eq(column_card::version + 1),
+                ))
+                .returning(SELECT_CARD)
+                .get_result::<ReturnedCard>(conn)?;
+
+            let card = to_pub_card(conn, card)?;
+            WebhookQueries::emit(conn, board_id, "set_card_parent", token, &card)?;
+            Ok(card)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/cards/<card_id>/assignee
+/// Assigns the card to a member of the board and notifies them
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `assignee_id` - The id of the new assignee, `null` to unassign the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `card` - The card
+#[put("/<board_id>/cards/<card_id>/assignee", data = "<assignee_id>")]
+pub async fn boards_assign_card(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    board_id: String,
+    card_id: String,
+    assignee_id: Json<Option<Uuid>>,
+) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    let (card, notification) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let board_id = Uuid::try_parse(&board_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+                let card_id = Uuid::try_parse(&card_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
+
+                let (card_id, previous_assignee) = column_card::table
+                    .inner_join(board_column::table)
+                    .filter(column_card::id.eq(card_id))
+                    .filter(board_column::board_id.eq(board_id))
+                    .select((column_card::id, column_card::assignee_id))
+                    .first::<(Uuid, Option<Uuid>)>(conn)?;
+                // Only members of the board can be assigned
+                if let Some(assignee_id) = assignee_id.0 {
+                    let _ = board_users_relation::table
+                        .filter(
+                            board_users_relation::board_id
+                                .eq(board_id)
+                                .and(board_users_relation::user_id.eq(assignee_id)),
+                        )
+                        .first::<BoardUsersRelation>(conn)?;
+                }
+
+                let card = diesel::update(column_card::table)
+                    .filter(column_card::id.eq(card_id))
+                    .set((
+                        column_card::assignee_id.eq(assignee_id.0),
+                        column_card::version.eq(column_card::version + 1),
+                    ))
+                    .returning(SELECT_CARD)
+                    .get_result::<ReturnedCard>(conn)?;
+
+                let notification = match assignee_id.0 {
+                    Some(assignee_id)
+                        if assignee_id != token && previous_assignee != Some(assignee_id) =>
+                    {
+                        Some(NotificationQueries::create(
+                            conn,
+                            assignee_id,
+                            Some(token),
+                            NotificationKind::CardAssigned,
+                            json!({ "board_id": board_id, "card_id": card.0, "card_name": card.1 }),
+                        )?)
+                    }
+                    _ => None,
+                };
+
+                let card = to_pub_card(conn, card)?;
+                WebhookQueries::emit(conn, board_id, "assign_card", token, &card)?;
+                Ok((card, notification))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(notification.into_iter().collect()).await;
+    Ok(ApiResponse::new(card))
+}
+
 /// # POST /boards/<board_id>/cards/<card_id>/attachments
 /// Adds an attachment to the card
 /// # Arguments
@@ -152,9 +359,19 @@ pub async fn boards_add_attachment_to_card(
                 if cover.is_none() {
                     diesel::update(column_card::table)
                         .filter(column_card::id.eq(card_id))
-                        .set(column_card::cover_attachment.eq(file_name.clone()))
+                        .set((
+                            column_card::cover_attachment.eq(file_name.clone()),
+                            column_card::version.eq(column_card::version + 1),
+                        ))
                         .execute(conn)?;
                 }
+                WebhookQueries::emit(
+                    conn,
+                    board_id,
+                    "add_attachment_to_card",
+                    uploader_id,
+                    &json!({ "card_id": card_id, "attachment_id": new_attachment.id }),
+                )?;
 
                 Ok::<(), diesel::result::Error>(())
             })
@@ -293,9 +510,19 @@ pub async fn boards_delete_attachment_of_card(
                 if cover.is_some() {
                     diesel::update(column_card::table)
                         .filter(column_card::id.eq(card_id))
-                        .set(column_card::cover_attachment.eq(None::<String>))
+                        .set((
+                            column_card::cover_attachment.eq(None::<String>),
+                            column_card::version.eq(column_card::version + 1),
+                        ))
                         .execute(conn)?;
                 }
+                WebhookQueries::emit(
+                    conn,
+                    board_id,
+                    "delete_attachment_of_card",
+                    token,
+                    &json!({ "card_id": card_id, "attachment_id": attachment_id }),
+                )?;
                 Ok::<String, diesel::result::Error>(file_name)
             })
         })
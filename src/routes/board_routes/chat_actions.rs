@@ -0,0 +1,88 @@
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use uuid::Uuid;
+
+use crate::{
+    database::{chat_queries::ChatQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        messages::BoardChat,
+        user::{PubUser, User},
+        BoardUsersRelation,
+    },
+    schema::{board_users_relation, users},
+};
+
+/// # GET /boards/<board_id>/chat
+/// Returns the chat of the board, created on first use
+///
+/// Every member of the board is a member of its chat, the same conversation id is used
+/// with `/chat_source/events` and `/chat_source/last_messages`. Users removed from the
+/// board lose access to the chat right away
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `chat` - The conversation and its members
+/// ```json
+/// {
+///     "conversation": {
+///         "id": <conversation_id>,
+///         "member_one": null,
+///         "member_two": null,
+///         "board_id": <board_id>
+///     },
+///     "members": [
+///         {
+///             "id": <user_id>,
+///             "username": <username>,
+///             ...
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+#[get("/<board_id>/chat")]
+pub async fn boards_get_chat(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<BoardChat>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            let conversation = ChatQueries::board_conversation(conn, board_id)?;
+            let members = ChatQueries::members(conn, conversation.id)?;
+            let members = users::table
+                .filter(users::id.eq_any(members))
+                .order(users::username.asc())
+                .select(User::as_select())
+                .load::<User>(conn)?
+                .into_iter()
+                .map(PubUser::from)
+                .collect();
+
+            Ok(BoardChat {
+                conversation,
+                members,
+            })
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -1,20 +1,24 @@
-use diesel::{result::Error, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
-use rocket::serde::json::Json;
+use std::sync::Arc;
+
+use diesel::{
+    result::Error, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use rocket::{serde::json::Json, State};
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
-    database::Db,
+    database::{notification_queries::NotificationQueries, webhook_queries::WebhookQueries, Db},
     errors::{ApiError, ApiErrorType},
     models::{
-        api_response::ApiResponse,
-        auth::AuthResult,
-        BoardUsersRelation,
+        api_response::ApiResponse, auth::AuthResult, notification::NotificationKind,
+        ws_state::WsState, BoardUsersRelation,
     },
     schema::{board_users_relation, boards},
 };
 
 /// # POST /boards/<board_id>/collaborators
-/// Adds a collaborator to the board with the given id
+/// Adds a collaborator to the board with the given id and notifies them
 /// # Arguments
 /// * `board_id` - The id of the board
 /// * `auth` - Takes the token of the user
@@ -24,45 +28,64 @@ use crate::{
 pub async fn boards_add_collaborator(
     db: Db,
     auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
     board_id: String,
     collaborator_id: Json<Uuid>,
 ) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
     let token = auth.unpack()?.id;
 
-    db.run(move |conn| {
-        conn.transaction(|conn| {
-            let board_id = Uuid::try_parse(&board_id)
-                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
-
-            // Check if current user is board member
-            let _ = board_users_relation::table
-                .filter(
-                    board_users_relation::board_id
-                        .eq(board_id)
-                        .and(board_users_relation::user_id.eq(token)),
-                )
-                .first::<BoardUsersRelation>(conn)?;
-
-            // Check if current user is board creator
-            let _ = boards::table
-                .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
-                .select(boards::id)
-                .first::<Uuid>(conn)?;
-
-            let collaborator = diesel::insert_into(board_users_relation::table)
-                .values(BoardUsersRelation {
-                    board_id,
-                    user_id: collaborator_id.0,
-                })
-                .returning(board_users_relation::user_id)
-                .get_result::<Uuid>(conn)?;
-
-            Ok::<Uuid, Error>(collaborator)
+    let (collaborator, notification) = db
+        .run(move |conn| {
+            conn.transaction(|conn| {
+                let board_id = Uuid::try_parse(&board_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+                // Check if current user is board member
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
+
+                // Check if current user is board creator
+                let board_name = boards::table
+                    .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                    .select(boards::name)
+                    .first::<String>(conn)?;
+
+                let collaborator = diesel::insert_into(board_users_relation::table)
+                    .values(BoardUsersRelation {
+                        board_id,
+                        user_id: collaborator_id.0,
+                        direct: true,
+                        via_workspace: false,
+                    })
+                    .on_conflict((board_users_relation::board_id, board_users_relation::user_id))
+                    .do_update()
+                    .set(board_users_relation::direct.eq(true))
+                    .returning(board_users_relation::user_id)
+                    .get_result::<Uuid>(conn)?;
+
+                let notification = NotificationQueries::create(
+                    conn,
+                    collaborator,
+                    Some(token),
+                    NotificationKind::AddedToBoard,
+                    json!({ "board_id": board_id, "board_name": board_name }),
+                )?;
+
+                WebhookQueries::emit(conn, board_id, "add_collaborator", token, &collaborator)?;
+
+                Ok::<_, Error>((collaborator, notification))
+            })
         })
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+        .await
+        .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))?;
+
+    ws_state.notify(vec![notification]).await;
+    Ok(ApiResponse::new(collaborator))
 }
 
 /// # GET /boards/<board_id>/collaborators
@@ -158,6 +181,8 @@ pub async fn boards_get_collaborator(
 
 /// # DELETE /boards/<board_id>/collaborators/<collaborator_id>
 /// Removes the collaborator with the given id from the board
+///
+/// A collaborator who is also a member of the workspace of the board keeps access through it
 /// # Arguments
 /// * `board_id` - The id of the board
 /// * `collaborator_id` - The id of the collaborator
@@ -187,12 +212,28 @@ pub async fn boards_remove_collaborator(
             let collaborator_id = Uuid::try_parse(&collaborator_id)
                 .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
 
+            // Members of the workspace of the board keep the access they get through it
             diesel::delete(
+                board_users_relation::table
+                    .filter(board_users_relation::board_id.eq(board_id))
+                    .filter(board_users_relation::user_id.eq(collaborator_id))
+                    .filter(board_users_relation::via_workspace.eq(false)),
+            )
+            .execute(conn)?;
+            diesel::update(
                 board_users_relation::table
                     .filter(board_users_relation::board_id.eq(board_id))
                     .filter(board_users_relation::user_id.eq(collaborator_id)),
             )
+            .set(board_users_relation::direct.eq(false))
             .execute(conn)?;
+            WebhookQueries::emit(
+                conn,
+                board_id,
+                "remove_collaborator",
+                token,
+                &collaborator_id,
+            )?;
 
             Ok::<Uuid, Error>(collaborator_id)
         })
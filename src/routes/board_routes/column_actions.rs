@@ -3,11 +3,17 @@ use rocket::serde::json::Json;
 use uuid::Uuid;
 
 use crate::{
-    database::Db,
+    database::{
+        analytics_queries::AnalyticsQueries, operation_queries::OperationQueries,
+        webhook_queries::WebhookQueries, Db,
+    },
     errors::{ApiError, ApiErrorType},
     models::{
-        api_response::ApiResponse, auth::AuthResult, BoardColumn, BoardUsersRelation, NewColumn,
-        PubColumn, ReturnedColumn,
+        api_response::ApiResponse,
+        auth::AuthResult,
+        concurrency::IfMatch,
+        operation::{ColumnEdit, ColumnSnapshot, OperationKind},
+        BoardColumn, BoardUsersRelation, NewColumn, PubColumn, ReturnedColumn, SELECT_COLUMN,
     },
     schema::{board_column, board_users_relation, card_attachments, column_card, files},
 };
@@ -49,6 +55,17 @@ pub async fn boards_create_column(
                 })
                 .returning(board_column::id)
                 .get_result::<Uuid>(conn)?;
+            OperationQueries::record(
+                conn,
+                board_id,
+                token,
+                OperationKind::ColumnCreated,
+                column_id,
+                None,
+                Some(1),
+            )?;
+
+            WebhookQueries::emit(conn, board_id, "create_column", token, &column_id)?;
 
             Ok::<Uuid, diesel::result::Error>(column_id)
         })
@@ -96,14 +113,10 @@ pub async fn boards_get_columns(
 
         let columns = board_column::table
             .filter(board_column::board_id.eq(board_id))
-            .select((board_column::id, board_column::name, board_column::position))
+            .select(SELECT_COLUMN)
             .load::<ReturnedColumn>(conn)?
             .into_iter()
-            .map(|col| PubColumn {
-                id: col.0,
-                name: col.1,
-                position: col.2,
-            })
+            .map(PubColumn::from)
             .collect::<Vec<PubColumn>>();
 
         Ok::<Vec<PubColumn>, diesel::result::Error>(columns)
@@ -120,12 +133,13 @@ pub async fn boards_get_columns(
 /// * `column_id` - The id of the column
 /// * `auth` - Takes the token of the user
 /// # Returns
-/// * `column` - The column
+/// * `column` - The column, its version is also sent as the `ETag` header
 /// ```json
 /// {
 ///     "id": <column_id>,
 ///     "name": <column_name>,
-///     "position": <column_position>
+///     "position": <column_position>,
+///     "version": <column_version>
 /// }
 /// ```
 #[get("/<board_id>/columns/<column_id>")]
@@ -153,39 +167,44 @@ pub async fn boards_get_column(
                 board_column::id.eq(Uuid::try_parse(&column_id)
                     .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?),
             )
-            .select((board_column::id, board_column::name, board_column::position))
+            .select(SELECT_COLUMN)
             .first::<ReturnedColumn>(conn)?;
 
-        Ok::<PubColumn, diesel::result::Error>(PubColumn {
-            id: column.0,
-            name: column.1,
-            position: column.2,
-        })
+        Ok::<PubColumn, diesel::result::Error>(PubColumn::from(column))
     })
     .await
-    .map(ApiResponse::new)
+    .map(|column| {
+        let version = column.version;
+        ApiResponse::new(column).with_etag(version)
+    })
     .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
 }
 
 /// # PUT /boards/<board_id>/columns/<column_id>
 /// Updates the column with the given id
+///
+/// Requires the `If-Match` header with the version the edit is based on, a stale
+/// version is answered with `412 Precondition Failed` and the current column
 /// # Arguments
 /// * `board_id` - The id of the board
 /// * `column_id` - The id of the column
 /// * `auth` - Takes the token of the user
+/// * `if_match` - The `ETag` of the column as last read
 /// # Returns
-/// * `column` - The column
+/// * `column` - The column, its new version is also sent as the `ETag` header
 /// ```json
 /// {
 ///     "id": <column_id>,
 ///     "name": <column_name>,
-///     "position": <column_position>
+///     "position": <column_position>,
+///     "version": <column_version>
 /// }
 /// ```
 #[put("/<board_id>/columns/<column_id>", data = "<column>")]
 pub async fn boards_update_column(
     db: Db,
     auth: AuthResult,
+    if_match: IfMatch,
     board_id: String,
     column_id: String,
     column: Json<NewColumn>,
@@ -193,37 +212,68 @@ pub async fn boards_update_column(
     let token = auth.unpack()?.id;
     let board_id = Uuid::try_parse(&board_id)
         .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let column_id = Uuid::try_parse(&column_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let precondition = if_match.unpack()?;
 
-    db.run(move |conn| {
-        let _ = board_users_relation::table
-            .filter(
-                board_users_relation::board_id
-                    .eq(board_id)
-                    .and(board_users_relation::user_id.eq(token)),
-            )
-            .first::<BoardUsersRelation>(conn)?;
+    let (column, applied) = db
+        .run(move |conn| {
+            conn.transaction(|conn| {
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
 
-        let column = diesel::update(board_column::table)
-            .filter(
-                board_column::id.eq(Uuid::try_parse(&column_id)
-                    .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?),
-            )
-            .set((
-                board_column::name.eq(column.name.clone()),
-                board_column::position.eq(column.position),
-            ))
-            .returning((board_column::id, board_column::name, board_column::position))
-            .get_result::<ReturnedColumn>(conn)?;
+                let current = board_column::table
+                    .filter(board_column::id.eq(column_id))
+                    .filter(board_column::board_id.eq(board_id))
+                    .select(SELECT_COLUMN)
+                    .for_update()
+                    .first::<ReturnedColumn>(conn)?;
+                if !precondition.matches(current.3) {
+                    return Ok((PubColumn::from(current), false));
+                }
+                let before = ColumnEdit {
+                    name: current.1.clone(),
+                    position: current.2,
+                };
 
-        Ok::<PubColumn, diesel::result::Error>(PubColumn {
-            id: column.0,
-            name: column.1,
-            position: column.2,
+                let column = diesel::update(board_column::table)
+                    .filter(board_column::id.eq(column_id))
+                    .set((
+                        board_column::name.eq(column.name.clone()),
+                        board_column::position.eq(column.position),
+                        board_column::version.eq(board_column::version + 1),
+                    ))
+                    .returning(SELECT_COLUMN)
+                    .get_result::<ReturnedColumn>(conn)?;
+                OperationQueries::record(
+                    conn,
+                    board_id,
+                    token,
+                    OperationKind::ColumnUpdated,
+                    column_id,
+                    serde_json::to_value(before).ok(),
+                    Some(column.3),
+                )?;
+
+                let column = PubColumn::from(column);
+                WebhookQueries::emit(conn, board_id, "update_column", token, &column)?;
+
+                Ok::<_, diesel::result::Error>((column, true))
+            })
         })
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))
+        .await
+        .map_err(|e| ApiResponse::from_error(ApiError::from_error(e)))?;
+
+    let version = column.version;
+    if !applied {
+        return Ok(ApiResponse::precondition_failed(column, version));
+    }
+    Ok(ApiResponse::new(column).with_etag(version))
 }
 
 /// # DELETE /boards/<board_id>/columns/<column_id>
@@ -251,6 +301,8 @@ pub async fn boards_delete_column(
     let token = auth.unpack()?.id;
     let board_id = Uuid::try_parse(&board_id)
         .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let column_id = Uuid::try_parse(&column_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
 
     db.run(move |conn| {
         conn.transaction(|conn| {
@@ -262,52 +314,60 @@ pub async fn boards_delete_column(
                 )
                 .first::<BoardUsersRelation>(conn)?;
 
-            let cards = column_card::table
-                .filter(
-                    column_card::column_id.eq(Uuid::try_parse(&column_id)
-                        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?),
-                )
-                .select((column_card::id, column_card::name))
-                .load::<(Uuid, String)>(conn)?;
+            let card_ids = column_card::table
+                .filter(column_card::column_id.eq(column_id))
+                .order(column_card::position.asc())
+                .select(column_card::id)
+                .load::<Uuid>(conn)?;
 
-            for (card_id, _) in cards {
-                let attachments = card_attachments::table
+            // Attachment files stay on disk until the deletion can't be undone anymore
+            let mut cards = Vec::new();
+            for card_id in card_ids {
+                let card = OperationQueries::snapshot_card(conn, card_id)?;
+                diesel::delete(card_attachments::table)
                     .filter(card_attachments::card_id.eq(card_id))
-                    .inner_join(files::table)
-                    .select((card_attachments::file_id, files::name))
-                    .load::<(Uuid, String)>(conn)?;
-
-                for (attachment, file_name) in attachments {
-                    diesel::delete(card_attachments::table)
-                        .filter(card_attachments::card_id.eq(card_id))
-                        .filter(card_attachments::file_id.eq(attachment))
-                        .execute(conn)?;
-                    diesel::delete(files::table)
-                        .filter(files::id.eq(attachment))
-                        .execute(conn)?;
-                    std::fs::remove_file(format!("tmp/{}", file_name)).unwrap();
-                }
+                    .execute(conn)?;
+                diesel::delete(files::table)
+                    .filter(files::id.eq_any(card.attachments.iter().map(|file| file.id)))
+                    .execute(conn)?;
+                AnalyticsQueries::record_movement(
+                    conn,
+                    board_id,
+                    card_id,
+                    Some(column_id),
+                    None,
+                    Some(token),
+                )?;
+                cards.push(card);
             }
             let _ = diesel::delete(column_card::table)
-                .filter(
-                    column_card::column_id.eq(Uuid::try_parse(&column_id)
-                        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?),
-                )
+                .filter(column_card::column_id.eq(column_id))
                 .execute(conn)?;
 
             let column = diesel::delete(board_column::table)
-                .filter(
-                    board_column::id.eq(Uuid::try_parse(&column_id)
-                        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?),
-                )
-                .returning((board_column::id, board_column::name, board_column::position))
+                .filter(board_column::id.eq(column_id))
+                .returning(SELECT_COLUMN)
                 .get_result::<ReturnedColumn>(conn)?;
-
-            Ok::<PubColumn, diesel::result::Error>(PubColumn {
+            let snapshot = ColumnSnapshot {
                 id: column.0,
-                name: column.1,
+                name: column.1.clone(),
                 position: column.2,
-            })
+                cards,
+            };
+            OperationQueries::record(
+                conn,
+                board_id,
+                token,
+                OperationKind::ColumnDeleted,
+                column_id,
+                serde_json::to_value(snapshot).ok(),
+                None,
+            )?;
+
+            let column = PubColumn::from(column);
+            WebhookQueries::emit(conn, board_id, "delete_column", token, &column)?;
+
+            Ok::<PubColumn, diesel::result::Error>(column)
         })
     })
     .await
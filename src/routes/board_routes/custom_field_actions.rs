@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use diesel::{
+    upsert::excluded, BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl,
+    RunQueryDsl, SelectableHelper,
+};
+use rocket::serde::json::Json;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    database::{webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        custom_field::{CardFieldValue, CustomField, CustomFieldInfo, FieldKind, NewCustomField},
+        BoardUsersRelation,
+    },
+    schema::{board_custom_fields, board_users_relation, boards, card_field_values, column_card},
+};
+
+use super::helpers::{card_on_board, load_field_values};
+
+/// Checks that the name is free on the board and that the options suit the kind
+fn validate_field(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    field_id: Option<Uuid>,
+    name: &str,
+    kind: FieldKind,
+    options: &[String],
+) -> Result<(), ApiError> {
+    let invalid = || ApiError::from_type(ApiErrorType::InvalidCustomField);
+    if name.trim().is_empty() {
+        return Err(invalid());
+    }
+    if kind == FieldKind::SingleSelect && options.is_empty() {
+        return Err(invalid());
+    }
+    let taken = board_custom_fields::table
+        .filter(board_custom_fields::board_id.eq(board_id))
+        .filter(board_custom_fields::name.eq(name))
+        .filter(board_custom_fields::id.ne(field_id.unwrap_or_default()))
+        .count()
+        .get_result::<i64>(conn)?;
+    if taken > 0 {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// # POST /boards/<board_id>/fields
+/// Defines a new custom field on the board, only the creator of the board can do it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// * `field` - The field, `kind` is one of `text`, `number`, `date`, `single_select` or `checkbox`
+/// ```json
+/// {
+///     "name": "Priority",
+///     "kind": "single_select",
+///     "options": ["Low", "Medium", "High"],
+///     "position": 0
+/// }
+/// ```
+/// # Returns
+/// * `field` - The field
+#[post("/<board_id>/fields", data = "<field>")]
+pub async fn boards_create_custom_field(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    field: Json<NewCustomField>,
+) -> Result<ApiResponse<CustomField>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            // Check if current user is board creator
+            let _ = boards::table
+                .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                .select(boards::id)
+                .first::<Uuid>(conn)?;
+
+            let options = match field.kind {
+                FieldKind::SingleSelect => field.options.clone(),
+                _ => Vec::new(),
+            };
+            validate_field(conn, board_id, None, &field.name, field.kind, &options)?;
+
+            let field = diesel::insert_into(board_custom_fields::table)
+                .values(CustomField {
+                    id: Uuid::new_v4(),
+                    board_id,
+                    name: field.name.trim().to_string(),
+                    kind: field.kind.as_str().to_string(),
+                    options: Value::from(options),
+                    position: field.position,
+                })
+                .get_result::<CustomField>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "create_custom_field", token, &field)?;
+
+            Ok(field)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/fields
+/// Returns the custom fields of the board, in order
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `fields` - A list of the fields of the board
+/// ```json
+/// [
+///     {
+///         "id": <field_id>,
+///         "board_id": <board_id>,
+///         "name": "Priority",
+///         "kind": "single_select",
+///         "options": ["Low", "Medium", "High"],
+///         "position": 0
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/fields")]
+pub async fn boards_get_custom_fields(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<Vec<CustomField>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let fields = board_custom_fields::table
+            .filter(board_custom_fields::board_id.eq(board_id))
+            .order((
+                board_custom_fields::position.asc(),
+                board_custom_fields::name.asc(),
+            ))
+            .select(CustomField::as_select())
+            .load::<CustomField>(conn)?;
+
+        Ok::<Vec<CustomField>, ApiError>(fields)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/fields/<field_id>
+/// Renames, reorders or changes the options of the custom field, only the creator of the board can do it
+///
+/// Values of a single-select field that are no longer among its options are cleared
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `field_id` - The id of the field
+/// * `auth` - Takes the token of the user
+/// * `field` - The new name, options and position of the field
+/// # Returns
+/// * `field` - The field
+#[put("/<board_id>/fields/<field_id>", data = "<field>")]
+pub async fn boards_update_custom_field(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    field_id: String,
+    field: Json<CustomFieldInfo>,
+) -> Result<ApiResponse<CustomField>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let field_id = Uuid::try_parse(&field_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            // Check if current user is board creator
+            let _ = boards::table
+                .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                .select(boards::id)
+                .first::<Uuid>(conn)?;
+
+            let current = board_custom_fields::table
+                .filter(board_custom_fields::id.eq(field_id))
+                .filter(board_custom_fields::board_id.eq(board_id))
+                .select(CustomField::as_select())
+                .first::<CustomField>(conn)?;
+            let kind = current.kind()?;
+            let options = match kind {
+                FieldKind::SingleSelect => field.options.clone(),
+                _ => Vec::new(),
+            };
+            validate_field(conn, board_id, Some(field_id), &field.name, kind, &options)?;
+
+            if kind == FieldKind::SingleSelect {
+                let removed = current
+                    .options()
+                    .into_iter()
+                    .filter(|option| !options.contains(option))
+                    .map(Value::from)
+                    .collect::<Vec<Value>>();
+                diesel::delete(card_field_values::table)
+                    .filter(card_field_values::field_id.eq(field_id))
+                    .filter(card_field_values::value.eq_any(removed))
+                    .execute(conn)?;
+            }
+
+            let field = diesel::update(board_custom_fields::table.find(field_id))
+                .set((
+                    board_custom_fields::name.eq(field.name.trim()),
+                    board_custom_fields::options.eq(Value::from(options)),
+                    board_custom_fields::position.eq(field.position),
+                ))
+                .get_result::<CustomField>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "update_custom_field", token, &field)?;
+
+            Ok(field)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/fields/<field_id>
+/// Deletes the custom field and its values on every card, only the creator of the board can do it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `field_id` - The id of the field
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `field_id` - The id of the deleted field
+#[delete("/<board_id>/fields/<field_id>")]
+pub async fn boards_delete_custom_field(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    field_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let field_id = Uuid::try_parse(&field_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            // Check if current user is board creator
+            let _ = boards::table
+                .filter(boards::id.eq(board_id).and(boards::creator_id.eq(token)))
+                .select(boards::id)
+                .first::<Uuid>(conn)?;
+
+            let field_id = diesel::delete(board_custom_fields::table)
+                .filter(board_custom_fields::id.eq(field_id))
+                .filter(board_custom_fields::board_id.eq(board_id))
+                .returning(board_custom_fields::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "delete_custom_field", token, &field_id)?;
+
+            Ok::<Uuid, ApiError>(field_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/cards/<card_id>/fields/<field_id>
+/// Sets the value of a custom field on the card, `null` clears it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `field_id` - The id of the field
+/// * `auth` - Takes the token of the user
+/// * `value` - The value, it must match the kind of the field
+/// # Returns
+/// * `custom_fields` - All the custom field values of the card
+/// ```json
+/// {
+///     <field_id>: <value>,
+///     ...
+/// }
+/// ```
+#[put("/<board_id>/cards/<card_id>/fields/<field_id>", data = "<value>")]
+pub async fn boards_set_card_field(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    field_id: String,
+    value: Json<Value>,
+) -> Result<ApiResponse<HashMap<Uuid, Value>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let field_id = Uuid::try_parse(&field_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            let field = board_custom_fields::table
+                .filter(board_custom_fields::id.eq(field_id))
+                .filter(board_custom_fields::board_id.eq(board_id))
+                .select(CustomField::as_select())
+                .first::<CustomField>(conn)?;
+
+            let value = value.into_inner();
+            if value.is_null() {
+                diesel::delete(card_field_values::table)
+                    .filter(card_field_values::card_id.eq(card_id))
+                    .filter(card_field_values::field_id.eq(field_id))
+                    .execute(conn)?;
+            } else {
+                field.kind()?.validate(&value, &field.options())?;
+                diesel::insert_into(card_field_values::table)
+                    .values(CardFieldValue {
+                        card_id,
+                        field_id,
+                        value,
+                    })
+                    .on_conflict((card_field_values::card_id, card_field_values::field_id))
+                    .do_update()
+                    .set(card_field_values::value.eq(excluded(card_field_values::value)))
+                    .execute(conn)?;
+            }
+            diesel::update(column_card::table)
+                .filter(column_card::id.eq(card_id))
+                .set(column_card::version.eq(column_card::version + 1))
+                .execute(conn)?;
+
+            let values = load_field_values(conn, card_id)?;
+            WebhookQueries::emit(
+                conn,
+                board_id,
+                "set_card_field",
+                token,
+                &json!({ "card_id": card_id, "custom_fields": values }),
+            )?;
+
+            Ok(values)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate, Utc};
+use diesel::{
+    result::{DatabaseErrorKind, Error},
+    ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        analytics_queries::AnalyticsQueries, automation_queries::AutomationQueries,
+        operation_queries::OperationQueries,
+    },
+    errors::{ApiError, ApiErrorType},
+    models::{automation::RuleEvent, operation::CardSnapshot, PubCard, ReturnedCard},
+    schema::{board_column, board_lane, card_attachments, card_field_values, column_card, files},
+};
+
+/// Number of days covered when no range is given
+const DEFAULT_RANGE_DAYS: u64 = 30;
+/// Longest range that can be requested at once
+const MAX_RANGE_DAYS: i64 = 366;
+
+fn parse_date(date: Option<String>) -> Result<Option<NaiveDate>, ApiError> {
+    date.map(|date| {
+        NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| ApiError::from_type(ApiErrorType::InvalidDateRange))
+    })
+    .transpose()
+}
+
+/// Maps a unique violation to `error_type`, for a write guarded by a check that a
+/// concurrent request can pass at the same time
+pub fn unique_violation_as(error_type: ApiErrorType) -> impl FnOnce(Error) -> ApiError {
+    move |error| match error {
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            ApiError::from_type(error_type)
+        }
+        error => ApiError::from(error),
+    }
+}
+
+/// Parses an inclusive range of `YYYY-MM-DD` days from query parameters
+///
+/// The range ends today and covers the last 30 days unless told otherwise,
+/// and cannot exceed a year
+pub fn parse_date_range(
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(NaiveDate, NaiveDate), ApiError> {
+    let to = parse_date(to)?.unwrap_or_else(|| Utc::now().date_naive());
+    let from = parse_date(from)?.unwrap_or(to - Days::new(DEFAULT_RANGE_DAYS - 1));
+    if !(0..MAX_RANGE_DAYS).contains(&(to - from).num_days()) {
+        return Err(ApiError::from_type(ApiErrorType::InvalidDateRange));
+    }
+    Ok((from, to))
+}
+
+/// Returns the ids of every card below the given one, closest first
+pub fn load_descendants(conn: &mut PgConnection, card_id: Uuid) -> QueryResult<Vec<Uuid>> {
+    let mut descendants = Vec::new();
+    let mut frontier = vec![card_id];
+    while !frontier.is_empty() {
+        frontier = column_card::table
+            .filter(column_card::parent_id.eq_any(&frontier))
+            .select(column_card::id)
+            .load::<Uuid>(conn)?;
+        descendants.extend(frontier.iter().copied());
+    }
+    Ok(descendants)
+}
+
+/// Checks that the card lives in one of the columns of the board
+pub fn card_on_board(conn: &mut PgConnection, board_id: Uuid, card_id: Uuid) -> QueryResult<bool> {
+    column_card::table
+        .inner_join(board_column::table)
+        .filter(column_card::id.eq(card_id))
+        .filter(board_column::board_id.eq(board_id))
+        .count()
+        .get_result::<i64>(conn)
+        .map(|count| count > 0)
+}
+
+/// Checks that `parent_id` can become the parent of `card_id`
+///
+/// The parent has to be on the same board and must be neither the card itself
+/// nor one of its descendants, so the hierarchy never forms a cycle
+pub fn validate_parent(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    card_id: Option<Uuid>,
+    parent_id: Uuid,
+) -> Result<(), ApiError> {
+    if !card_on_board(conn, board_id, parent_id)? {
+        return Err(ApiError::from_type(ApiErrorType::InvalidParentCard));
+    }
+    if let Some(card_id) = card_id {
+        if card_id == parent_id || load_descendants(conn, card_id)?.contains(&parent_id) {
+            return Err(ApiError::from_type(ApiErrorType::InvalidParentCard));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the lane belongs to the board
+pub fn validate_lane(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    lane_id: Uuid,
+) -> Result<(), ApiError> {
+    let _ = board_lane::table
+        .filter(board_lane::id.eq(lane_id))
+        .filter(board_lane::board_id.eq(board_id))
+        .select(board_lane::id)
+        .first::<Uuid>(conn)
+        .map_err(|_| ApiError::from_type(ApiErrorType::InvalidLane))?;
+    Ok(())
+}
+
+/// Returns the custom field values of the card, by field id
+pub fn load_field_values(
+    conn: &mut PgConnection,
+    card_id: Uuid,
+) -> QueryResult<HashMap<Uuid, Value>> {
+    card_field_values::table
+        .filter(card_field_values::card_id.eq(card_id))
+        .select((card_field_values::field_id, card_field_values::value))
+        .load::<(Uuid, Value)>(conn)
+        .map(|values| values.into_iter().collect())
+}
+
+/// Converts a card row and loads its custom field values
+pub fn to_pub_card(conn: &mut PgConnection, card: ReturnedCard) -> QueryResult<PubCard> {
+    let mut card = PubCard::from(card);
+    card.custom_fields = load_field_values(conn, card.id)?;
+    Ok(card)
+}
+
+/// Fills `custom_fields` on every card with a single query
+pub fn attach_field_values(conn: &mut PgConnection, cards: &mut [PubCard]) -> QueryResult<()> {
+    let values = card_field_values::table
+        .filter(card_field_values::card_id.eq_any(cards.iter().map(|card| card.id)))
+        .select((
+            card_field_values::card_id,
+            card_field_values::field_id,
+            card_field_values::value,
+        ))
+        .load::<(Uuid, Uuid, Value)>(conn)?;
+    let mut by_card: HashMap<Uuid, HashMap<Uuid, Value>> = HashMap::new();
+    for (card_id, field_id, value) in values {
+        by_card.entry(card_id).or_default().insert(field_id, value);
+    }
+    for card in cards.iter_mut() {
+        card.custom_fields = by_card.remove(&card.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Moves the card to the end of another column of the board and closes the gap it leaves
+///
+/// The rules of the board that react to the card entering the column run too
+pub fn move_card_to_column_end(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    card_id: Uuid,
+    to_column_id: Uuid,
+    moved_by: Option<Uuid>,
+) -> QueryResult<()> {
+    if AutomationQueries::move_to_column_end(conn, board_id, card_id, to_column_id, moved_by)? {
+        AutomationQueries::fire(
+            conn,
+            board_id,
+            card_id,
+            RuleEvent::CardEnteredColumn {
+                column_id: to_column_id,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Deletes the card with its attachments and closes the gap it leaves in its column
+///
+/// The attachment files stay on disk so the returned snapshot can bring the card back,
+/// they are removed once the deletion can't be undone anymore
+pub fn delete_card_row(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    card_id: Uuid,
+    deleted_by: Uuid,
+) -> QueryResult<CardSnapshot> {
+    let snapshot = OperationQueries::snapshot_card(conn, card_id)?;
+
+    diesel::update(column_card::table)
+        .filter(column_card::column_id.eq(snapshot.column_id))
+        .filter(column_card::position.gt(snapshot.position))
+        .set(column_card::position.eq(column_card::position - 1))
+        .execute(conn)?;
+
+    diesel::delete(card_attachments::table)
+        .filter(card_attachments::card_id.eq(card_id))
+        .execute(conn)?;
+    diesel::delete(files::table)
+        .filter(files::id.eq_any(snapshot.attachments.iter().map(|file| file.id)))
+        .execute(conn)?;
+
+    AnalyticsQueries::record_movement(
+        conn,
+        board_id,
+        card_id,
+        Some(snapshot.column_id),
+        None,
+        Some(deleted_by),
+    )?;
+
+    diesel::delete(column_card::table)
+        .filter(column_card::id.eq(card_id))
+        .execute(conn)?;
+    Ok(snapshot)
+}
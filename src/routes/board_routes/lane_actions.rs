@@ -0,0 +1,230 @@
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse, auth::AuthResult, BoardLane, BoardUsersRelation, NewLane,
+        PubLane, ReturnedLane,
+    },
+    schema::{board_lane, board_users_relation},
+};
+
+/// # POST /boards/<board_id>/lanes
+/// Creates a new swimlane in the board with the given id
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// * `lane` - The name and position of the lane
+/// # Returns
+/// * `lane_id` - The id of the lane
+#[post("/<board_id>/lanes", data = "<lane>")]
+pub async fn boards_create_lane(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    lane: Json<NewLane>,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            let lane_id = diesel::insert_into(board_lane::table)
+                .values(BoardLane {
+                    id: None,
+                    name: lane.name.clone(),
+                    position: lane.position,
+                    board_id,
+                })
+                .returning(board_lane::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "create_lane", token, &lane_id)?;
+
+            Ok::<Uuid, ApiError>(lane_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/lanes
+/// Returns the swimlanes of the board, in order
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `lanes` - A list of lanes of the board
+/// ```json
+///  [
+///        {
+///            "id": <lane_id>,
+///            "name": <lane_name>,
+///            "position": <lane_position>
+///        },
+///        ...
+///  ]
+/// ```
+#[get("/<board_id>/lanes")]
+pub async fn boards_get_lanes(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<Vec<PubLane>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let lanes = board_lane::table
+            .filter(board_lane::board_id.eq(board_id))
+            .order(board_lane::position.asc())
+            .select((board_lane::id, board_lane::name, board_lane::position))
+            .load::<ReturnedLane>(conn)?
+            .into_iter()
+            .map(|lane| PubLane {
+                id: lane.0,
+                name: lane.1,
+                position: lane.2,
+            })
+            .collect::<Vec<PubLane>>();
+
+        Ok::<Vec<PubLane>, ApiError>(lanes)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/lanes/<lane_id>
+/// Renames or moves the swimlane with the given id
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `lane_id` - The id of the lane
+/// * `auth` - Takes the token of the user
+/// * `lane` - The new name and position of the lane
+/// # Returns
+/// * `lane` - The lane
+/// ```json
+/// {
+///     "id": <lane_id>,
+///     "name": <lane_name>,
+///     "position": <lane_position>
+/// }
+/// ```
+#[put("/<board_id>/lanes/<lane_id>", data = "<lane>")]
+pub async fn boards_update_lane(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    lane_id: String,
+    lane: Json<NewLane>,
+) -> Result<ApiResponse<PubLane>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let lane_id = Uuid::try_parse(&lane_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            let lane = diesel::update(board_lane::table)
+                .filter(board_lane::id.eq(lane_id))
+                .filter(board_lane::board_id.eq(board_id))
+                .set((
+                    board_lane::name.eq(lane.name.clone()),
+                    board_lane::position.eq(lane.position),
+                ))
+                .returning((board_lane::id, board_lane::name, board_lane::position))
+                .get_result::<ReturnedLane>(conn)?;
+
+            let lane = PubLane {
+                id: lane.0,
+                name: lane.1,
+                position: lane.2,
+            };
+            WebhookQueries::emit(conn, board_id, "update_lane", token, &lane)?;
+
+            Ok::<PubLane, ApiError>(lane)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/lanes/<lane_id>
+/// Deletes the swimlane with the given id, its cards stay on the board without a lane
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `lane_id` - The id of the lane
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `lane_id` - The id of the deleted lane
+#[delete("/<board_id>/lanes/<lane_id>")]
+pub async fn boards_delete_lane(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    lane_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let lane_id = Uuid::try_parse(&lane_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            let lane_id = diesel::delete(board_lane::table)
+                .filter(board_lane::id.eq(lane_id))
+                .filter(board_lane::board_id.eq(board_id))
+                .returning(board_lane::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "delete_lane", token, &lane_id)?;
+
+            Ok::<Uuid, ApiError>(lane_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -1,5 +1,18 @@
 pub mod base_actions;
 pub mod column_actions;
+pub mod lane_actions;
 pub mod card_actions;
 pub mod collaborator_actions;
-pub mod card_editing;
\ No newline at end of file
+pub mod card_editing;
+pub mod recurrence_actions;
+pub mod custom_field_actions;
+pub mod time_actions;
+pub mod sprint_actions;
+pub mod analytics_actions;
+pub mod undo_actions;
+pub mod revision_actions;
+pub mod rule_actions;
+pub mod webhook_actions;
+pub mod chat_actions;
+pub(crate) mod webhook_events;
+pub(crate) mod helpers;
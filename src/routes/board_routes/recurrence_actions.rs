@@ -0,0 +1,223 @@
+use chrono::Utc;
+use diesel::{
+    upsert::excluded, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+use rocket::serde::json::Json;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    database::{webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        recurrence::{CardRecurrence, NewRecurrence, RecurrenceRule},
+        BoardUsersRelation,
+    },
+    schema::{board_column, board_users_relation, card_recurrences},
+};
+
+use super::helpers::card_on_board;
+
+/// # PUT /boards/<board_id>/cards/<card_id>/recurrence
+/// Makes the card recurring, replacing any previous recurrence of the card
+///
+/// Once the card is completed or `next_run_at` passes, the scheduler creates
+/// a copy of the card at the end of `column_id` and the recurrence moves on to the copy
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// * `recurrence` - The recurrence rule and the column new instances are created in
+/// ```json
+/// {
+///     "rule": "FREQ=WEEKLY;INTERVAL=1",
+///     "column_id": <column_id>,
+///     "starts_at": <first_occurrence>
+/// }
+/// ```
+/// # Returns
+/// * `recurrence` - The recurrence
+#[put("/<board_id>/cards/<card_id>/recurrence", data = "<recurrence>")]
+pub async fn boards_set_card_recurrence(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    recurrence: Json<NewRecurrence>,
+) -> Result<ApiResponse<CardRecurrence>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let rule = recurrence.rule.parse::<RecurrenceRule>()?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            let column_id = board_column::table
+                .filter(board_column::id.eq(recurrence.column_id))
+                .filter(board_column::board_id.eq(board_id))
+                .select(board_column::id)
+                .first::<Uuid>(conn)?;
+
+            let now = Utc::now().naive_utc();
+            let next_run_at = match recurrence.starts_at {
+                // The scheduler has to be able to step past the first occurrence
+                Some(starts_at) => rule.next_after(starts_at).map(|_| starts_at),
+                None => rule.next_after(now),
+            }
+            .ok_or_else(|| ApiError::from_type(ApiErrorType::InvalidRecurrenceRule))?;
+
+            let recurrence = diesel::insert_into(card_recurrences::table)
+                .values(CardRecurrence {
+                    id: Uuid::new_v4(),
+                    card_id,
+                    column_id,
+                    rule: rule.to_string(),
+                    next_run_at,
+                    created_at: now,
+                })
+                .on_conflict(card_recurrences::card_id)
+                .do_update()
+                .set((
+                    card_recurrences::column_id.eq(excluded(card_recurrences::column_id)),
+                    card_recurrences::rule.eq(excluded(card_recurrences::rule)),
+                    card_recurrences::next_run_at.eq(excluded(card_recurrences::next_run_at)),
+                ))
+                .get_result::<CardRecurrence>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "set_card_recurrence", token, &recurrence)?;
+
+            Ok(recurrence)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/cards/<card_id>/recurrence
+/// Returns the recurrence of the card
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `recurrence` - The recurrence
+/// ```json
+/// {
+///     "id": <recurrence_id>,
+///     "card_id": <card_id>,
+///     "column_id": <column_id>,
+///     "rule": "FREQ=WEEKLY;INTERVAL=1",
+///     "next_run_at": <next_occurrence>,
+///     "created_at": <creation_time>
+/// }
+/// ```
+#[get("/<board_id>/cards/<card_id>/recurrence")]
+pub async fn boards_get_card_recurrence(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+) -> Result<ApiResponse<CardRecurrence>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        let board_id = Uuid::try_parse(&board_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+        let card_id = Uuid::try_parse(&card_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let recurrence = card_recurrences::table
+            .inner_join(board_column::table)
+            .filter(card_recurrences::card_id.eq(card_id))
+            .filter(board_column::board_id.eq(board_id))
+            .select(CardRecurrence::as_select())
+            .first::<CardRecurrence>(conn)?;
+
+        Ok::<CardRecurrence, ApiError>(recurrence)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/cards/<card_id>/recurrence
+/// Stops the card from recurring
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `recurrence_id` - The id of the removed recurrence
+#[delete("/<board_id>/cards/<card_id>/recurrence")]
+pub async fn boards_delete_card_recurrence(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            let id = diesel::delete(card_recurrences::table)
+                .filter(card_recurrences::card_id.eq(card_id))
+                .returning(card_recurrences::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(
+                conn,
+                board_id,
+                "delete_card_recurrence",
+                token,
+                &json!({ "card_id": card_id, "recurrence_id": id }),
+            )?;
+
+            Ok(id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
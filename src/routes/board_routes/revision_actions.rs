@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+use rocket::State;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        mention_queries::MentionQueries, operation_queries::OperationQueries,
+        revision_queries::RevisionQueries, webhook_queries::WebhookQueries, Db,
+    },
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        operation::{CardEdit, OperationKind},
+        revision::{CardRevision, RevisionDiff},
+        ws_state::WsState,
+        BoardUsersRelation, PubCard, ReturnedCard, SELECT_CARD,
+    },
+    schema::{board_users_relation, card_revisions, column_card},
+};
+
+use super::helpers::{card_on_board, to_pub_card};
+
+/// Checks that the user is a member of the board and the card belongs to it
+fn check_card(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    card_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    let _ = board_users_relation::table
+        .filter(
+            board_users_relation::board_id
+                .eq(board_id)
+                .and(board_users_relation::user_id.eq(user_id)),
+        )
+        .first::<BoardUsersRelation>(conn)?;
+    if !card_on_board(conn, board_id, card_id)? {
+        return Err(ApiError::from_type(ApiErrorType::NotFound));
+    }
+    Ok(())
+}
+
+/// # GET /boards/<board_id>/cards/<card_id>/revisions
+/// Returns every stored revision of the card's name and description, newest first
+///
+/// A card only gets revisions once it is edited, the first edit also stores the original text
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `revisions` - A list of revisions
+/// ```json
+/// [
+///     {
+///         "id": <revision_id>,
+///         "card_id": <card_id>,
+///         "revision": 2,
+///         "name": <card_name>,
+///         "description": <card_description>,
+///         "author_id": <user_id>,
+///         "created_at": "2025-03-24T09:00:00"
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/cards/<card_id>/revisions")]
+pub async fn boards_get_card_revisions(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+) -> Result<ApiResponse<Vec<CardRevision>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = Uuid::try_parse(&card_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_card(conn, board_id, card_id, token)?;
+
+        let revisions = card_revisions::table
+            .filter(card_revisions::card_id.eq(card_id))
+            .order(card_revisions::revision.desc())
+            .select(CardRevision::as_select())
+            .load::<CardRevision>(conn)?;
+
+        Ok::<Vec<CardRevision>, ApiError>(revisions)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/cards/<card_id>/revisions/diff?<from>&<to>
+/// Compares the descriptions of two revisions of the card line by line
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `from` - The older revision number
+/// * `to` - The newer revision number
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `diff` - The changed name and the lines of the description
+/// ```json
+/// {
+///     "from": 1,
+///     "to": 3,
+///     "name_from": <card_name>,
+///     "name_to": <card_name>,
+///     "lines": [
+///         { "op": "equal", "line": "## Scope" },
+///         { "op": "delete", "line": "Only boards" },
+///         { "op": "insert", "line": "Boards and workspaces" },
+///         ...
+///     ]
+/// }
+/// ```
+#[get("/<board_id>/cards/<card_id>/revisions/diff?<from>&<to>")]
+pub async fn boards_diff_card_revisions(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    from: i32,
+    to: i32,
+) -> Result<ApiResponse<RevisionDiff>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = Uuid::try_parse(&card_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_card(conn, board_id, card_id, token)?;
+
+        let from = RevisionQueries::get(conn, card_id, from)?;
+        let to = RevisionQueries::get(conn, card_id, to)?;
+
+        Ok::<RevisionDiff, ApiError>(RevisionDiff::new(&from, &to))
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/cards/<card_id>/revisions/<revision>/restore
+/// Brings back the name and description of an old revision
+///
+/// The restored text becomes the newest revision, so the history is never rewritten
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `revision` - The revision number to restore
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `card` - The card, its new version is also sent as the `ETag` header
+/// ```json
+/// {
+///     "id": <card_id>,
+///     "column_id": <column_id>,
+///     "name": <card_name>,
+///     "description": <card_description>,
+///     "version": <card_version>
+/// }
+/// ```
+#[post("/<board_id>/cards/<card_id>/revisions/<revision>/restore")]
+pub async fn boards_restore_card_revision(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    revision: i32,
+    ws_state: &State<Arc<WsState>>,
+) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = Uuid::try_parse(&card_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let (card, notifications) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                check_card(conn, board_id, card_id, token)?;
+
+                let revision = RevisionQueries::get(conn, card_id, revision)?;
+                let current = column_card::table
+                    .filter(column_card::id.eq(card_id))
+                    .select(SELECT_CARD)
+                    .for_update()
+                    .first::<ReturnedCard>(conn)?;
+                let before = CardEdit {
+                    name: current.1,
+                    description: current.4,
+                    completed_at: current.7,
+                };
+
+                let card = diesel::update(column_card::table)
+                    .filter(column_card::id.eq(card_id))
+                    .set((
+                        column_card::name.eq(revision.name),
+                        column_card::description.eq(revision.description),
+                        column_card::version.eq(column_card::version + 1),
+                    ))
+                    .returning(SELECT_CARD)
+                    .get_result::<ReturnedCard>(conn)?;
+                RevisionQueries::record(
+                    conn,
+                    card_id,
+                    &before.name,
+                    before.description.as_deref(),
+                    token,
+                )?;
+                OperationQueries::record(
+                    conn,
+                    board_id,
+                    token,
+                    OperationKind::CardUpdated,
+                    card_id,
+                    serde_json::to_value(before).ok(),
+                    Some(card.11),
+                )?;
+
+                let notifications = MentionQueries::store_card_mentions(
+                    conn,
+                    board_id,
+                    card_id,
+                    &card.1,
+                    token,
+                    card.4.as_deref().unwrap_or_default(),
+                )?;
+
+                let card = to_pub_card(conn, card)?;
+                WebhookQueries::emit(conn, board_id, "restore_card_revision", token, &card)?;
+
+                Ok((card, notifications))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(notifications).await;
+    let version = card.version;
+    Ok(ApiResponse::new(card).with_etag(version))
+}
@@ -0,0 +1,487 @@
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use rocket::serde::json::Json;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{automation_queries::AutomationQueries, webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        automation::{
+            Action, BoardRule, Condition, DryRunMatch, NewRule, RuleDefinition, RuleExecution,
+            Trigger,
+        },
+        custom_field::{CustomField, FieldKind},
+        BoardUsersRelation,
+    },
+    schema::{
+        board_column, board_custom_fields, board_lane, board_rules, board_users_relation, boards,
+        rule_executions,
+    },
+};
+
+use super::helpers::card_on_board;
+
+const MAX_EXECUTIONS: i64 = 200;
+
+fn check_member(conn: &mut PgConnection, board_id: Uuid, user_id: Uuid) -> Result<(), ApiError> {
+    let _ = board_users_relation::table
+        .filter(
+            board_users_relation::board_id
+                .eq(board_id)
+                .and(board_users_relation::user_id.eq(user_id)),
+        )
+        .first::<BoardUsersRelation>(conn)?;
+    Ok(())
+}
+
+fn check_creator(conn: &mut PgConnection, board_id: Uuid, user_id: Uuid) -> Result<(), ApiError> {
+    let _ = boards::table
+        .filter(boards::id.eq(board_id).and(boards::creator_id.eq(user_id)))
+        .select(boards::id)
+        .first::<Uuid>(conn)?;
+    Ok(())
+}
+
+/// Checks that every column, lane, field and user the rule refers to belongs to the board,
+/// and that field values suit their field
+fn validate_rule(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    name: &str,
+    definition: &RuleDefinition,
+) -> Result<(), ApiError> {
+    let invalid = || ApiError::from_type(ApiErrorType::InvalidRule);
+    if name.trim().is_empty() || definition.actions.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut column_ids = Vec::new();
+    let mut field_values: Vec<(Uuid, Option<&Value>)> = Vec::new();
+    match &definition.trigger {
+        Trigger::CardCreated { column_id } => column_ids.extend(column_id),
+        Trigger::CardEnteredColumn { column_id } => column_ids.push(*column_id),
+        Trigger::DatePassed { field_id } => {
+            let field = load_field(conn, board_id, *field_id)?.ok_or_else(invalid)?;
+            if field.kind()? != FieldKind::Date {
+                return Err(invalid());
+            }
+        }
+    }
+    for condition in &definition.conditions {
+        match condition {
+            Condition::InColumn { column_id } => column_ids.push(*column_id),
+            Condition::InLane {
+                lane_id: Some(lane_id),
+            } => {
+                let lane = board_lane::table
+                    .filter(board_lane::id.eq(lane_id))
+                    .filter(board_lane::board_id.eq(board_id))
+                    .select(board_lane::id)
+                    .first::<Uuid>(conn)
+                    .optional()?;
+                if lane.is_none() {
+                    return Err(invalid());
+                }
+            }
+            Condition::FieldEquals { field_id, value } => {
+                field_values.push((*field_id, Some(value)))
+            }
+            _ => {}
+        }
+    }
+    for action in &definition.actions {
+        match action {
+            Action::MoveToColumn { column_id } => column_ids.push(*column_id),
+            Action::SetField { field_id, value } => {
+                field_values.push((*field_id, Some(value).filter(|value| !value.is_null())))
+            }
+            Action::Assign { user_id } => check_member(conn, board_id, *user_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::InvalidUserId))?,
+            _ => {}
+        }
+    }
+
+    for column_id in column_ids {
+        let column = board_column::table
+            .filter(board_column::id.eq(column_id))
+            .filter(board_column::board_id.eq(board_id))
+            .select(board_column::id)
+            .first::<Uuid>(conn)
+            .optional()?;
+        if column.is_none() {
+            return Err(invalid());
+        }
+    }
+    for (field_id, value) in field_values {
+        let field = load_field(conn, board_id, field_id)?.ok_or_else(invalid)?;
+        if let Some(value) = value {
+            field.kind()?.validate(value, &field.options())?;
+        }
+    }
+    Ok(())
+}
+
+fn load_field(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    field_id: Uuid,
+) -> Result<Option<CustomField>, ApiError> {
+    let field = board_custom_fields::table
+        .filter(board_custom_fields::id.eq(field_id))
+        .filter(board_custom_fields::board_id.eq(board_id))
+        .select(CustomField::as_select())
+        .first::<CustomField>(conn)
+        .optional()?;
+    Ok(field)
+}
+
+fn to_values(definition: &RuleDefinition) -> (Value, Value, Value) {
+    (
+        serde_json::to_value(&definition.trigger).unwrap_or_default(),
+        serde_json::to_value(&definition.conditions).unwrap_or_default(),
+        serde_json::to_value(&definition.actions).unwrap_or_default(),
+    )
+}
+
+/// # POST /boards/<board_id>/rules
+/// Adds an automation rule to the board, only the creator of the board can do it
+///
+/// Rules run inside the change that triggers them. `trigger.type` is one of
+/// `card_created`, `card_entered_column` or `date_passed` (checked every minute against a
+/// `date` custom field). Conditions are `in_column`, `in_lane`, `field_equals`, `assigned`,
+/// `completed` and `name_contains`, all of them have to hold. Actions are `set_completed`,
+/// `assign`, `unassign`, `set_field` and `move_to_column`
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// * `rule` - The rule
+/// ```json
+/// {
+///     "name": "Close finished cards",
+///     "trigger": { "type": "card_entered_column", "column_id": <column_id> },
+///     "conditions": [{ "type": "completed", "completed": false }],
+///     "actions": [{ "type": "set_completed", "completed": true }, { "type": "unassign" }],
+///     "enabled": true
+/// }
+/// ```
+/// # Returns
+/// * `rule` - The rule
+#[post("/<board_id>/rules", data = "<rule>")]
+pub async fn boards_create_rule(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    rule: Json<NewRule>,
+) -> Result<ApiResponse<BoardRule>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            // Check if current user is board creator
+            check_creator(conn, board_id, token)?;
+            validate_rule(conn, board_id, &rule.name, &rule.definition)?;
+
+            let (trigger, conditions, actions) = to_values(&rule.definition);
+            let rule = diesel::insert_into(board_rules::table)
+                .values(BoardRule {
+                    id: Uuid::new_v4(),
+                    board_id,
+                    name: rule.name.trim().to_string(),
+                    trigger,
+                    conditions,
+                    actions,
+                    enabled: rule.enabled,
+                    created_by: Some(token),
+                    created_at: Utc::now().naive_utc(),
+                })
+                .get_result::<BoardRule>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "create_rule", token, &rule)?;
+
+            Ok(rule)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/rules
+/// Returns the automation rules of the board, in the order they run
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `rules` - A list of rules
+/// ```json
+/// [
+///     {
+///         "id": <rule_id>,
+///         "board_id": <board_id>,
+///         "name": "Close finished cards",
+///         "trigger": { "type": "card_entered_column", "column_id": <column_id> },
+///         "conditions": [],
+///         "actions": [{ "type": "set_completed", "completed": true }],
+///         "enabled": true,
+///         "created_by": <user_id>,
+///         "created_at": "2025-03-27T10:00:00"
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/rules")]
+pub async fn boards_get_rules(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<Vec<BoardRule>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+
+        let rules = board_rules::table
+            .filter(board_rules::board_id.eq(board_id))
+            .order(board_rules::created_at.asc())
+            .select(BoardRule::as_select())
+            .load::<BoardRule>(conn)?;
+
+        Ok::<Vec<BoardRule>, ApiError>(rules)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/rules/<rule_id>
+/// Replaces the definition of the rule, only the creator of the board can do it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `rule_id` - The id of the rule
+/// * `auth` - Takes the token of the user
+/// * `rule` - The rule, in the same shape as when creating it
+/// # Returns
+/// * `rule` - The rule
+#[put("/<board_id>/rules/<rule_id>", data = "<rule>")]
+pub async fn boards_update_rule(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    rule_id: String,
+    rule: Json<NewRule>,
+) -> Result<ApiResponse<BoardRule>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let rule_id = Uuid::try_parse(&rule_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            // Check if current user is board creator
+            check_creator(conn, board_id, token)?;
+            validate_rule(conn, board_id, &rule.name, &rule.definition)?;
+
+            let (trigger, conditions, actions) = to_values(&rule.definition);
+            let rule = diesel::update(board_rules::table)
+                .filter(board_rules::id.eq(rule_id))
+                .filter(board_rules::board_id.eq(board_id))
+                .set((
+                    board_rules::name.eq(rule.name.trim()),
+                    board_rules::trigger.eq(trigger),
+                    board_rules::conditions.eq(conditions),
+                    board_rules::actions.eq(actions),
+                    board_rules::enabled.eq(rule.enabled),
+                ))
+                .returning(BoardRule::as_returning())
+                .get_result::<BoardRule>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "update_rule", token, &rule)?;
+
+            Ok(rule)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/rules/<rule_id>
+/// Deletes the rule along with its execution log, only the creator of the board can do it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `rule_id` - The id of the rule
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `rule_id` - The id of the deleted rule
+#[delete("/<board_id>/rules/<rule_id>")]
+pub async fn boards_delete_rule(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    rule_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let rule_id = Uuid::try_parse(&rule_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction(|conn| {
+            // Check if current user is board creator
+            check_creator(conn, board_id, token)?;
+
+            let rule_id = diesel::delete(board_rules::table)
+                .filter(board_rules::id.eq(rule_id))
+                .filter(board_rules::board_id.eq(board_id))
+                .returning(board_rules::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "delete_rule", token, &rule_id)?;
+
+            Ok::<Uuid, ApiError>(rule_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/rules/dry_run?<card_id>
+/// Shows which cards a rule would apply to right now, without changing anything
+///
+/// The trigger is matched against the current state of each card: a card sitting in the
+/// column of a `card_entered_column` rule counts as having entered it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - Only try the rule on this card
+/// * `auth` - Takes the token of the user
+/// * `rule` - The rule to try, in the same shape as when creating it
+/// # Returns
+/// * `matches` - The cards the rule applies to and the actions it would take
+/// ```json
+/// [
+///     {
+///         "card_id": <card_id>,
+///         "card_name": <card_name>,
+///         "actions": [{ "type": "unassign" }]
+///     },
+///     ...
+/// ]
+/// ```
+#[post("/<board_id>/rules/dry_run?<card_id>", data = "<rule>")]
+pub async fn boards_dry_run_rule(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: Option<String>,
+    rule: Json<NewRule>,
+) -> Result<ApiResponse<Vec<DryRunMatch>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = card_id
+        .map(|card_id| Uuid::try_parse(&card_id))
+        .transpose()
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+        validate_rule(conn, board_id, &rule.name, &rule.definition)?;
+        if let Some(card_id) = card_id {
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+        }
+
+        let matches = AutomationQueries::dry_run(conn, board_id, &rule.definition, card_id)?;
+
+        Ok::<Vec<DryRunMatch>, ApiError>(matches)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/rules/executions?<rule_id>&<card_id>
+/// Returns the execution log of the board's rules, newest first
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `rule_id` - Only the executions of this rule
+/// * `card_id` - Only the executions on this card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `executions` - The latest 200 executions, `detail` holds the actions and, for
+///   `failed` ones, the error that rolled them back
+/// ```json
+/// [
+///     {
+///         "id": <execution_id>,
+///         "rule_id": <rule_id>,
+///         "board_id": <board_id>,
+///         "card_id": <card_id>,
+///         "event": "card_entered_column",
+///         "status": "applied",
+///         "context": null,
+///         "detail": { "actions": [{ "type": "unassign" }] },
+///         "created_at": "2025-03-27T10:00:00"
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/rules/executions?<rule_id>&<card_id>")]
+pub async fn boards_get_rule_executions(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    rule_id: Option<String>,
+    card_id: Option<String>,
+) -> Result<ApiResponse<Vec<RuleExecution>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let rule_id = rule_id
+        .map(|rule_id| Uuid::try_parse(&rule_id))
+        .transpose()
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = card_id
+        .map(|card_id| Uuid::try_parse(&card_id))
+        .transpose()
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+
+        let mut query = rule_executions::table
+            .filter(rule_executions::board_id.eq(board_id))
+            .select(RuleExecution::as_select())
+            .into_boxed();
+        if let Some(rule_id) = rule_id {
+            query = query.filter(rule_executions::rule_id.eq(rule_id));
+        }
+        if let Some(card_id) = card_id {
+            query = query.filter(rule_executions::card_id.eq(card_id));
+        }
+        let executions = query
+            .order(rule_executions::created_at.desc())
+            .limit(MAX_EXECUTIONS)
+            .load::<RuleExecution>(conn)?;
+
+        Ok::<Vec<RuleExecution>, ApiError>(executions)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,610 @@
+use chrono::{Days, NaiveDateTime, Utc};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        sprint::{
+            Burndown, BurndownPoint, CompleteSprint, CompletedSprint, NewSprint, Sprint,
+            SprintState,
+        },
+        BoardUsersRelation, PubCard, ReturnedCard, SELECT_CARD,
+    },
+    schema::{board_column, board_users_relation, column_card, sprints},
+};
+
+use super::helpers::{
+    attach_field_values, card_on_board, move_card_to_column_end, to_pub_card, unique_violation_as,
+};
+
+fn check_member(conn: &mut PgConnection, board_id: Uuid, user_id: Uuid) -> Result<(), ApiError> {
+    let _ = board_users_relation::table
+        .filter(
+            board_users_relation::board_id
+                .eq(board_id)
+                .and(board_users_relation::user_id.eq(user_id)),
+        )
+        .first::<BoardUsersRelation>(conn)?;
+    Ok(())
+}
+
+fn load_sprint(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    sprint_id: Uuid,
+) -> Result<Sprint, ApiError> {
+    let sprint = sprints::table
+        .filter(sprints::id.eq(sprint_id))
+        .filter(sprints::board_id.eq(board_id))
+        .select(Sprint::as_select())
+        .first::<Sprint>(conn)?;
+    Ok(sprint)
+}
+
+fn validate_sprint(sprint: &NewSprint) -> Result<(), ApiError> {
+    if sprint.name.trim().is_empty() || sprint.ends_on < sprint.starts_on {
+        return Err(ApiError::from_type(ApiErrorType::InvalidSprint));
+    }
+    Ok(())
+}
+
+/// # POST /boards/<board_id>/sprints
+/// Plans a new sprint on the board
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// * `sprint` - The sprint
+/// ```json
+/// {
+///     "name": "Sprint 12",
+///     "goal": <goal>,
+///     "starts_on": "2025-03-10",
+///     "ends_on": "2025-03-21"
+/// }
+/// ```
+/// # Returns
+/// * `sprint` - The sprint, in the `planned` state
+#[post("/<board_id>/sprints", data = "<sprint>")]
+pub async fn boards_create_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint: Json<NewSprint>,
+) -> Result<ApiResponse<Sprint>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    validate_sprint(&sprint)?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+
+            let sprint = sprint.into_inner();
+            let sprint = diesel::insert_into(sprints::table)
+                .values(Sprint {
+                    id: Uuid::new_v4(),
+                    board_id,
+                    name: sprint.name.trim().to_string(),
+                    goal: sprint.goal,
+                    starts_on: sprint.starts_on,
+                    ends_on: sprint.ends_on,
+                    state: SprintState::Planned.as_str().to_string(),
+                    carried_over: 0,
+                    created_at: Utc::now().naive_utc(),
+                    completed_at: None,
+                })
+                .get_result::<Sprint>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "create_sprint", token, &sprint)?;
+
+            Ok::<Sprint, ApiError>(sprint)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/sprints
+/// Returns the sprints of the board, most recent first
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `sprints` - A list of sprints
+/// ```json
+/// [
+///     {
+///         "id": <sprint_id>,
+///         "board_id": <board_id>,
+///         "name": "Sprint 12",
+///         "goal": <goal>,
+///         "starts_on": "2025-03-10",
+///         "ends_on": "2025-03-21",
+///         "state": "planned" | "active" | "completed",
+///         "carried_over": 0,
+///         "created_at": <creation_time>,
+///         "completed_at": <completion_time>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/sprints")]
+pub async fn boards_get_sprints(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<Vec<Sprint>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+
+        let sprints = sprints::table
+            .filter(sprints::board_id.eq(board_id))
+            .order(sprints::starts_on.desc())
+            .select(Sprint::as_select())
+            .load::<Sprint>(conn)?;
+
+        Ok::<Vec<Sprint>, ApiError>(sprints)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/sprints/<sprint_id>/cards
+/// Returns the cards assigned to the sprint
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `cards` - A list of cards
+#[get("/<board_id>/sprints/<sprint_id>/cards")]
+pub async fn boards_get_sprint_cards(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+) -> Result<ApiResponse<Vec<PubCard>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+        let sprint = load_sprint(conn, board_id, sprint_id)?;
+
+        let mut cards = column_card::table
+            .filter(column_card::sprint_id.eq(sprint.id))
+            .select(SELECT_CARD)
+            .load::<ReturnedCard>(conn)?
+            .into_iter()
+            .map(PubCard::from)
+            .collect::<Vec<PubCard>>();
+        attach_field_values(conn, &mut cards)?;
+
+        Ok::<Vec<PubCard>, ApiError>(cards)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/sprints/<sprint_id>
+/// Updates the name, goal and dates of a sprint that is not completed yet
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// * `sprint` - The new values, same as when creating the sprint
+/// # Returns
+/// * `sprint` - The sprint
+#[put("/<board_id>/sprints/<sprint_id>", data = "<sprint>")]
+pub async fn boards_update_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+    sprint: Json<NewSprint>,
+) -> Result<ApiResponse<Sprint>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    validate_sprint(&sprint)?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+            let current = load_sprint(conn, board_id, sprint_id)?;
+            if current.state == SprintState::Completed.as_str() {
+                return Err(ApiError::from_type(ApiErrorType::InvalidSprintState));
+            }
+
+            let sprint = sprint.into_inner();
+            let sprint = diesel::update(sprints::table.find(sprint_id))
+                .set((
+                    sprints::name.eq(sprint.name.trim()),
+                    sprints::goal.eq(sprint.goal),
+                    sprints::starts_on.eq(sprint.starts_on),
+                    sprints::ends_on.eq(sprint.ends_on),
+                ))
+                .get_result::<Sprint>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "update_sprint", token, &sprint)?;
+
+            Ok(sprint)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/sprints/<sprint_id>
+/// Deletes the sprint, its cards stay on the board without a sprint
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `sprint_id` - The id of the deleted sprint
+#[delete("/<board_id>/sprints/<sprint_id>")]
+pub async fn boards_delete_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+
+            let sprint_id = diesel::delete(sprints::table)
+                .filter(sprints::id.eq(sprint_id))
+                .filter(sprints::board_id.eq(board_id))
+                .returning(sprints::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "delete_sprint", token, &sprint_id)?;
+
+            Ok::<Uuid, ApiError>(sprint_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/sprints/<sprint_id>/start
+/// Starts a planned sprint, a board can only run one sprint at a time
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `sprint` - The sprint, in the `active` state
+#[post("/<board_id>/sprints/<sprint_id>/start")]
+pub async fn boards_start_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+) -> Result<ApiResponse<Sprint>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+            let sprint = load_sprint(conn, board_id, sprint_id)?;
+            if sprint.state != SprintState::Planned.as_str() {
+                return Err(ApiError::from_type(ApiErrorType::InvalidSprintState));
+            }
+            let active = sprints::table
+                .filter(sprints::board_id.eq(board_id))
+                .filter(sprints::state.eq(SprintState::Active.as_str()))
+                .count()
+                .get_result::<i64>(conn)?;
+            if active > 0 {
+                return Err(ApiError::from_type(ApiErrorType::SprintAlreadyActive));
+            }
+
+            let sprint = diesel::update(sprints::table.find(sprint_id))
+                .set(sprints::state.eq(SprintState::Active.as_str()))
+                .get_result::<Sprint>(conn)
+                .map_err(unique_violation_as(ApiErrorType::SprintAlreadyActive))?;
+
+            WebhookQueries::emit(conn, board_id, "start_sprint", token, &sprint)?;
+
+            Ok(sprint)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/sprints/<sprint_id>/complete
+/// Completes the active sprint and moves its unfinished cards out of it
+///
+/// Unfinished cards join `next_sprint_id` when it is given, otherwise they leave
+/// the sprint and go to the end of `backlog_column_id`. One of the two is required
+/// as soon as the sprint has unfinished cards
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// * `target` - Where the unfinished cards go
+/// ```json
+/// {
+///     "next_sprint_id": <sprint_id>,
+///     "backlog_column_id": <column_id>
+/// }
+/// ```
+/// # Returns
+/// * `result` - The completed sprint and the ids of the cards that were moved
+/// ```json
+/// {
+///     "sprint": { ... },
+///     "moved_cards": [<card_id>, ...]
+/// }
+/// ```
+#[post("/<board_id>/sprints/<sprint_id>/complete", data = "<target>")]
+pub async fn boards_complete_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+    target: Json<CompleteSprint>,
+) -> Result<ApiResponse<CompletedSprint>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+            let sprint = load_sprint(conn, board_id, sprint_id)?;
+            if sprint.state != SprintState::Active.as_str() {
+                return Err(ApiError::from_type(ApiErrorType::InvalidSprintState));
+            }
+
+            let unfinished = column_card::table
+                .filter(column_card::sprint_id.eq(sprint_id))
+                .filter(column_card::completed_at.is_null())
+                .select(column_card::id)
+                .load::<Uuid>(conn)?;
+            if !unfinished.is_empty() {
+                match (target.next_sprint_id, target.backlog_column_id) {
+                    (Some(next_sprint_id), _) => {
+                        let next = load_sprint(conn, board_id, next_sprint_id)?;
+                        if next.id == sprint_id || next.state == SprintState::Completed.as_str() {
+                            return Err(ApiError::from_type(ApiErrorType::InvalidSprint));
+                        }
+                        diesel::update(column_card::table)
+                            .filter(column_card::id.eq_any(&unfinished))
+                            .set((
+                                column_card::sprint_id.eq(next.id),
+                                column_card::version.eq(column_card::version + 1),
+                            ))
+                            .execute(conn)?;
+                    }
+                    (None, Some(backlog_column_id)) => {
+                        let column_id = board_column::table
+                            .filter(board_column::id.eq(backlog_column_id))
+                            .filter(board_column::board_id.eq(board_id))
+                            .select(board_column::id)
+                            .first::<Uuid>(conn)?;
+                        for card_id in &unfinished {
+                            move_card_to_column_end(
+                                conn,
+                                board_id,
+                                *card_id,
+                                column_id,
+                                Some(token),
+                            )?;
+                        }
+                        diesel::update(column_card::table)
+                            .filter(column_card::id.eq_any(&unfinished))
+                            .set((
+                                column_card::sprint_id.eq(None::<Uuid>),
+                                column_card::version.eq(column_card::version + 1),
+                            ))
+                            .execute(conn)?;
+                    }
+                    (None, None) => return Err(ApiError::from_type(ApiErrorType::InvalidSprint)),
+                }
+            }
+
+            let sprint = diesel::update(sprints::table.find(sprint_id))
+                .set((
+                    sprints::state.eq(SprintState::Completed.as_str()),
+                    sprints::completed_at.eq(Utc::now().naive_utc()),
+                    sprints::carried_over.eq(unfinished.len() as i32),
+                ))
+                .get_result::<Sprint>(conn)?;
+
+            let completed = CompletedSprint {
+                sprint,
+                moved_cards: unfinished,
+            };
+            WebhookQueries::emit(conn, board_id, "complete_sprint", token, &completed)?;
+
+            Ok(completed)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/sprints/<sprint_id>/burndown
+/// Returns the number of open cards at the end of each day of the sprint
+///
+/// Cards carried over when the sprint was completed count as never finished
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `sprint_id` - The id of the sprint
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `burndown` - The burndown series with the ideal line
+/// ```json
+/// {
+///     "sprint_id": <sprint_id>,
+///     "total": 10,
+///     "completed": 4,
+///     "points": [
+///         { "date": "2025-03-10", "remaining": 10, "ideal": 10.0 },
+///         ...
+///         { "date": "2025-03-21", "remaining": null, "ideal": 0.0 }
+///     ]
+/// }
+/// ```
+#[get("/<board_id>/sprints/<sprint_id>/burndown")]
+pub async fn boards_get_sprint_burndown(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    sprint_id: String,
+) -> Result<ApiResponse<Burndown>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let sprint_id = Uuid::try_parse(&sprint_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        check_member(conn, board_id, token)?;
+        let sprint = load_sprint(conn, board_id, sprint_id)?;
+
+        let completions = column_card::table
+            .filter(column_card::sprint_id.eq(sprint_id))
+            .select(column_card::completed_at)
+            .load::<Option<NaiveDateTime>>(conn)?;
+        let total = completions.len() as i64 + sprint.carried_over as i64;
+        let completions = completions.into_iter().flatten().collect::<Vec<_>>();
+
+        let now = Utc::now().naive_utc();
+        let days = (sprint.ends_on - sprint.starts_on).num_days() as u64 + 1;
+        let points = (0..days)
+            .map(|day| {
+                let date = sprint.starts_on + Days::new(day);
+                let end_of_day = (date + Days::new(1))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap_or_default();
+                let remaining = (date.and_hms_opt(0, 0, 0).unwrap_or_default() <= now).then(|| {
+                    total
+                        - completions
+                            .iter()
+                            .filter(|completed_at| **completed_at < end_of_day)
+                            .count() as i64
+                });
+                let ideal = if days > 1 {
+                    total as f64 * (1.0 - day as f64 / (days - 1) as f64)
+                } else {
+                    0.0
+                };
+                BurndownPoint {
+                    date,
+                    remaining,
+                    ideal,
+                }
+            })
+            .collect();
+
+        Ok::<Burndown, ApiError>(Burndown {
+            sprint_id,
+            total,
+            completed: completions.len() as i64,
+            points,
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/cards/<card_id>/sprint
+/// Assigns the card to a sprint of the board, `null` removes it from its sprint
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// * `sprint_id` - The id of a sprint that is not completed, or `null`
+/// # Returns
+/// * `card` - The card
+#[put("/<board_id>/cards/<card_id>/sprint", data = "<sprint_id>")]
+pub async fn boards_set_card_sprint(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    sprint_id: Json<Option<Uuid>>,
+) -> Result<ApiResponse<PubCard>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let card_id = Uuid::try_parse(&card_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            check_member(conn, board_id, token)?;
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            if let Some(sprint_id) = sprint_id.0 {
+                let sprint = load_sprint(conn, board_id, sprint_id)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::InvalidSprint))?;
+                if sprint.state == SprintState::Completed.as_str() {
+                    return Err(ApiError::from_type(ApiErrorType::InvalidSprintState));
+                }
+            }
+
+            let card = diesel::update(column_card::table)
+                .filter(column_card::id.eq(card_id))
+                .set((
+                    column_card::sprint_id.eq(sprint_id.0),
+                    column_card::version.eq(column_card::version + 1),
+                ))
+                .returning(SELECT_CARD)
+                .get_result::<ReturnedCard>(conn)?;
+
+            let card = to_pub_card(conn, card)?;
+            WebhookQueries::emit(conn, board_id, "set_card_sprint", token, &card)?;
+
+            Ok(card)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
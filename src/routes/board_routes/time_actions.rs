@@ -0,0 +1,378 @@
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        time_queries::{TimeQueries, TimeScope},
+        webhook_queries::WebhookQueries,
+        Db,
+    },
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        time_entry::{NewTimeEntry, TimeEntry, TimeReport, TimerNote},
+        BoardUsersRelation,
+    },
+    schema::{board_users_relation, time_entries},
+};
+
+use super::helpers::{card_on_board, parse_date_range, unique_violation_as};
+
+/// # POST /boards/<board_id>/cards/<card_id>/timer/start
+/// Starts a timer on the card for the current user
+///
+/// A user can only have one running timer, starting a second one fails until the first is stopped
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// * `timer` - An optional note for the entry
+/// ```json
+/// {
+///     "note": <note>
+/// }
+/// ```
+/// # Returns
+/// * `entry` - The running time entry
+#[post("/<board_id>/cards/<card_id>/timer/start", data = "<timer>")]
+pub async fn boards_start_timer(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    timer: Option<Json<TimerNote>>,
+) -> Result<ApiResponse<TimeEntry>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            let running = time_entries::table
+                .filter(time_entries::user_id.eq(token))
+                .filter(time_entries::ended_at.is_null())
+                .count()
+                .get_result::<i64>(conn)?;
+            if running > 0 {
+                return Err(ApiError::from_type(ApiErrorType::TimerAlreadyRunning));
+            }
+
+            let now = Utc::now().naive_utc();
+            let entry = diesel::insert_into(time_entries::table)
+                .values(TimeEntry {
+                    id: Uuid::new_v4(),
+                    board_id,
+                    card_id,
+                    user_id: token,
+                    started_at: now,
+                    ended_at: None,
+                    note: timer.and_then(|timer| timer.into_inner().note),
+                    created_at: now,
+                })
+                .get_result::<TimeEntry>(conn)
+                .map_err(unique_violation_as(ApiErrorType::TimerAlreadyRunning))?;
+
+            WebhookQueries::emit(conn, board_id, "start_timer", token, &entry)?;
+
+            Ok(entry)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/cards/<card_id>/timer/stop
+/// Stops the timer the current user is running on the card
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `entry` - The finished time entry
+#[post("/<board_id>/cards/<card_id>/timer/stop")]
+pub async fn boards_stop_timer(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+) -> Result<ApiResponse<TimeEntry>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let entry = diesel::update(time_entries::table)
+                .filter(time_entries::board_id.eq(board_id))
+                .filter(time_entries::card_id.eq(card_id))
+                .filter(time_entries::user_id.eq(token))
+                .filter(time_entries::ended_at.is_null())
+                .set(time_entries::ended_at.eq(Utc::now().naive_utc()))
+                .get_result::<TimeEntry>(conn)
+                .optional()?
+                .ok_or_else(|| ApiError::from_type(ApiErrorType::NoRunningTimer))?;
+            WebhookQueries::emit(conn, board_id, "stop_timer", token, &entry)?;
+
+            Ok(entry)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/cards/<card_id>/time
+/// Records time the current user spent on the card without running a timer
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `auth` - Takes the token of the user
+/// * `entry` - The time span, it must not end before it starts nor in the future
+/// ```json
+/// {
+///     "started_at": "2025-03-06T09:00:00",
+///     "ended_at": "2025-03-06T11:30:00",
+///     "note": <note>
+/// }
+/// ```
+/// # Returns
+/// * `entry` - The time entry
+#[post("/<board_id>/cards/<card_id>/time", data = "<entry>")]
+pub async fn boards_add_time_entry(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    entry: Json<NewTimeEntry>,
+) -> Result<ApiResponse<TimeEntry>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let now = Utc::now().naive_utc();
+    if entry.ended_at < entry.started_at || entry.ended_at > now {
+        return Err(ApiResponse::from_error_type(ApiErrorType::InvalidTimeEntry));
+    }
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let _ = board_users_relation::table
+                .filter(
+                    board_users_relation::board_id
+                        .eq(board_id)
+                        .and(board_users_relation::user_id.eq(token)),
+                )
+                .first::<BoardUsersRelation>(conn)?;
+
+            if !card_on_board(conn, board_id, card_id)? {
+                return Err(ApiError::from_type(ApiErrorType::NotFound));
+            }
+            let entry = entry.into_inner();
+            let entry = diesel::insert_into(time_entries::table)
+                .values(TimeEntry {
+                    id: Uuid::new_v4(),
+                    board_id,
+                    card_id,
+                    user_id: token,
+                    started_at: entry.started_at,
+                    ended_at: Some(entry.ended_at),
+                    note: entry.note,
+                    created_at: now,
+                })
+                .get_result::<TimeEntry>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "add_time_entry", token, &entry)?;
+
+            Ok(entry)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/cards/<card_id>/time/<entry_id>
+/// Deletes one of the time entries of the current user
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `entry_id` - The id of the time entry
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `entry_id` - The id of the deleted entry
+#[delete("/<board_id>/cards/<card_id>/time/<entry_id>")]
+pub async fn boards_delete_time_entry(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    entry_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let board_id = Uuid::try_parse(&board_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let card_id = Uuid::try_parse(&card_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+            let entry_id = Uuid::try_parse(&entry_id)
+                .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+            let entry_id = diesel::delete(time_entries::table)
+                .filter(time_entries::id.eq(entry_id))
+                .filter(time_entries::board_id.eq(board_id))
+                .filter(time_entries::card_id.eq(card_id))
+                .filter(time_entries::user_id.eq(token))
+                .returning(time_entries::id)
+                .get_result::<Uuid>(conn)?;
+
+            WebhookQueries::emit(conn, board_id, "delete_time_entry", token, &entry_id)?;
+
+            Ok::<Uuid, ApiError>(entry_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/cards/<card_id>/time?<from>&<to>&<format>
+/// Returns the time spent on the card by every member, `format=csv` exports it as CSV
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `card_id` - The id of the card
+/// * `from` - The first day of the range, as `YYYY-MM-DD`, 30 days ago by default
+/// * `to` - The last day of the range, as `YYYY-MM-DD`, today by default
+/// * `format` - `json` or `csv`
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `summary` - The totals per user and per card and the entries that started in the range
+/// ```json
+/// {
+///     "from": "2025-03-01",
+///     "to": "2025-03-31",
+///     "total_seconds": 9000,
+///     "by_user": [{ "id": <user_id>, "name": <username>, "seconds": 9000 }],
+///     "by_card": [{ "id": <card_id>, "name": <card_name>, "seconds": 9000 }],
+///     "entries": [
+///         {
+///             "id": <entry_id>,
+///             "board_id": <board_id>,
+///             "card_id": <card_id>,
+///             "card_name": <card_name>,
+///             "user_id": <user_id>,
+///             "username": <username>,
+///             "started_at": <start>,
+///             "ended_at": <end>,
+///             "duration_seconds": 9000,
+///             "note": <note>
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+#[get("/<board_id>/cards/<card_id>/time?<from>&<to>&<format>")]
+pub async fn boards_get_card_time(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    card_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    format: Option<String>,
+) -> Result<TimeReport, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let (from, to) = parse_date_range(from, to)?;
+
+    db.run(move |conn| {
+        let board_id = Uuid::try_parse(&board_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+        let card_id = Uuid::try_parse(&card_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        if !card_on_board(conn, board_id, card_id)? {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+        let summary = TimeQueries::summary(conn, TimeScope::Card(card_id), from, to)?;
+        Ok(summary)
+    })
+    .await
+    .map(|summary| TimeReport::new(summary, format.as_deref()))
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/time?<from>&<to>&<format>
+/// Returns the time spent on the cards of the board, `format=csv` exports it as CSV
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `from` - The first day of the range, as `YYYY-MM-DD`, 30 days ago by default
+/// * `to` - The last day of the range, as `YYYY-MM-DD`, today by default
+/// * `format` - `json` or `csv`
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `summary` - Same as `GET /boards/<board_id>/cards/<card_id>/time`
+#[get("/<board_id>/time?<from>&<to>&<format>")]
+pub async fn boards_get_board_time(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    format: Option<String>,
+) -> Result<TimeReport, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let (from, to) = parse_date_range(from, to)?;
+
+    db.run(move |conn| {
+        let board_id = Uuid::try_parse(&board_id)
+            .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let summary = TimeQueries::summary(conn, TimeScope::Board(board_id), from, to)?;
+        Ok::<_, ApiError>(summary)
+    })
+    .await
+    .map(|summary| TimeReport::new(summary, format.as_deref()))
+    .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,468 @@
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use rocket::State;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        analytics_queries::AnalyticsQueries, operation_queries::OperationQueries,
+        revision_queries::RevisionQueries, webhook_queries::WebhookQueries, Db,
+    },
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        operation::{
+            BoardOperation, CardEdit, CardPlacement, CardSnapshot, ColumnEdit, ColumnSnapshot,
+            OperationKind, PubOperation, UndoConfig,
+        },
+        BoardColumn, BoardUsersRelation,
+    },
+    schema::{board_column, board_lane, board_operations, board_users_relation, column_card},
+};
+
+use super::helpers::delete_card_row;
+
+fn conflict() -> ApiError {
+    ApiError::from_type(ApiErrorType::OperationConflict)
+}
+
+fn parse_snapshot<T: serde::de::DeserializeOwned>(snapshot: Option<Value>) -> Result<T, ApiError> {
+    snapshot
+        .and_then(|snapshot| serde_json::from_value(snapshot).ok())
+        .ok_or_else(|| ApiError::from_type(ApiErrorType::InternalServerError))
+}
+
+/// Locks the card and returns its column, position and version, if it is still on the board
+fn lock_card(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    card_id: Uuid,
+) -> Result<Option<(Uuid, i32, i32)>, ApiError> {
+    let board_columns = board_column::table
+        .filter(board_column::board_id.eq(board_id))
+        .select(board_column::id);
+    let card = column_card::table
+        .filter(column_card::id.eq(card_id))
+        .filter(column_card::column_id.eq_any(board_columns))
+        .select((
+            column_card::column_id,
+            column_card::position,
+            column_card::version,
+        ))
+        .for_update()
+        .first::<(Uuid, i32, i32)>(conn)
+        .optional()?;
+    Ok(card)
+}
+
+/// Locks the column and returns its version, if it is still on the board
+fn lock_column(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    column_id: Uuid,
+) -> Result<Option<i32>, ApiError> {
+    let version = board_column::table
+        .filter(board_column::id.eq(column_id))
+        .filter(board_column::board_id.eq(board_id))
+        .select(board_column::version)
+        .for_update()
+        .first::<i32>(conn)
+        .optional()?;
+    Ok(version)
+}
+
+/// Fails unless the card is still exactly as the operation left it
+fn unchanged_card(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+) -> Result<(Uuid, i32), ApiError> {
+    match lock_card(conn, operation.board_id, operation.target_id)? {
+        Some((column_id, position, version)) if Some(version) == operation.result_version => {
+            Ok((column_id, position))
+        }
+        _ => Err(conflict()),
+    }
+}
+
+/// Fails unless the column is still exactly as the operation left it
+fn unchanged_column(conn: &mut PgConnection, operation: &BoardOperation) -> Result<(), ApiError> {
+    match lock_column(conn, operation.board_id, operation.target_id)? {
+        Some(version) if Some(version) == operation.result_version => Ok(()),
+        _ => Err(conflict()),
+    }
+}
+
+/// Makes sure every card can go back into its column and none of them exists already
+fn check_restorable(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    cards: &[CardSnapshot],
+) -> Result<(), ApiError> {
+    for card in cards {
+        if lock_column(conn, board_id, card.column_id)?.is_none() {
+            return Err(conflict());
+        }
+    }
+    let existing = column_card::table
+        .filter(column_card::id.eq_any(cards.iter().map(|card| card.id)))
+        .count()
+        .get_result::<i64>(conn)?;
+    if existing > 0 {
+        return Err(conflict());
+    }
+    Ok(())
+}
+
+/// Returns the paths of the card's attachments, nothing can bring them back anymore
+fn undo_card_created(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+    token: Uuid,
+) -> Result<Vec<String>, ApiError> {
+    let _ = unchanged_card(conn, operation)?;
+    let children = column_card::table
+        .filter(column_card::parent_id.eq(operation.target_id))
+        .count()
+        .get_result::<i64>(conn)?;
+    if children > 0 {
+        return Err(conflict());
+    }
+    let snapshot = delete_card_row(conn, operation.board_id, operation.target_id, token)?;
+    Ok(snapshot
+        .attachments
+        .into_iter()
+        .map(|file| file.path())
+        .collect())
+}
+
+fn undo_card_updated(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+    token: Uuid,
+) -> Result<(), ApiError> {
+    let _ = unchanged_card(conn, operation)?;
+    let before = parse_snapshot::<CardEdit>(operation.snapshot.clone())?;
+    let (name, description) = column_card::table
+        .filter(column_card::id.eq(operation.target_id))
+        .select((column_card::name, column_card::description))
+        .first::<(String, Option<String>)>(conn)?;
+    diesel::update(column_card::table)
+        .filter(column_card::id.eq(operation.target_id))
+        .set((
+            column_card::name.eq(before.name),
+            column_card::description.eq(before.description),
+            column_card::completed_at.eq(before.completed_at),
+            column_card::version.eq(column_card::version + 1),
+        ))
+        .execute(conn)?;
+    RevisionQueries::record(
+        conn,
+        operation.target_id,
+        &name,
+        description.as_deref(),
+        token,
+    )?;
+    Ok(())
+}
+
+fn undo_card_moved(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+    token: Uuid,
+) -> Result<(), ApiError> {
+    let (column_id, position) = unchanged_card(conn, operation)?;
+    let before = parse_snapshot::<CardPlacement>(operation.snapshot.clone())?;
+    if lock_column(conn, operation.board_id, before.column_id)?.is_none() {
+        return Err(conflict());
+    }
+    let lane_id = match before.lane_id {
+        Some(lane_id) => board_lane::table
+            .filter(board_lane::id.eq(lane_id))
+            .filter(board_lane::board_id.eq(operation.board_id))
+            .select(board_lane::id)
+            .first::<Uuid>(conn)
+            .optional()?,
+        None => None,
+    };
+
+    // Close the gap the card leaves, then open one where it used to be
+    diesel::update(column_card::table)
+        .filter(column_card::column_id.eq(column_id))
+        .filter(column_card::position.gt(position))
+        .set(column_card::position.eq(column_card::position - 1))
+        .execute(conn)?;
+    let remaining = column_card::table
+        .filter(column_card::column_id.eq(before.column_id))
+        .filter(column_card::id.ne(operation.target_id))
+        .count()
+        .get_result::<i64>(conn)?;
+    let to_pos = before.position.min(remaining as i32);
+    diesel::update(column_card::table)
+        .filter(column_card::column_id.eq(before.column_id))
+        .filter(column_card::id.ne(operation.target_id))
+        .filter(column_card::position.ge(to_pos))
+        .set(column_card::position.eq(column_card::position + 1))
+        .execute(conn)?;
+    diesel::update(column_card::table)
+        .filter(column_card::id.eq(operation.target_id))
+        .set((
+            column_card::column_id.eq(before.column_id),
+            column_card::position.eq(to_pos),
+            column_card::lane_id.eq(lane_id),
+            column_card::version.eq(column_card::version + 1),
+        ))
+        .execute(conn)?;
+
+    if column_id != before.column_id {
+        AnalyticsQueries::record_movement(
+            conn,
+            operation.board_id,
+            operation.target_id,
+            Some(column_id),
+            Some(before.column_id),
+            Some(token),
+        )?;
+    }
+    Ok(())
+}
+
+fn undo_card_deleted(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+    token: Uuid,
+) -> Result<(), ApiError> {
+    let cards = parse_snapshot::<Vec<CardSnapshot>>(operation.snapshot.clone())?;
+    check_restorable(conn, operation.board_id, &cards)?;
+
+    // Cards were deleted deepest first, so they come back root first
+    for card in cards.iter().rev() {
+        OperationQueries::restore_card(conn, operation.board_id, card, token)?;
+    }
+    OperationQueries::restore_parents(conn, &cards)?;
+    Ok(())
+}
+
+fn undo_column_created(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+) -> Result<(), ApiError> {
+    unchanged_column(conn, operation)?;
+    let cards = column_card::table
+        .filter(column_card::column_id.eq(operation.target_id))
+        .count()
+        .get_result::<i64>(conn)?;
+    if cards > 0 {
+        return Err(conflict());
+    }
+    diesel::delete(board_column::table)
+        .filter(board_column::id.eq(operation.target_id))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn undo_column_updated(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+) -> Result<(), ApiError> {
+    unchanged_column(conn, operation)?;
+    let before = parse_snapshot::<ColumnEdit>(operation.snapshot.clone())?;
+    diesel::update(board_column::table)
+        .filter(board_column::id.eq(operation.target_id))
+        .set((
+            board_column::name.eq(before.name),
+            board_column::position.eq(before.position),
+            board_column::version.eq(board_column::version + 1),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn undo_column_deleted(
+    conn: &mut PgConnection,
+    operation: &BoardOperation,
+    token: Uuid,
+) -> Result<(), ApiError> {
+    let column = parse_snapshot::<ColumnSnapshot>(operation.snapshot.clone())?;
+    if lock_column(conn, operation.board_id, column.id)?.is_some() {
+        return Err(conflict());
+    }
+    diesel::insert_into(board_column::table)
+        .values(BoardColumn {
+            id: Some(column.id),
+            name: column.name,
+            position: column.position,
+            board_id: operation.board_id,
+        })
+        .execute(conn)?;
+    check_restorable(conn, operation.board_id, &column.cards)?;
+
+    // Cards were captured in position order, so every one lands where it was
+    for card in &column.cards {
+        OperationQueries::restore_card(conn, operation.board_id, card, token)?;
+    }
+    OperationQueries::restore_parents(conn, &column.cards)?;
+    Ok(())
+}
+
+/// # GET /boards/<board_id>/operations
+/// Returns the operations of the current user on the board that can still be undone, newest first
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `operations` - A list of operations
+/// ```json
+/// [
+///     {
+///         "id": <operation_id>,
+///         "kind": "card_moved",
+///         "target_id": <card_id>,
+///         "created_at": "2025-03-20T10:00:00",
+///         "expires_at": "2025-03-20T10:30:00",
+///         "undone_at": null
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/operations")]
+pub async fn boards_get_operations(
+    db: Db,
+    auth: AuthResult,
+    undo: &State<UndoConfig>,
+    board_id: String,
+) -> Result<ApiResponse<Vec<PubOperation>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let window = undo.window();
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(token)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let operations = board_operations::table
+            .filter(board_operations::board_id.eq(board_id))
+            .filter(board_operations::user_id.eq(token))
+            .filter(board_operations::undone_at.is_null())
+            .filter(board_operations::created_at.ge(Utc::now().naive_utc() - window))
+            .order(board_operations::created_at.desc())
+            .select(BoardOperation::as_select())
+            .load::<BoardOperation>(conn)?
+            .into_iter()
+            .map(|operation| PubOperation::new(operation, window))
+            .collect();
+
+        Ok::<Vec<PubOperation>, ApiError>(operations)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/undo/<operation_id>
+/// Reverses one of the current user's operations on the board
+///
+/// Only works within the undo window and as long as the rows the operation touched
+/// haven't changed since, otherwise nothing is modified
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `operation_id` - The id of the operation
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `operation` - The undone operation
+/// ```json
+/// {
+///     "id": <operation_id>,
+///     "kind": "card_deleted",
+///     "target_id": <card_id>,
+///     "created_at": "2025-03-20T10:00:00",
+///     "expires_at": "2025-03-20T10:30:00",
+///     "undone_at": "2025-03-20T10:05:00"
+/// }
+/// ```
+#[post("/<board_id>/undo/<operation_id>")]
+pub async fn boards_undo_operation(
+    db: Db,
+    auth: AuthResult,
+    undo: &State<UndoConfig>,
+    board_id: String,
+    operation_id: String,
+) -> Result<ApiResponse<PubOperation>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let operation_id = Uuid::try_parse(&operation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let window = undo.window();
+
+    let (operation, stale_files) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let _ = board_users_relation::table
+                    .filter(
+                        board_users_relation::board_id
+                            .eq(board_id)
+                            .and(board_users_relation::user_id.eq(token)),
+                    )
+                    .first::<BoardUsersRelation>(conn)?;
+
+                // Only the author can undo an operation
+                let operation = board_operations::table
+                    .filter(board_operations::id.eq(operation_id))
+                    .filter(board_operations::board_id.eq(board_id))
+                    .filter(board_operations::user_id.eq(token))
+                    .select(BoardOperation::as_select())
+                    .for_update()
+                    .first::<BoardOperation>(conn)?;
+                let now = Utc::now().naive_utc();
+                if operation.undone_at.is_some() {
+                    return Err(ApiError::from_type(ApiErrorType::OperationAlreadyUndone));
+                }
+                if operation.created_at + window < now {
+                    return Err(ApiError::from_type(ApiErrorType::OperationExpired));
+                }
+
+                let mut stale_files = Vec::new();
+                match operation.kind.parse::<OperationKind>()? {
+                    OperationKind::CardCreated => {
+                        stale_files = undo_card_created(conn, &operation, token)?
+                    }
+                    OperationKind::CardUpdated => undo_card_updated(conn, &operation, token)?,
+                    OperationKind::CardMoved => undo_card_moved(conn, &operation, token)?,
+                    OperationKind::CardDeleted => undo_card_deleted(conn, &operation, token)?,
+                    OperationKind::ColumnCreated => undo_column_created(conn, &operation)?,
+                    OperationKind::ColumnUpdated => undo_column_updated(conn, &operation)?,
+                    OperationKind::ColumnDeleted => undo_column_deleted(conn, &operation, token)?,
+                }
+
+                let operation = diesel::update(board_operations::table)
+                    .filter(board_operations::id.eq(operation_id))
+                    .set(board_operations::undone_at.eq(now))
+                    .returning(BoardOperation::as_returning())
+                    .get_result::<BoardOperation>(conn)?;
+
+                let operation = PubOperation::new(operation, window);
+                WebhookQueries::emit(conn, board_id, "undo_operation", token, &operation)?;
+
+                Ok((operation, stale_files))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    // Files only leave the disk once the undo is committed
+    for file in stale_files {
+        let _ = std::fs::remove_file(file);
+    }
+    Ok(ApiResponse::new(operation))
+}
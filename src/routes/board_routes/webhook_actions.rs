@@ -0,0 +1,383 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use reqwest::Url;
+use rocket::{serde::json::Json, State};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::{webhook_queries::WebhookQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        webhook::{NewWebhook, PubWebhook, Webhook, WebhookDelivery, WebhookQueue},
+    },
+    schema::{boards, webhook_deliveries, webhooks},
+};
+
+use super::webhook_events::BoardEvents;
+
+const MAX_DELIVERIES: i64 = 200;
+
+fn check_creator(conn: &mut PgConnection, board_id: Uuid, user_id: Uuid) -> Result<(), ApiError> {
+    let _ = boards::table
+        .filter(boards::id.eq(board_id).and(boards::creator_id.eq(user_id)))
+        .select(boards::id)
+        .first::<Uuid>(conn)?;
+    Ok(())
+}
+
+/// Checks that the URL is http(s), the secret isn't empty and every event exists
+fn validate_webhook(webhook: &NewWebhook, events: &BoardEvents) -> Result<(), ApiError> {
+    let invalid = || ApiError::from_type(ApiErrorType::InvalidWebhook);
+    let url = Url::parse(&webhook.url).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https") || webhook.url.len() > 2048 {
+        return Err(invalid());
+    }
+    if webhook.secret.is_empty() || webhook.secret.len() > 255 {
+        return Err(invalid());
+    }
+    if !webhook.events.iter().all(|event| events.contains(event)) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+fn find_webhook(
+    conn: &mut PgConnection,
+    board_id: Uuid,
+    webhook_id: Uuid,
+) -> Result<Webhook, ApiError> {
+    let webhook = webhooks::table
+        .filter(webhooks::id.eq(webhook_id))
+        .filter(webhooks::board_id.eq(board_id))
+        .select(Webhook::as_select())
+        .first::<Webhook>(conn)?;
+    Ok(webhook)
+}
+
+/// # GET /boards/webhooks/events
+/// Returns the events a webhook can subscribe to
+///
+/// Every change made through the board routes is an event, named after the route
+/// # Returns
+/// * `events` - The names of the events
+/// ```json
+/// ["create_card", "update_card", "delete_card", ...]
+/// ```
+#[get("/webhooks/events")]
+pub async fn boards_get_webhook_events(
+    auth: AuthResult,
+    events: &State<BoardEvents>,
+) -> Result<ApiResponse<Vec<String>>, ApiResponse<ApiError>> {
+    let _ = auth.unpack()?.id;
+    Ok(ApiResponse::new(events.names().to_vec()))
+}
+
+/// # POST /boards/<board_id>/webhooks
+/// Registers a webhook for the board, only the creator of the board can do it
+///
+/// After each change to the board the webhook subscribed to, its URL receives a
+/// `POST` with the event as JSON. The body is signed with the secret, the
+/// `X-Webhook-Signature` header holds `sha256=<hex HMAC-SHA256 of the body>`.
+/// Failed deliveries are retried with a growing delay, up to 8 attempts
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// * `webhook` - The webhook, an empty `events` list subscribes to every event
+/// ```json
+/// {
+///     "url": "https://example.com/hooks/board",
+///     "secret": <secret>,
+///     "events": ["create_card", "update_card"],
+///     "active": true
+/// }
+/// ```
+/// # Returns
+/// * `webhook` - The webhook, without its secret
+/// ```json
+/// {
+///     "id": <webhook_id>,
+///     "board_id": <board_id>,
+///     "url": "https://example.com/hooks/board",
+///     "events": ["create_card", "update_card"],
+///     "active": true,
+///     "created_by": <user_id>,
+///     "created_at": "2025-03-31T09:00:00"
+/// }
+/// ```
+#[post("/<board_id>/webhooks", data = "<webhook>")]
+pub async fn boards_create_webhook(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    webhook: Json<NewWebhook>,
+    events: &State<BoardEvents>,
+) -> Result<ApiResponse<PubWebhook>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    validate_webhook(&webhook, events)?;
+
+    db.run(move |conn| {
+        // Check if current user is board creator
+        check_creator(conn, board_id, token)?;
+
+        let webhook = diesel::insert_into(webhooks::table)
+            .values(Webhook {
+                id: Uuid::new_v4(),
+                board_id,
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone(),
+                events: Value::from(webhook.events.clone()),
+                active: webhook.active,
+                created_by: Some(token),
+                created_at: Utc::now().naive_utc(),
+            })
+            .returning(Webhook::as_returning())
+            .get_result::<Webhook>(conn)?;
+
+        Ok::<PubWebhook, ApiError>(webhook.into())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/webhooks
+/// Returns the webhooks of the board, only the creator of the board can see them
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `webhooks` - A list of webhooks, without their secrets
+#[get("/<board_id>/webhooks")]
+pub async fn boards_get_webhooks(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+) -> Result<ApiResponse<Vec<PubWebhook>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        // Check if current user is board creator
+        check_creator(conn, board_id, token)?;
+
+        let webhooks = webhooks::table
+            .filter(webhooks::board_id.eq(board_id))
+            .order(webhooks::created_at.asc())
+            .select(Webhook::as_select())
+            .load::<Webhook>(conn)?;
+
+        Ok::<Vec<PubWebhook>, ApiError>(webhooks.into_iter().map(PubWebhook::from).collect())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /boards/<board_id>/webhooks/<webhook_id>
+/// Replaces the URL, secret, events and state of the webhook, only the creator of the board can do it
+///
+/// Pausing a webhook holds its pending deliveries until it is active again
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `webhook_id` - The id of the webhook
+/// * `auth` - Takes the token of the user
+/// * `webhook` - The webhook, in the same shape as when creating it
+/// # Returns
+/// * `webhook` - The webhook, without its secret
+#[put("/<board_id>/webhooks/<webhook_id>", data = "<webhook>")]
+pub async fn boards_update_webhook(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    webhook_id: String,
+    webhook: Json<NewWebhook>,
+    events: &State<BoardEvents>,
+) -> Result<ApiResponse<PubWebhook>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let webhook_id = Uuid::try_parse(&webhook_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    validate_webhook(&webhook, events)?;
+
+    db.run(move |conn| {
+        // Check if current user is board creator
+        check_creator(conn, board_id, token)?;
+
+        let webhook = diesel::update(webhooks::table)
+            .filter(webhooks::id.eq(webhook_id))
+            .filter(webhooks::board_id.eq(board_id))
+            .set((
+                webhooks::url.eq(&webhook.url),
+                webhooks::secret.eq(&webhook.secret),
+                webhooks::events.eq(Value::from(webhook.events.clone())),
+                webhooks::active.eq(webhook.active),
+            ))
+            .returning(Webhook::as_returning())
+            .get_result::<Webhook>(conn)?;
+
+        Ok::<PubWebhook, ApiError>(webhook.into())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /boards/<board_id>/webhooks/<webhook_id>
+/// Deletes the webhook along with its deliveries, only the creator of the board can do it
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `webhook_id` - The id of the webhook
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `webhook_id` - The id of the deleted webhook
+#[delete("/<board_id>/webhooks/<webhook_id>")]
+pub async fn boards_delete_webhook(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    webhook_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let webhook_id = Uuid::try_parse(&webhook_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        // Check if current user is board creator
+        check_creator(conn, board_id, token)?;
+
+        let webhook_id = diesel::delete(webhooks::table)
+            .filter(webhooks::id.eq(webhook_id))
+            .filter(webhooks::board_id.eq(board_id))
+            .returning(webhooks::id)
+            .get_result::<Uuid>(conn)?;
+
+        Ok::<Uuid, ApiError>(webhook_id)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /boards/<board_id>/webhooks/<webhook_id>/deliveries
+/// Returns the latest deliveries of the webhook, newest first
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `webhook_id` - The id of the webhook
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `deliveries` - Up to 200 deliveries
+/// ```json
+/// [
+///     {
+///         "id": <delivery_id>,
+///         "webhook_id": <webhook_id>,
+///         "event": "update_card",
+///         "payload": {
+///             "event": "update_card",
+///             "board_id": <board_id>,
+///             "actor_id": <user_id>,
+///             "data": <what the route changed, usually its response>,
+///             "occurred_at": "2025-03-31T09:00:00"
+///         },
+///         "status": "pending" | "delivered" | "failed",
+///         "attempts": 1,
+///         "next_attempt_at": "2025-03-31T09:00:20",
+///         "last_status_code": 500,
+///         "last_error": "Receiver answered 500 Internal Server Error",
+///         "created_at": "2025-03-31T09:00:00",
+///         "delivered_at": null
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/<board_id>/webhooks/<webhook_id>/deliveries")]
+pub async fn boards_get_webhook_deliveries(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    webhook_id: String,
+) -> Result<ApiResponse<Vec<WebhookDelivery>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let webhook_id = Uuid::try_parse(&webhook_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        // Check if current user is board creator
+        check_creator(conn, board_id, token)?;
+        let webhook = find_webhook(conn, board_id, webhook_id)?;
+
+        let deliveries = webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook.id))
+            .order(webhook_deliveries::created_at.desc())
+            .limit(MAX_DELIVERIES)
+            .select(WebhookDelivery::as_select())
+            .load::<WebhookDelivery>(conn)?;
+
+        Ok::<Vec<WebhookDelivery>, ApiError>(deliveries)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /boards/<board_id>/webhooks/<webhook_id>/deliveries/<delivery_id>/redeliver
+/// Sends the payload of a delivery again, as a new delivery with its own retries
+/// # Arguments
+/// * `board_id` - The id of the board
+/// * `webhook_id` - The id of the webhook
+/// * `delivery_id` - The id of the delivery to send again
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `delivery` - The new delivery
+#[post("/<board_id>/webhooks/<webhook_id>/deliveries/<delivery_id>/redeliver")]
+pub async fn boards_redeliver_webhook_delivery(
+    db: Db,
+    auth: AuthResult,
+    board_id: String,
+    webhook_id: String,
+    delivery_id: String,
+    queue: &State<Arc<WebhookQueue>>,
+) -> Result<ApiResponse<WebhookDelivery>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(&board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let webhook_id = Uuid::try_parse(&webhook_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let delivery_id = Uuid::try_parse(&delivery_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let delivery = db
+        .run(move |conn| {
+            // Check if current user is board creator
+            check_creator(conn, board_id, token)?;
+            let webhook = find_webhook(conn, board_id, webhook_id)?;
+
+            let delivery = webhook_deliveries::table
+                .filter(webhook_deliveries::id.eq(delivery_id))
+                .filter(webhook_deliveries::webhook_id.eq(webhook.id))
+                .select(WebhookDelivery::as_select())
+                .first::<WebhookDelivery>(conn)?;
+
+            Ok::<WebhookDelivery, ApiError>(WebhookQueries::redeliver(conn, &delivery)?)
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    queue.wake();
+    Ok(ApiResponse::new(delivery))
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use crate::models::webhook::WebhookQueue;
+use rocket::{
+    fairing::{self, Fairing, Info, Kind},
+    http::Method,
+    route::Route,
+    Build, Request, Response, Rocket,
+};
+
+/// Board routes that aren't `GET` but emit no event, they don't change the board
+/// itself or, for `delete_board`, the webhooks of the board are deleted with it
+const NOT_EVENTS: [&str; 6] = [
+    "boards_delete_board",
+    "boards_dry_run_rule",
+    "boards_create_webhook",
+    "boards_update_webhook",
+    "boards_delete_webhook",
+    "boards_redeliver_webhook_delivery",
+];
+
+/// Returns the webhook event a board route emits, its name without the `boards_` prefix
+pub fn event_name(route: &Route) -> Option<&str> {
+    let name = route.name.as_deref()?;
+    if route.method == Method::Get || NOT_EVENTS.contains(&name) {
+        return None;
+    }
+    name.strip_prefix("boards_")
+}
+
+/// Every event webhooks can subscribe to, collected from the mounted routes at ignition
+pub struct BoardEvents(Vec<String>);
+
+impl BoardEvents {
+    fn collect(rocket: &Rocket<Build>) -> Self {
+        let mut events = rocket
+            .routes()
+            .filter(|route| route.uri.path().starts_with("/boards/<board_id>"))
+            .filter_map(event_name)
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        events.sort_unstable();
+        events.dedup();
+        BoardEvents(events)
+    }
+
+    pub fn contains(&self, event: &str) -> bool {
+        self.0.iter().any(|name| name == event)
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.0
+    }
+}
+
+/// Wakes the delivery worker up after a successful change made through a board route
+///
+/// The routes queue their deliveries inside their own transaction, so by the time
+/// the response is ready they are committed, or rolled back together with the change
+pub struct WebhookEvents;
+
+#[rocket::async_trait]
+impl Fairing for WebhookEvents {
+    fn info(&self) -> Info {
+        Info {
+            name: "Webhook events",
+            kind: Kind::Ignite | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let events = BoardEvents::collect(&rocket);
+        Ok(rocket.manage(events))
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if response.status().code / 100 != 2 || request.route().and_then(event_name).is_none() {
+            return;
+        }
+        if let Some(queue) = request.rocket().state::<Arc<WebhookQueue>>() {
+            queue.wake();
+        }
+    }
+}
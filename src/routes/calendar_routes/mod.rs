@@ -0,0 +1,205 @@
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use rocket::http::ContentType;
+use uuid::Uuid;
+
+use crate::{
+    database::{calendar_queries::CalendarQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        calendar::{render, CalendarComponent, CalendarFeed, PubCalendarFeed},
+        BoardUsersRelation,
+    },
+    schema::{board_users_relation, boards, calendar_feeds},
+};
+
+/// # GET /calendar/<token>.ics?<kind>
+/// Returns the iCalendar file of a feed, calendar apps subscribe to this URL
+///
+/// Takes no token, the URL itself is the secret. Every date a card holds in a `date`
+/// custom field is an all-day entry
+/// # Arguments
+/// * `feed` - The token of the feed followed by `.ics`
+/// * `kind` - `event` (default) writes `VEVENT`s, `todo` writes `VTODO`s with their completion
+/// # Returns
+/// * `calendar` - A `text/calendar` file
+#[get("/<feed>?<kind>")]
+pub async fn get_calendar_feed(
+    db: Db,
+    feed: &str,
+    kind: Option<&str>,
+) -> Result<(ContentType, String), ApiResponse<ApiError>> {
+    let token = feed
+        .strip_suffix(".ics")
+        .ok_or_else(|| ApiError::from_type(ApiErrorType::NotFound))?
+        .to_string();
+    let component = CalendarComponent::from_param(kind);
+
+    let ics = db
+        .run(move |conn| {
+            let feed = CalendarQueries::find(conn, &token)?
+                .ok_or_else(|| ApiError::from_type(ApiErrorType::NotFound))?;
+            let name = match feed.board_id {
+                Some(board_id) => {
+                    // The feed stops working for users who left the board
+                    let _ = board_users_relation::table
+                        .filter(
+                            board_users_relation::board_id
+                                .eq(board_id)
+                                .and(board_users_relation::user_id.eq(feed.user_id)),
+                        )
+                        .first::<BoardUsersRelation>(conn)?;
+                    boards::table
+                        .filter(boards::id.eq(board_id))
+                        .select(boards::name)
+                        .first::<String>(conn)?
+                }
+                None => "Card dates".to_string(),
+            };
+            let entries = CalendarQueries::entries(conn, feed.user_id, feed.board_id)?;
+
+            Ok::<String, ApiError>(render(&name, &entries, component, Utc::now().naive_utc()))
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    let content_type = ContentType::new("text", "calendar").with_params(("charset", "utf-8"));
+    Ok((content_type, ics))
+}
+
+/// # GET /calendar/feeds
+/// Returns the calendar feeds of the user
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `feeds` - A list of feeds, `board_id` is `null` for the feed of every board
+/// ```json
+/// [
+///     {
+///         "id": <feed_id>,
+///         "board_id": <board_id>,
+///         "url": "/calendar/<token>.ics",
+///         "created_at": <creation_time>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/feeds")]
+pub async fn get_calendar_feeds(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Vec<PubCalendarFeed>>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        let feeds = calendar_feeds::table
+            .filter(calendar_feeds::user_id.eq(user_id))
+            .order(calendar_feeds::created_at.asc())
+            .select(CalendarFeed::as_select())
+            .load::<CalendarFeed>(conn)?;
+
+        Ok::<Vec<PubCalendarFeed>, ApiError>(feeds.into_iter().map(PubCalendarFeed::from).collect())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /calendar/feed
+/// Creates the feed of every board of the user, or moves it to a new URL
+///
+/// The previous URL stops working, to revoke a leaked one
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `feed` - The feed
+/// ```json
+/// {
+///     "id": <feed_id>,
+///     "board_id": null,
+///     "url": "/calendar/<token>.ics",
+///     "created_at": <creation_time>
+/// }
+/// ```
+#[post("/feed")]
+pub async fn regenerate_calendar_feed(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<PubCalendarFeed>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        let feed = CalendarQueries::regenerate(conn, user_id, None)?;
+        Ok::<PubCalendarFeed, ApiError>(feed.into())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /calendar/boards/<board_id>/feed
+/// Creates the feed of a single board, or moves it to a new URL
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `board_id` - The id of the board
+/// # Returns
+/// * `feed` - The feed
+#[post("/boards/<board_id>/feed")]
+pub async fn regenerate_board_calendar_feed(
+    db: Db,
+    auth: AuthResult,
+    board_id: &str,
+) -> Result<ApiResponse<PubCalendarFeed>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let board_id = Uuid::try_parse(board_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let _ = board_users_relation::table
+            .filter(
+                board_users_relation::board_id
+                    .eq(board_id)
+                    .and(board_users_relation::user_id.eq(user_id)),
+            )
+            .first::<BoardUsersRelation>(conn)?;
+
+        let feed = CalendarQueries::regenerate(conn, user_id, Some(board_id))?;
+        Ok::<PubCalendarFeed, ApiError>(feed.into())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /calendar/feeds/<feed_id>
+/// Deletes the feed, its URL stops working
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `feed_id` - The id of the feed
+/// # Returns
+/// * `feed_id` - The id of the deleted feed
+#[delete("/feeds/<feed_id>")]
+pub async fn delete_calendar_feed(
+    db: Db,
+    auth: AuthResult,
+    feed_id: &str,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let feed_id = Uuid::try_parse(feed_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let feed_id = diesel::delete(calendar_feeds::table)
+            .filter(calendar_feeds::id.eq(feed_id))
+            .filter(calendar_feeds::user_id.eq(user_id))
+            .returning(calendar_feeds::id)
+            .get_result::<Uuid>(conn)?;
+
+        Ok::<Uuid, ApiError>(feed_id)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
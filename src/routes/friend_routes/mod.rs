@@ -1,15 +1,21 @@
+use std::sync::Arc;
+
 use chrono::{Duration, Local, NaiveDateTime};
 use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::State;
+use serde_json::json;
 use uuid::Uuid;
 use getrandom;
 
 use crate::{
-    database::Db,
+    database::{notification_queries::NotificationQueries, Db},
     errors::{ApiError, ApiErrorType},
     models::{
         api_response::ApiResponse,
         auth::AuthResult,
         friends::{FriendCode, FriendRelationship},
+        notification::NotificationKind,
+        ws_state::WsState,
     },
     schema::{friends, users},
 };
@@ -77,7 +83,7 @@ pub async fn get_friend_code(
 }
 
 /// # POST /friends/redeem
-/// Redeem a friend code
+/// Redeem a friend code, the owner of the code gets notified
 /// # Arguments
 /// * `db` - The database connection
 /// * `auth` - The authentication result
@@ -88,58 +94,77 @@ pub async fn get_friend_code(
 pub async fn redeem_friend_code(
     db: Db,
     auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
     code: String,
 ) -> Result<ApiResponse<FriendRelationship>, ApiResponse<ApiError>> {
     let user_id = auth.unpack()?.id;
     let code = code.trim().replace("\"", "");
 
-    db.run(move |conn| {
-        conn.transaction(|conn| {
-            // Find code owner
-            let friend = users::table
-                .filter(users::friend_code.eq(&code))
-                // .filter(users::friend_code_expires_at.gt(Local::now().naive_utc()))
-                .select((users::id, users::friend_code, users::friend_code_expires_at))
-                .first::<(Uuid, Option<String>, Option<NaiveDateTime>)>(conn)?;
-
-            // Prevent self-friending
-
-            if friend.0 == user_id {
-                return Err(ApiError::from_type(ApiErrorType::InvalidRequest));
-            }
-
-            // Create mutual friendship
-            diesel::insert_into(friends::table)
-                .values(&vec![
-                    (
-                        friends::user_id.eq(user_id),
-                        friends::friend_id.eq(friend.0),
-                    ),
-                    (
-                        friends::user_id.eq(friend.0),
-                        friends::friend_id.eq(user_id),
-                    ),
-                ])
-                .execute(conn)?;
-
-            // Invalidate used code
-            diesel::update(users::table.find(friend.0))
-                .set((
-                    users::friend_code.eq::<Option<String>>(None),
-                    users::friend_code_expires_at.eq::<Option<NaiveDateTime>>(None),
+    let (relationship, notification) = db
+        .run(move |conn| {
+            conn.transaction(|conn| {
+                // Find code owner
+                let friend = users::table
+                    .filter(users::friend_code.eq(&code))
+                    // .filter(users::friend_code_expires_at.gt(Local::now().naive_utc()))
+                    .select((users::id, users::friend_code, users::friend_code_expires_at))
+                    .first::<(Uuid, Option<String>, Option<NaiveDateTime>)>(conn)?;
+
+                // Prevent self-friending
+
+                if friend.0 == user_id {
+                    return Err(ApiError::from_type(ApiErrorType::InvalidRequest));
+                }
+
+                // Create mutual friendship
+                diesel::insert_into(friends::table)
+                    .values(&vec![
+                        (
+                            friends::user_id.eq(user_id),
+                            friends::friend_id.eq(friend.0),
+                        ),
+                        (
+                            friends::user_id.eq(friend.0),
+                            friends::friend_id.eq(user_id),
+                        ),
+                    ])
+                    .execute(conn)?;
+
+                // Invalidate used code
+                diesel::update(users::table.find(friend.0))
+                    .set((
+                        users::friend_code.eq::<Option<String>>(None),
+                        users::friend_code_expires_at.eq::<Option<NaiveDateTime>>(None),
+                    ))
+                    .execute(conn)?;
+
+                let username = users::table
+                    .find(user_id)
+                    .select(users::username)
+                    .first::<String>(conn)?;
+                let notification = NotificationQueries::create(
+                    conn,
+                    friend.0,
+                    Some(user_id),
+                    NotificationKind::FriendAdded,
+                    json!({ "friend_id": user_id, "username": username }),
+                )?;
+
+                Ok((
+                    FriendRelationship {
+                        user_id,
+                        friend_id: friend.0,
+                        created_at: Local::now().naive_utc(),
+                    },
+                    notification,
                 ))
-                .execute(conn)?;
-
-            Ok(FriendRelationship {
-                user_id,
-                friend_id: friend.0,
-                created_at: Local::now().naive_utc(),
             })
         })
-    })
-    .await
-    .map(ApiResponse::new)
-    .map_err(|e| ApiResponse::from_error(e.into()))
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(vec![notification]).await;
+    Ok(ApiResponse::new(relationship))
 }
 
 /// Generate unique 8-digit alphanumeric code
@@ -5,6 +5,11 @@ mod board_routes;
 mod users_interaction;
 mod auth_routes;
 mod friend_routes;
+mod notification_routes;
+mod time_routes;
+mod workspace_routes;
+mod token_routes;
+mod calendar_routes;
 pub trait AuthorizationRoutes {
     fn mount_auth_routes(self) -> Self;
     fn mount_board_routes(self) -> Self;
@@ -1,12 +1,16 @@
-use rocket::{fs::FileServer, Build, Rocket};
+use std::sync::Arc;
 
-use crate::models::ws_state::WsState;
+use rocket::{fairing::AdHoc, fs::FileServer, Build, Rocket};
+
+use crate::models::{operation::UndoConfig, webhook::WebhookQueue, ws_state::WsState};
 
 use super::{
     auth_routes,
     board_routes::*,
-    file_routes, friend_routes, routes,
-    users_interaction::{self, chat::*, conversations::*},
+    calendar_routes,
+    file_routes, friend_routes, notification_routes, routes, time_routes, token_routes,
+    workspace_routes,
+    users_interaction::{self, chat::*, conversations::*, groups::*, message_actions::*},
     AuthorizationRoutes,
 };
 
@@ -33,14 +37,76 @@ impl AuthorizationRoutes for Rocket<Build> {
             "/friends",
             routes![
                 users_interaction::get_friends,
+                users_interaction::get_friends_presence,
                 friend_routes::generate_friend_code,
                 friend_routes::get_friend_code,
                 friend_routes::redeem_friend_code,
             ],
         )
+        .mount(
+            "/notifications",
+            routes![
+                notification_routes::get_notifications,
+                notification_routes::mark_notification_read,
+                notification_routes::mark_all_notifications_read,
+            ],
+        )
+        .mount(
+            "/time",
+            routes![time_routes::get_time_summary, time_routes::get_running_timer],
+        )
+        .mount(
+            "/workspaces",
+            routes![
+                workspace_routes::workspace_actions::create_workspace,
+                workspace_routes::workspace_actions::get_workspaces,
+                workspace_routes::workspace_actions::get_workspace,
+                workspace_routes::workspace_actions::update_workspace,
+                workspace_routes::workspace_actions::delete_workspace,
+                workspace_routes::member_actions::add_workspace_member,
+                workspace_routes::member_actions::update_workspace_member,
+                workspace_routes::member_actions::remove_workspace_member,
+            ],
+        )
+        .mount(
+            "/tokens",
+            routes![
+                token_routes::create_api_token,
+                token_routes::get_api_tokens,
+                token_routes::revoke_api_token,
+            ],
+        )
+        .mount(
+            "/calendar",
+            routes![
+                calendar_routes::get_calendar_feed,
+                calendar_routes::get_calendar_feeds,
+                calendar_routes::regenerate_calendar_feed,
+                calendar_routes::regenerate_board_calendar_feed,
+                calendar_routes::delete_calendar_feed,
+            ],
+        )
         .mount(
             "/chat_source",
-            routes![events, last_messages, get_or_create_conversation],
+            routes![
+                events,
+                last_messages,
+                get_or_create_conversation,
+                create_group,
+                get_groups,
+                get_group,
+                rename_group,
+                add_group_member,
+                update_group_member,
+                remove_group_member,
+                leave_group,
+                edit_message,
+                delete_message,
+                get_message_edits,
+                read_message,
+                get_conversations,
+                get_conversation_reads,
+            ],
         )
     }
 
@@ -52,12 +118,17 @@ impl AuthorizationRoutes for Rocket<Build> {
                 base_actions::boards_get_boards,
                 base_actions::boards_get_board,
                 base_actions::boards_update_board,
+                base_actions::boards_move_to_workspace,
                 base_actions::boards_delete_board,
                 column_actions::boards_create_column,
                 column_actions::boards_get_columns,
                 column_actions::boards_get_column,
                 column_actions::boards_update_column,
                 column_actions::boards_delete_column,
+                lane_actions::boards_create_lane,
+                lane_actions::boards_get_lanes,
+                lane_actions::boards_update_lane,
+                lane_actions::boards_delete_lane,
                 card_actions::boards_create_card,
                 card_actions::boards_get_cards,
                 card_actions::boards_get_card,
@@ -65,20 +136,70 @@ impl AuthorizationRoutes for Rocket<Build> {
                 card_actions::boards_delete_card,
                 card_actions::boards_reorder_cards,
                 card_editing::boards_get_card_by_id,
+                card_editing::boards_set_card_parent,
+                card_editing::boards_assign_card,
                 card_editing::boards_add_attachment_to_card,
                 card_editing::boards_get_attachments_of_card,
                 card_editing::boards_delete_attachment_of_card,
+                recurrence_actions::boards_set_card_recurrence,
+                recurrence_actions::boards_get_card_recurrence,
+                recurrence_actions::boards_delete_card_recurrence,
                 collaborator_actions::boards_add_collaborator,
                 collaborator_actions::boards_get_collaborators,
                 collaborator_actions::boards_get_collaborator,
                 collaborator_actions::boards_remove_collaborator,
+                custom_field_actions::boards_create_custom_field,
+                custom_field_actions::boards_get_custom_fields,
+                custom_field_actions::boards_update_custom_field,
+                custom_field_actions::boards_delete_custom_field,
+                custom_field_actions::boards_set_card_field,
+                time_actions::boards_start_timer,
+                time_actions::boards_stop_timer,
+                time_actions::boards_add_time_entry,
+                time_actions::boards_delete_time_entry,
+                time_actions::boards_get_card_time,
+                time_actions::boards_get_board_time,
+                sprint_actions::boards_create_sprint,
+                sprint_actions::boards_get_sprints,
+                sprint_actions::boards_get_sprint_cards,
+                sprint_actions::boards_update_sprint,
+                sprint_actions::boards_delete_sprint,
+                sprint_actions::boards_start_sprint,
+                sprint_actions::boards_complete_sprint,
+                sprint_actions::boards_get_sprint_burndown,
+                sprint_actions::boards_set_card_sprint,
+                analytics_actions::boards_get_analytics,
+                analytics_actions::boards_get_card_timeline,
+                analytics_actions::boards_get_stale_cards,
+                undo_actions::boards_get_operations,
+                undo_actions::boards_undo_operation,
+                revision_actions::boards_get_card_revisions,
+                revision_actions::boards_diff_card_revisions,
+                revision_actions::boards_restore_card_revision,
+                rule_actions::boards_create_rule,
+                rule_actions::boards_get_rules,
+                rule_actions::boards_update_rule,
+                rule_actions::boards_delete_rule,
+                rule_actions::boards_dry_run_rule,
+                rule_actions::boards_get_rule_executions,
+                webhook_actions::boards_get_webhook_events,
+                webhook_actions::boards_create_webhook,
+                webhook_actions::boards_get_webhooks,
+                webhook_actions::boards_update_webhook,
+                webhook_actions::boards_delete_webhook,
+                webhook_actions::boards_get_webhook_deliveries,
+                webhook_actions::boards_redeliver_webhook_delivery,
+                chat_actions::boards_get_chat,
             ],
         )
+        .attach(webhook_events::WebhookEvents)
     }
 
     fn manage_state(self) -> Self {
         let ws_state = WsState::new();
         self.manage(ws_state)
+            .manage(Arc::new(WebhookQueue::default()))
+            .attach(AdHoc::config::<UndoConfig>())
     }
 
     fn mount_uploads(self) -> Self {
@@ -0,0 +1,105 @@
+use uuid::Uuid;
+
+use crate::{
+    database::{notification_queries::NotificationQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        notification::{Notification, NotificationPage},
+    },
+};
+
+/// # GET /notifications?<page>&<per_page>&<unread>
+/// Returns the notifications of the user, newest first
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `page` - The page to return, starting at 1
+/// * `per_page` - The size of a page, at most 100
+/// * `unread` - Only return notifications that were not read yet
+/// # Returns
+/// * `page` - A page of notifications
+/// ```json
+/// {
+///     "notifications": [
+///         {
+///             "id": <notification_id>,
+///             "user_id": <user_id>,
+///             "actor_id": <actor_id>,
+///             "kind": "added_to_board" | "card_assigned" | "friend_added" | "chat_message"
+///                 | "mentioned" | "added_to_workspace" | "added_to_group",
+///             "payload": { ... },
+///             "read_at": <read_time>,
+///             "created_at": <creation_time>
+///         },
+///         ...
+///     ],
+///     "page": <page>,
+///     "per_page": <per_page>,
+///     "total": <total>,
+///     "unread": <unread>
+/// }
+/// ```
+#[get("/?<page>&<per_page>&<unread>")]
+pub async fn get_notifications(
+    db: Db,
+    auth: AuthResult,
+    page: Option<i64>,
+    per_page: Option<i64>,
+    unread: Option<bool>,
+) -> Result<ApiResponse<NotificationPage>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    NotificationQueries::load_page(
+        &db,
+        user_id,
+        unread.unwrap_or(false),
+        page.unwrap_or(1),
+        per_page.unwrap_or(20),
+    )
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /notifications/<notification_id>/read
+/// Marks the notification as read
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `notification_id` - The id of the notification
+/// # Returns
+/// * `notification` - The notification
+#[put("/<notification_id>/read")]
+pub async fn mark_notification_read(
+    db: Db,
+    auth: AuthResult,
+    notification_id: &str,
+) -> Result<ApiResponse<Notification>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let notification_id = Uuid::try_parse(notification_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    NotificationQueries::mark_read(&db, user_id, notification_id)
+        .await
+        .map(ApiResponse::new)
+        .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /notifications/read
+/// Marks all the notifications of the user as read
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `count` - The number of notifications that were marked
+#[put("/read")]
+pub async fn mark_all_notifications_read(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<usize>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    NotificationQueries::mark_all_read(&db, user_id)
+        .await
+        .map(ApiResponse::new)
+        .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,69 @@
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+
+use crate::{
+    database::{
+        time_queries::{TimeQueries, TimeScope},
+        Db,
+    },
+    errors::ApiError,
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        time_entry::{TimeEntry, TimeReport},
+    },
+    schema::time_entries,
+};
+
+use super::board_routes::helpers::parse_date_range;
+
+/// # GET /time?<from>&<to>&<format>
+/// Returns the time the user tracked on every board, `format=csv` exports it as CSV
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `from` - The first day of the range, as `YYYY-MM-DD`, 30 days ago by default
+/// * `to` - The last day of the range, as `YYYY-MM-DD`, today by default
+/// * `format` - `json` or `csv`
+/// # Returns
+/// * `summary` - Same as `GET /boards/<board_id>/cards/<card_id>/time`
+#[get("/?<from>&<to>&<format>")]
+pub async fn get_time_summary(
+    db: Db,
+    auth: AuthResult,
+    from: Option<String>,
+    to: Option<String>,
+    format: Option<String>,
+) -> Result<TimeReport, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let (from, to) = parse_date_range(from, to)?;
+
+    db.run(move |conn| TimeQueries::summary(conn, TimeScope::User(user_id), from, to))
+        .await
+        .map(|summary| TimeReport::new(summary, format.as_deref()))
+        .map_err(|e| ApiResponse::from_error(e.into()))
+}
+
+/// # GET /time/running
+/// Returns the timer the user is running, if any
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `entry` - The running time entry or `null`
+#[get("/running")]
+pub async fn get_running_timer(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Option<TimeEntry>>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        time_entries::table
+            .filter(time_entries::user_id.eq(user_id))
+            .filter(time_entries::ended_at.is_null())
+            .select(TimeEntry::as_select())
+            .first::<TimeEntry>(conn)
+            .optional()
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(|e| ApiResponse::from_error(e.into()))
+}
@@ -0,0 +1,149 @@
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{api_token_queries::ApiTokenQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        api_token::{ApiToken, CreatedApiToken, NewApiToken, PubApiToken},
+        auth::AuthResult,
+    },
+    schema::api_tokens,
+};
+
+/// # POST /tokens
+/// Creates a personal API token for scripts, sent as `Authorization: Bearer pat_...`
+///
+/// The token is only returned here, it is stored hashed. Scopes are `boards:read`,
+/// `boards:write`, `boards:admin`, `files:read` and `files:write`, a `write` scope also
+/// grants `read` and `boards:admin` also grants `boards:write`. Deleting boards, moving them
+/// between workspaces and managing workspaces, collaborators or webhooks needs `boards:admin`.
+/// Tokens can't be used to manage tokens, chat or change the account
+/// # Arguments
+/// * `auth` - Takes the token of the user, has to be a session token
+/// * `token` - The token
+/// ```json
+/// {
+///     "name": "CI",
+///     "scopes": ["boards:read", "files:write"],
+///     "expires_at": "2026-01-01T00:00:00"
+/// }
+/// ```
+/// # Returns
+/// * `token` - The token and its details
+/// ```json
+/// {
+///     "token": "pat_...",
+///     "id": <token_id>,
+///     "name": "CI",
+///     "prefix": "pat_1a2b3c4d",
+///     "scopes": ["boards:read", "files:write"],
+///     "expires_at": "2026-01-01T00:00:00",
+///     "last_used_at": null,
+///     "created_at": <creation_time>
+/// }
+/// ```
+#[post("/", data = "<token>")]
+pub async fn create_api_token(
+    db: Db,
+    auth: AuthResult,
+    token: Json<NewApiToken>,
+) -> Result<ApiResponse<CreatedApiToken>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let name = token.name.trim();
+    if name.is_empty() || name.len() > 255 || token.scopes.is_empty() {
+        return Err(ApiResponse::from_error_type(ApiErrorType::InvalidApiToken));
+    }
+    if token
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now().naive_utc())
+    {
+        return Err(ApiResponse::from_error_type(ApiErrorType::InvalidApiToken));
+    }
+
+    db.run(move |conn| {
+        let (row, token) = ApiTokenQueries::create(conn, user_id, &token)?;
+        Ok::<CreatedApiToken, ApiError>(CreatedApiToken {
+            token,
+            info: row.into(),
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /tokens
+/// Returns the API tokens of the user, newest first, with when each was last used
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `tokens` - A list of tokens, without the tokens themselves
+/// ```json
+/// [
+///     {
+///         "id": <token_id>,
+///         "name": "CI",
+///         "prefix": "pat_1a2b3c4d",
+///         "scopes": ["boards:read"],
+///         "expires_at": null,
+///         "last_used_at": <last_use_time>,
+///         "created_at": <creation_time>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/")]
+pub async fn get_api_tokens(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Vec<PubApiToken>>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        let tokens = api_tokens::table
+            .filter(api_tokens::user_id.eq(user_id))
+            .order(api_tokens::created_at.desc())
+            .select(ApiToken::as_select())
+            .load::<ApiToken>(conn)?;
+
+        Ok::<Vec<PubApiToken>, ApiError>(tokens.into_iter().map(PubApiToken::from).collect())
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /tokens/<token_id>
+/// Revokes the API token, requests made with it fail right away
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `token_id` - The id of the token
+/// # Returns
+/// * `token_id` - The id of the revoked token
+#[delete("/<token_id>")]
+pub async fn revoke_api_token(
+    db: Db,
+    auth: AuthResult,
+    token_id: &str,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let token_id = Uuid::try_parse(token_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let token_id = diesel::delete(api_tokens::table)
+            .filter(api_tokens::id.eq(token_id))
+            .filter(api_tokens::user_id.eq(user_id))
+            .returning(api_tokens::id)
+            .get_result::<Uuid>(conn)?;
+
+        Ok::<Uuid, ApiError>(token_id)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
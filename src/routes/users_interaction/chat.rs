@@ -11,37 +11,123 @@ use uuid::Uuid;
 use ws::{Message, *};
 
 use crate::{
-    database::Db,
+    database::{
+        chat_queries::{ChatQueries, DEFAULT_PAGE_SIZE},
+        Db,
+    },
     errors::{ApiError, ApiErrorType},
     jwt::Token,
     models::{
         api_response::ApiResponse,
-        messages::{ChatMessageDTO, ClientMessage},
-        ws_state::{WsMessage, WsState},
+        auth::AuthResult,
+        messages::{ChatMessageDTO, ClientAction, ClientMessage, MessageCursor, MessagePage},
+        presence::ClientSignal,
+        ws_state::{ConnectionId, WsMessage, WsState},
     },
     schema::chat_messages,
 };
 
-use super::helpers::get_last_messages;
+use super::helpers::{apply_client_action, notify_absent_members};
 
-#[get("/last_messages/<conversation_id>")]
+/// # GET /chat_source/last_messages/<conversation_id>?<before>&<after>&<limit>
+/// Returns a page of the history of the conversation, oldest message first
+///
+/// Only members of the conversation can read it, for the chat of a board that is
+/// whoever is currently a member of the board. Deleted messages are returned as
+/// tombstones, with an empty content and `deleted` set
+/// # Arguments
+/// * `conversation_id` - The id of the conversation
+/// * `before` - Only messages sent before this cursor, the newest ones when left out
+/// * `after` - Only messages sent after this cursor, the page then starts right after it
+/// * `limit` - The size of the page, 50 by default and at most 100
+/// * `auth` - Takes the token of the user
+///
+/// A cursor is a message id of the conversation, a timestamp in milliseconds or a
+/// `YYYY-MM-DDTHH:MM:SS` UTC time
+/// # Returns
+/// * `page` - The messages and the cursor of the next page, `null` when there is none.
+///   Pass it as `before` to scroll back, or as `after` when paging forward
+/// ```json
+/// {
+///     "messages": [
+///         {
+///             "id": <message_id>,
+///             "sender_id": <user_id>,
+///             "conversation_id": <conversation_id>,
+///             "content": <content>,
+///             "file_id": <file_id>,
+///             "deleted": false,
+///             "created_at": <send_time>,
+///             "updated_at": <edit_time>
+///         },
+///         ...
+///     ],
+///     "next_cursor": <message_id>
+/// }
+/// ```
+#[get("/last_messages/<conversation_id>?<before>&<after>&<limit>")]
 pub async fn last_messages(
     db: Db,
+    auth: AuthResult,
     conversation_id: String,
-) -> Result<ApiResponse<Vec<ChatMessageDTO>>, ApiResponse<ApiError>> {
-    let conversation_id = Uuid::parse_str(&conversation_id);
-    if conversation_id.is_err() {
-        return Err(ApiResponse::new(ApiError::from_type(
-            ApiErrorType::FailedToParseUUID,
-        )));
-    }
-    get_last_messages(db, conversation_id.unwrap()).await
+    before: Option<String>,
+    after: Option<String>,
+    limit: Option<i64>,
+) -> Result<ApiResponse<MessagePage>, ApiResponse<ApiError>> {
+    let user_id = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let before = before
+        .map(|cursor| cursor.parse::<MessageCursor>())
+        .transpose()?;
+    let after = after
+        .map(|cursor| cursor.parse::<MessageCursor>())
+        .transpose()?;
+
+    db.run(move |conn| {
+        if !ChatQueries::is_member(conn, conversation_id, user_id)? {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+        ChatQueries::history(
+            conn,
+            conversation_id,
+            before,
+            after,
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
 }
 
+/// First message of the chat socket, the conversation is optional since the socket can
+/// subscribe to conversations later on
 #[derive(Deserialize, Default, Debug)]
 struct Handshake {
     token: String,
-    conversation_id: String,
+    conversation_id: Option<String>,
+}
+
+/// Subscribes the connection to the conversation when the user is a member of it
+async fn subscribe(
+    db: &Db,
+    ws_state: &WsState,
+    connection_id: ConnectionId,
+    user_id: Uuid,
+    conv_id: Uuid,
+) {
+    let is_member = db
+        .run(move |conn| ChatQueries::is_member(conn, conv_id, user_id))
+        .await
+        .unwrap_or(false);
+    let reply = if is_member {
+        ws_state.subscribe(&connection_id, &conv_id).await;
+        WsMessage::Subscribed(conv_id)
+    } else {
+        WsMessage::Error("Not a member of the conversation".to_string())
+    };
+    ws_state.send_to_connection(&connection_id, reply).await;
 }
 
 #[get("/events")]
@@ -54,37 +140,90 @@ pub async fn events(ws: WebSocket, ws_state: &State<Arc<WsState>>, db: Db) -> Ch
     ws.channel(move |stream| {
         Box::pin(async move {
             let (mut sender, mut receiver) = stream.split();
-            let mut user_id = Uuid::nil();
 
             // Wait for initial handshake
-            if let Some(Ok(message)) = receiver.next().await {
-                if let Message::Text(text) = message {
-                    let handshake: Handshake = serde_json::from_str(&text).unwrap_or_default();
-                    let user_data = match Token::decode_token(handshake.token) {
-                        Ok(token) => token.claims.user,
-                        Err(_) => return Ok(()),
-                    };
-                    let conv_id = Uuid::parse_str(&handshake.conversation_id).unwrap_or_default();
-
-                    user_id = user_data.id;
+            let Some(Ok(Message::Text(text))) = receiver.next().await else {
+                return Ok(());
+            };
+            let handshake: Handshake = serde_json::from_str(&text).unwrap_or_default();
+            let user_id = match Token::decode_token(handshake.token) {
+                Ok(token) => token.claims.user.id,
+                Err(_) => return Ok(()),
+            };
+            let conv_id = handshake
+                .conversation_id
+                .map(|conv_id| Uuid::parse_str(&conv_id).unwrap_or_default());
+
+            if let Some(conv_id) = conv_id {
+                let is_member = db
+                    .run(move |conn| ChatQueries::is_member(conn, conv_id, user_id))
+                    .await
+                    .unwrap_or(false);
+                if !is_member {
                     let _ = sender
-                        .send(Message::Text(format!(
-                            "{{\"message\": \"Connected to chat: {}\"}}",
-                            conv_id
-                        )))
+                        .send(Message::Text(
+                            "{\"error\": \"Not a member of the conversation\"}".to_string(),
+                        ))
                         .await;
-
-                    ws_state.register_member(&user_data.id, sender).await;
-                    ws_state.add_to_conversation(&conv_id, &user_data.id).await;
-                    dbg!("Succsessful handshake", &ws_state);
+                    return Ok(());
                 }
+                let _ = sender
+                    .send(Message::Text(format!(
+                        "{{\"message\": \"Connected to chat: {}\"}}",
+                        conv_id
+                    )))
+                    .await;
+            } else {
+                let _ = sender
+                    .send(Message::Text("{\"message\": \"Connected\"}".to_string()))
+                    .await;
+            }
+
+            let connection_id = ws_state.register_member(&user_id, sender).await;
+            if let Some(conv_id) = conv_id {
+                ws_state.subscribe(&connection_id, &conv_id).await;
             }
+            ws_state.touch(&user_id).await;
 
             // Handle remaining messages
             while let Some(Ok(message)) = receiver.next().await {
                 match message {
                     Message::Text(text) => {
-                        dbg!(&text);
+                        ws_state.touch(&user_id).await;
+                        if let Ok(signal) = serde_json::from_str::<ClientSignal>(&text) {
+                            match signal {
+                                ClientSignal::Subscribe { conversation_id } => {
+                                    subscribe(
+                                        &db,
+                                        &ws_state,
+                                        connection_id,
+                                        user_id,
+                                        conversation_id,
+                                    )
+                                    .await
+                                }
+                                ClientSignal::Unsubscribe { conversation_id } => {
+                                    ws_state.unsubscribe(&connection_id, &conversation_id).await;
+                                    ws_state
+                                        .send_to_connection(
+                                            &connection_id,
+                                            WsMessage::Unsubscribed(conversation_id),
+                                        )
+                                        .await
+                                }
+                                ClientSignal::Typing { conversation_id } => {
+                                    ws_state.start_typing(&conversation_id, &user_id).await
+                                }
+                                ClientSignal::StoppedTyping { conversation_id } => {
+                                    ws_state.stop_typing(&conversation_id, &user_id).await
+                                }
+                            }
+                            continue;
+                        }
+                        if let Ok(action) = serde_json::from_str::<ClientAction>(&text) {
+                            apply_client_action(&db, &ws_state, user_id, action).await;
+                            continue;
+                        }
                         let message: ClientMessage =
                             serde_json::from_str(&text).unwrap_or_default();
                         let conv_id = Uuid::parse_str(&message.conversation_id).unwrap_or_default();
@@ -92,38 +231,51 @@ pub async fn events(ws: WebSocket, ws_state: &State<Arc<WsState>>, db: Db) -> Ch
                             continue;
                         }
 
-                        dbg!(&message);
-                        if !ws_state.user_in_conversation(&conv_id, &user_id).await {
-                            ws_state.add_to_conversation(&conv_id, &user_id).await;
+                        // Board members can change at any time, so access is checked
+                        // for every message and only current members receive it
+                        let members = db
+                            .run(move |conn| ChatQueries::members(conn, conv_id))
+                            .await
+                            .unwrap_or_default();
+                        if !members.contains(&user_id) {
+                            continue;
                         }
+                        // Sending to a conversation follows it on this connection
+                        ws_state.subscribe(&connection_id, &conv_id).await;
 
+                        let mut message: ChatMessageDTO = message.into();
+                        message.sender_id = user_id;
+                        ws_state.stop_typing(&conv_id, &user_id).await;
                         let _ = ws_state
-                            .send(&conv_id, WsMessage::Chat(message.clone().into()))
+                            .send_to_members(&conv_id, &members, WsMessage::Chat(message.clone()))
                             .await;
                         // send message to db
                         let db_clone = Arc::clone(&db);
-                        let _ = db_clone
+                        let stored = message.clone();
+                        let saved = db_clone
                             .run(move |conn| {
                                 diesel::insert_into(chat_messages::table)
-                                    .values::<ChatMessageDTO>(message.clone().into())
+                                    .values::<ChatMessageDTO>(stored)
                                     .execute(conn)
                             })
                             .await;
+                        if saved.is_ok() {
+                            // Sending a message reads the conversation up to it
+                            let message_id = message.id;
+                            let _ = db
+                                .run(move |conn| ChatQueries::mark_read(conn, message_id, user_id))
+                                .await;
+                            notify_absent_members(&db, &ws_state, &message).await;
+                        }
                     }
 
-                    Message::Close(_) => {
-                        dbg!("closing connection");
-                        let _ = ws_state.unregister(&user_id).await;
-                        dbg!("closing connection stop");
-                        break;
-                    }
+                    Message::Close(_) => break,
 
-                    _ => {
-                        dbg!("unknown message");
-                        break;
-                    }
+                    _ => break,
                 }
             }
+            // The socket can also drop without a close frame
+            let _ = ws_state.unregister(&connection_id).await;
 
             Ok(())
         })
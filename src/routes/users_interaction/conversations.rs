@@ -1,12 +1,12 @@
-use crate::database::Db;
-use crate::errors::ApiError;
+use crate::database::{chat_queries::ChatQueries, Db};
+use crate::errors::{ApiError, ApiErrorType};
 use crate::models::{
     api_response::ApiResponse,
     auth::AuthResult,
-    messages::Conversation,
+    messages::{Conversation, ConversationSummary, ReadReceipt},
     user::{PubUser, User},
 };
-use crate::schema::{conversations, users};
+use crate::schema::users;
 use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
 use uuid::Uuid;
 
@@ -35,9 +35,12 @@ pub async fn get_or_create_conversation(
     member_one: &str,
     member_two: &str,
 ) -> Result<ApiResponse<(Conversation, PubUser, PubUser)>, ApiResponse<ApiError>> {
-    let _ = auth.unpack()?.id;
+    let token = auth.unpack()?.id;
     let member_one = Uuid::parse_str(member_one).map_err(|e| ApiError::from_error(e))?;
     let member_two = Uuid::parse_str(member_two).map_err(|e| ApiError::from_error(e))?;
+    if token != member_one && token != member_two {
+        return Err(ApiResponse::from_error_type(ApiErrorType::Unauthorized));
+    }
     let conv = get_conversation_with_members(&db, member_one, member_two).await;
 
     if let Ok(conv) = conv {
@@ -46,12 +49,7 @@ pub async fn get_or_create_conversation(
 
     db.run(move |conn| {
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            let conversation = diesel::insert_into(conversations::table)
-                .values((
-                    conversations::member_one.eq(member_one),
-                    conversations::member_two.eq(member_two),
-                ))
-                .get_result::<Conversation>(conn)?;
+            let conversation = ChatQueries::create_direct(conn, member_one, member_two)?;
             let member_one: PubUser = users::table
                 .filter(users::id.eq(member_one))
                 .first::<User>(conn)?
@@ -67,3 +65,91 @@ pub async fn get_or_create_conversation(
     .map_err(|e| ApiResponse::from_error(e.into()))
     .map(|conversation| ApiResponse::new(conversation))
 }
+
+/// # GET /chat_source/conversations
+/// Returns the conversations of the user, most recently active first
+///
+/// Covers direct conversations, groups and the chats of the boards of the user
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `conversations` - Each conversation with its last message and how many messages
+///   from the other members came after the read position of the user
+/// ```json
+/// [
+///     {
+///         "conversation": {
+///             "id": <conversation_id>,
+///             "member_one": <user_id>,
+///             "member_two": <user_id>,
+///             "board_id": <board_id>,
+///             "name": <group_name>
+///         },
+///         "last_message": {
+///             "id": <message_id>,
+///             "sender_id": <user_id>,
+///             "conversation_id": <conversation_id>,
+///             "content": <content>,
+///             "file_id": <file_id>,
+///             "deleted": false,
+///             "created_at": <send_time>,
+///             "updated_at": <edit_time>
+///         },
+///         "unread": <unread_count>,
+///         "last_read_message_id": <message_id>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/conversations")]
+pub async fn get_conversations(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Vec<ConversationSummary>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| ChatQueries::conversations(conn, token))
+        .await
+        .map(ApiResponse::new)
+        .map_err(|e| ApiResponse::from_error(e.into()))
+}
+
+/// # GET /chat_source/conversations/<conversation_id>/reads
+/// Returns how far each member has read the conversation
+/// # Arguments
+/// * `conversation_id` - The id of the conversation
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `receipts` - The read position of every member who has read something
+/// ```json
+/// [
+///     {
+///         "conversation_id": <conversation_id>,
+///         "user_id": <user_id>,
+///         "message_id": <message_id>,
+///         "message_created_at": <send_time>,
+///         "read_at": <read_time>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/conversations/<conversation_id>/reads")]
+pub async fn get_conversation_reads(
+    db: Db,
+    auth: AuthResult,
+    conversation_id: String,
+) -> Result<ApiResponse<Vec<ReadReceipt>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        if !ChatQueries::is_member(conn, conversation_id, token)? {
+            return Err(ApiError::from_type(ApiErrorType::NotFound));
+        }
+        Ok(ChatQueries::reads(conn, conversation_id)?)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use diesel::{
+    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use rocket::{serde::json::Json, State};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    database::{chat_queries::ChatQueries, notification_queries::NotificationQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        messages::{
+            Conversation, ConversationMember, ConversationRole, GroupChat, GroupUpdate, NewGroup,
+            NewGroupMember, PubConversationMember,
+        },
+        notification::{Notification, NotificationKind},
+        ws_state::WsState,
+    },
+    schema::{conversation_members, conversations, users},
+};
+
+/// Checks the name of a group and returns it trimmed
+fn group_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 255 {
+        return Err(ApiError::from_type(ApiErrorType::InvalidGroup));
+    }
+    Ok(name.to_string())
+}
+
+/// Returns the group along with the membership of the user
+///
+/// Fails with `NotFound` when the conversation is not a group or the user is not in it
+fn group_membership(
+    conn: &mut PgConnection,
+    conversation_id: Uuid,
+    user_id: Uuid,
+) -> Result<(Conversation, ConversationMember), ApiError> {
+    let conversation = conversations::table
+        .find(conversation_id)
+        .select(Conversation::as_select())
+        .first::<Conversation>(conn)?;
+    if !conversation.is_group() {
+        return Err(ApiError::from_type(ApiErrorType::NotFound));
+    }
+    let member = ChatQueries::membership(conn, conversation_id, user_id)?;
+    Ok((conversation, member))
+}
+
+/// Fails with `InsufficientGroupRole` unless the member is an admin of the group
+fn require_admin(member: &ConversationMember) -> Result<(), ApiError> {
+    if member.role() < ConversationRole::Admin {
+        return Err(ApiError::from_type(ApiErrorType::InsufficientGroupRole));
+    }
+    Ok(())
+}
+
+fn notify_added(
+    conn: &mut PgConnection,
+    conversation: &Conversation,
+    user_id: Uuid,
+    added_by: Uuid,
+) -> Result<Notification, ApiError> {
+    Ok(NotificationQueries::create(
+        conn,
+        user_id,
+        Some(added_by),
+        NotificationKind::AddedToGroup,
+        json!({ "conversation_id": conversation.id, "name": conversation.name }),
+    )?)
+}
+
+/// # POST /chat_source/groups
+/// Creates a group, the creator joins it as `admin` and the other members are notified
+///
+/// Messages are exchanged through `/chat_source/events` like in any other conversation
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `group` - The name of the group and the users to add
+/// ```json
+/// {
+///     "name": <group_name>,
+///     "member_ids": [<user_id>, ...]
+/// }
+/// ```
+/// # Returns
+/// * `group` - The group and its members
+/// ```json
+/// {
+///     "conversation": {
+///         "id": <conversation_id>,
+///         "member_one": null,
+///         "member_two": null,
+///         "board_id": null,
+///         "name": <group_name>
+///     },
+///     "members": [
+///         {
+///             "user_id": <user_id>,
+///             "username": <username>,
+///             "role": "admin" | "member",
+///             "joined_at": <join_time>
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+#[post("/groups", data = "<group>")]
+pub async fn create_group(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    group: Json<NewGroup>,
+) -> Result<ApiResponse<GroupChat>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let name = group_name(&group.name)?;
+    let mut member_ids = group.member_ids.clone();
+    member_ids.sort();
+    member_ids.dedup();
+    member_ids.retain(|user_id| *user_id != token);
+
+    let (group, notifications) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let existing = users::table
+                    .filter(users::id.eq_any(&member_ids))
+                    .count()
+                    .get_result::<i64>(conn)?;
+                if existing != member_ids.len() as i64 {
+                    return Err(ApiError::from_type(ApiErrorType::UserNotFound));
+                }
+
+                let conversation = ChatQueries::create_group(conn, &name, token)?;
+                let mut notifications = Vec::new();
+                for user_id in member_ids {
+                    ChatQueries::add_member(
+                        conn,
+                        conversation.id,
+                        user_id,
+                        ConversationRole::Member,
+                    )?;
+                    notifications.push(notify_added(conn, &conversation, user_id, token)?);
+                }
+                let members = ChatQueries::member_list(conn, conversation.id)?;
+
+                Ok((
+                    GroupChat {
+                        conversation,
+                        members,
+                    },
+                    notifications,
+                ))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(notifications).await;
+    Ok(ApiResponse::new(group))
+}
+
+/// # GET /chat_source/groups
+/// Returns the groups of the user
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `groups` - A list of conversations, sorted by name
+#[get("/groups")]
+pub async fn get_groups(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Vec<Conversation>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        let groups = conversations::table
+            .inner_join(conversation_members::table)
+            .filter(conversation_members::user_id.eq(token))
+            .filter(conversations::name.is_not_null())
+            .order(conversations::name.asc())
+            .select(Conversation::as_select())
+            .load::<Conversation>(conn)?;
+
+        Ok::<Vec<Conversation>, ApiError>(groups)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /chat_source/groups/<conversation_id>
+/// Returns the group and its members, only to its members
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `group` - The group and its members, see `POST /chat_source/groups`
+#[get("/groups/<conversation_id>")]
+pub async fn get_group(
+    db: Db,
+    auth: AuthResult,
+    conversation_id: String,
+) -> Result<ApiResponse<GroupChat>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let (conversation, _) = group_membership(conn, conversation_id, token)?;
+        let members = ChatQueries::member_list(conn, conversation_id)?;
+
+        Ok::<GroupChat, ApiError>(GroupChat {
+            conversation,
+            members,
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /chat_source/groups/<conversation_id>
+/// Renames the group, requires the `admin` role
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `auth` - Takes the token of the user
+/// * `group` - The new name
+/// ```json
+/// {
+///     "name": <group_name>
+/// }
+/// ```
+/// # Returns
+/// * `conversation` - The renamed group
+#[put("/groups/<conversation_id>", data = "<group>")]
+pub async fn rename_group(
+    db: Db,
+    auth: AuthResult,
+    conversation_id: String,
+    group: Json<GroupUpdate>,
+) -> Result<ApiResponse<Conversation>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let name = group_name(&group.name)?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let (_, member) = group_membership(conn, conversation_id, token)?;
+            require_admin(&member)?;
+
+            let conversation = diesel::update(conversations::table.find(conversation_id))
+                .set(conversations::name.eq(name))
+                .returning(Conversation::as_returning())
+                .get_result::<Conversation>(conn)?;
+            Ok(conversation)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # POST /chat_source/groups/<conversation_id>/members
+/// Adds a member to the group and notifies them, requires the `admin` role
+///
+/// The new member can read the whole history of the group
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `auth` - Takes the token of the user
+/// * `member` - The user to add and their role
+/// ```json
+/// {
+///     "user_id": <user_id>,
+///     "role": "admin" | "member"
+/// }
+/// ```
+/// # Returns
+/// * `member` - The member
+/// ```json
+/// {
+///     "user_id": <user_id>,
+///     "username": <username>,
+///     "role": "member",
+///     "joined_at": <join_time>
+/// }
+/// ```
+#[post("/groups/<conversation_id>/members", data = "<member>")]
+pub async fn add_group_member(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    conversation_id: String,
+    member: Json<NewGroupMember>,
+) -> Result<ApiResponse<PubConversationMember>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let role = member.role.unwrap_or(ConversationRole::Member);
+
+    let (member, notification) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let (conversation, current) = group_membership(conn, conversation_id, token)?;
+                require_admin(&current)?;
+
+                let username = users::table
+                    .find(member.user_id)
+                    .select(users::username)
+                    .first::<String>(conn)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::UserNotFound))?;
+                if ChatQueries::is_member(conn, conversation_id, member.user_id)? {
+                    return Err(ApiError::from_type(ApiErrorType::InvalidGroup));
+                }
+
+                let member = ChatQueries::add_member(conn, conversation_id, member.user_id, role)?;
+                let notification = notify_added(conn, &conversation, member.user_id, token)?;
+
+                Ok((
+                    PubConversationMember {
+                        user_id: member.user_id,
+                        username,
+                        role,
+                        joined_at: member.joined_at,
+                    },
+                    notification,
+                ))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(vec![notification]).await;
+    Ok(ApiResponse::new(member))
+}
+
+/// # PUT /chat_source/groups/<conversation_id>/members/<user_id>
+/// Changes the role of a member, requires the `admin` role
+///
+/// A group always keeps at least one admin
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `user_id` - The id of the member
+/// * `auth` - Takes the token of the user
+/// * `role` - The new role, `"admin"` or `"member"`
+/// # Returns
+/// * `role` - The new role of the member
+#[put("/groups/<conversation_id>/members/<user_id>", data = "<role>")]
+pub async fn update_group_member(
+    db: Db,
+    auth: AuthResult,
+    conversation_id: String,
+    user_id: String,
+    role: Json<ConversationRole>,
+) -> Result<ApiResponse<ConversationRole>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let user_id = Uuid::try_parse(&user_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let role = role.into_inner();
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let (_, current) = group_membership(conn, conversation_id, token)?;
+            require_admin(&current)?;
+            let _ = ChatQueries::membership(conn, conversation_id, user_id)?;
+
+            diesel::update(conversation_members::table.find((conversation_id, user_id)))
+                .set(conversation_members::role.eq(role.as_str()))
+                .execute(conn)?;
+            let admins = conversation_members::table
+                .filter(conversation_members::conversation_id.eq(conversation_id))
+                .filter(conversation_members::role.eq(ConversationRole::Admin.as_str()))
+                .count()
+                .get_result::<i64>(conn)?;
+            if admins == 0 {
+                return Err(ApiError::from_type(ApiErrorType::InvalidGroup));
+            }
+
+            Ok(role)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /chat_source/groups/<conversation_id>/members/<user_id>
+/// Removes a member from the group, requires the `admin` role unless members remove themselves
+///
+/// The removed member stops receiving the messages, typing indicators and presence
+/// of the group right away
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `user_id` - The id of the member
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `user_id` - The id of the removed member
+#[delete("/groups/<conversation_id>/members/<user_id>")]
+pub async fn remove_group_member(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    conversation_id: String,
+    user_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let user_id = Uuid::try_parse(&user_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let (_, current) = group_membership(conn, conversation_id, token)?;
+            if user_id != token {
+                require_admin(&current)?;
+            }
+            let _ = ChatQueries::membership(conn, conversation_id, user_id)?;
+
+            ChatQueries::leave_group(conn, conversation_id, user_id)?;
+            Ok(user_id)
+        })
+    })
+    .await
+    .map_err(ApiResponse::from_error)?;
+
+    ws_state
+        .unsubscribe_member(&user_id, &conversation_id)
+        .await;
+    Ok(ApiResponse::new(user_id))
+}
+
+/// # POST /chat_source/groups/<conversation_id>/leave
+/// Leaves the group
+///
+/// The longest standing member becomes admin when the last admin leaves,
+/// the group and its messages are deleted when the last member leaves
+/// # Arguments
+/// * `conversation_id` - The id of the group
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `conversation_id` - The id of the group
+#[post("/groups/<conversation_id>/leave")]
+pub async fn leave_group(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    conversation_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let conversation_id = Uuid::try_parse(&conversation_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let _ = group_membership(conn, conversation_id, token)?;
+            ChatQueries::leave_group(conn, conversation_id, token)?;
+            Ok(conversation_id)
+        })
+    })
+    .await
+    .map_err(ApiResponse::from_error)?;
+
+    ws_state.unsubscribe_member(&token, &conversation_id).await;
+    Ok(ApiResponse::new(conversation_id))
+}
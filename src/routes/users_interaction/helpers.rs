@@ -1,33 +1,21 @@
-use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl,
+};
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::database::Db;
+use crate::database::{
+    chat_queries::ChatQueries, mention_queries::MentionQueries,
+    notification_queries::NotificationQueries, Db,
+};
 use crate::errors::ApiError;
 use crate::models::{
-    api_response::ApiResponse,
-    messages::{ChatMessageDTO, Conversation},
+    messages::{ChatMessageDTO, ClientAction, Conversation},
+    notification::NotificationKind,
     user::{PubUser, User},
+    ws_state::{WsMessage, WsState},
 };
-use crate::schema::{chat_messages, conversations, users};
-
-pub async fn get_last_messages(
-    db: Db,
-    conversation_id: Uuid,
-) -> Result<ApiResponse<Vec<ChatMessageDTO>>, ApiResponse<ApiError>> {
-    db.run(move |conn| {
-        conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            let messages = chat_messages::table
-                .filter(chat_messages::conversation_id.eq(conversation_id))
-                .order_by(chat_messages::created_at.desc())
-                .limit(100)
-                .load::<ChatMessageDTO>(conn)?;
-            Ok(messages)
-        })
-    })
-    .await
-    .map_err(|e| ApiResponse::from_error(e.into()))
-    .map(|messages| ApiResponse::new(messages))
-}
+use crate::schema::{conversations, users};
 
 pub async fn get_conversation_with_members(
     db: &Db,
@@ -62,3 +50,127 @@ pub async fn get_conversation_with_members(
     .await
     .map_err(|e| (e.into()))
 }
+
+/// Applies an edit, a deletion or a read acknowledgement sent over the chat socket
+/// and broadcasts it
+///
+/// Follows the same rules as the REST endpoints, actions that are not allowed are ignored
+pub async fn apply_client_action(db: &Db, ws_state: &WsState, user_id: Uuid, action: ClientAction) {
+    let change = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| match action {
+                ClientAction::Edit {
+                    message_id,
+                    content,
+                } => {
+                    let (message, members, notifications) =
+                        ChatQueries::edit_message(conn, message_id, user_id, &content)?;
+                    Ok((
+                        message.conversation_id,
+                        WsMessage::MessageEdited(message),
+                        members,
+                        notifications,
+                    ))
+                }
+                ClientAction::Delete { message_id } => {
+                    let (message, members) =
+                        ChatQueries::delete_message(conn, message_id, user_id)?;
+                    Ok((
+                        message.conversation_id,
+                        WsMessage::MessageDeleted(message),
+                        members,
+                        Vec::new(),
+                    ))
+                }
+                ClientAction::Read { message_id } => {
+                    let (receipt, members, advanced) =
+                        ChatQueries::mark_read(conn, message_id, user_id)?;
+                    let others = match advanced {
+                        true => members.into_iter().filter(|id| *id != user_id).collect(),
+                        false => Vec::new(),
+                    };
+                    Ok((
+                        receipt.conversation_id,
+                        WsMessage::ReadReceipt(receipt),
+                        others,
+                        Vec::new(),
+                    ))
+                }
+            })
+        })
+        .await;
+    let Ok((conv_id, event, members, notifications)) = change else {
+        return;
+    };
+
+    let _ = ws_state.send_to_members(&conv_id, &members, event).await;
+    ws_state.notify(notifications).await;
+}
+
+/// Stores the mentions of the message and notifies the members of the conversation
+///
+/// Mentioned members always get a mention notification, the others only get
+/// a chat notification when they are not following the conversation over a socket
+pub async fn notify_absent_members(db: &Db, ws_state: &WsState, message: &ChatMessageDTO) {
+    let conv_id = message.conversation_id;
+    let members = db.run(move |conn| ChatQueries::members(conn, conv_id)).await;
+    let Ok(members) = members else {
+        return;
+    };
+
+    let mentioned_message = message.clone();
+    let mentioned_members = members.clone();
+    let mentions = db
+        .run(move |conn| {
+            MentionQueries::store_message_mentions(conn, &mentioned_message, &mentioned_members)
+        })
+        .await;
+    let mentions = match mentions {
+        Ok(mentions) => mentions,
+        Err(e) => {
+            eprintln!("Failed to store chat mentions: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut absent = Vec::new();
+    for member_id in members {
+        if member_id != message.sender_id
+            && !mentions.iter().any(|mention| mention.user_id == member_id)
+            && !ws_state.user_in_conversation(&conv_id, &member_id).await
+        {
+            absent.push(member_id);
+        }
+    }
+    ws_state.notify(mentions).await;
+    if absent.is_empty() {
+        return;
+    }
+
+    let sender_id = message.sender_id;
+    let payload = json!({
+        "conversation_id": conv_id,
+        "message_id": message.id,
+        "preview": message.content.chars().take(100).collect::<String>(),
+    });
+    let notifications = db
+        .run(move |conn| {
+            absent
+                .into_iter()
+                .map(|member_id| {
+                    NotificationQueries::create(
+                        conn,
+                        member_id,
+                        Some(sender_id),
+                        NotificationKind::ChatMessage,
+                        payload.clone(),
+                    )
+                })
+                .collect::<QueryResult<Vec<_>>>()
+        })
+        .await;
+    match notifications {
+        Ok(notifications) => ws_state.notify(notifications).await,
+        Err(e) => eprintln!("Failed to store chat notifications: {}", e),
+    }
+}
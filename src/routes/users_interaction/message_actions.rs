@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use diesel::Connection;
+use rocket::{serde::json::Json, State};
+use uuid::Uuid;
+
+use crate::{
+    database::{chat_queries::ChatQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        messages::{ChatMessageDTO, ChatMessageEdit, MessageUpdate, ReadReceipt},
+        ws_state::{WsMessage, WsState},
+    },
+};
+
+/// # PUT /chat_source/messages/<message_id>
+/// Edits a message, only its sender can
+///
+/// Members following the conversation get a `MessageEdited` event, users the new content
+/// mentions for the first time are notified
+/// # Arguments
+/// * `message_id` - The id of the message
+/// * `auth` - Takes the token of the user
+/// * `message` - The new content
+/// ```json
+/// {
+///     "content": <content>
+/// }
+/// ```
+/// # Returns
+/// * `message` - The edited message, its `updated_at` tells when
+#[put("/messages/<message_id>", data = "<message>")]
+pub async fn edit_message(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    message_id: String,
+    message: Json<MessageUpdate>,
+) -> Result<ApiResponse<ChatMessageDTO>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let message_id = Uuid::try_parse(&message_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let (message, members, notifications) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                ChatQueries::edit_message(conn, message_id, token, &message.content)
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    let _ = ws_state
+        .send_to_members(
+            &message.conversation_id,
+            &members,
+            WsMessage::MessageEdited(message.clone()),
+        )
+        .await;
+    ws_state.notify(notifications).await;
+    Ok(ApiResponse::new(message))
+}
+
+/// # DELETE /chat_source/messages/<message_id>
+/// Deletes a message, only its sender can
+///
+/// The message stays in the conversation as a tombstone, with an empty content and
+/// `deleted` set. Members following the conversation get a `MessageDeleted` event
+/// # Arguments
+/// * `message_id` - The id of the message
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `message` - The tombstone of the message
+#[delete("/messages/<message_id>")]
+pub async fn delete_message(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    message_id: String,
+) -> Result<ApiResponse<ChatMessageDTO>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let message_id = Uuid::try_parse(&message_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let (message, members) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                ChatQueries::delete_message(conn, message_id, token)
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    let _ = ws_state
+        .send_to_members(
+            &message.conversation_id,
+            &members,
+            WsMessage::MessageDeleted(message.clone()),
+        )
+        .await;
+    Ok(ApiResponse::new(message))
+}
+
+/// # GET /chat_source/messages/<message_id>/edits
+/// Returns the previous versions of a message to the members of its conversation
+/// # Arguments
+/// * `message_id` - The id of the message
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `edits` - The previous contents, newest first. Empty once the message is deleted
+/// ```json
+/// [
+///     {
+///         "id": <edit_id>,
+///         "message_id": <message_id>,
+///         "content": <previous_content>,
+///         "edited_at": <replacement_time>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/messages/<message_id>/edits")]
+pub async fn get_message_edits(
+    db: Db,
+    auth: AuthResult,
+    message_id: String,
+) -> Result<ApiResponse<Vec<ChatMessageEdit>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let message_id = Uuid::try_parse(&message_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| ChatQueries::message_edits(conn, message_id, token))
+        .await
+        .map(ApiResponse::new)
+        .map_err(ApiResponse::from_error)
+}
+
+/// # POST /chat_source/messages/<message_id>/read
+/// Marks the conversation of the message as read up to it
+///
+/// The read position only moves forward, acknowledging an older message keeps it.
+/// When it moves, the other members following the conversation get a `ReadReceipt` event
+/// # Arguments
+/// * `message_id` - The id of the last message the user has read
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `receipt` - The current read position of the user
+/// ```json
+/// {
+///     "conversation_id": <conversation_id>,
+///     "user_id": <user_id>,
+///     "message_id": <message_id>,
+///     "message_created_at": <send_time>,
+///     "read_at": <read_time>
+/// }
+/// ```
+#[post("/messages/<message_id>/read")]
+pub async fn read_message(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    message_id: String,
+) -> Result<ApiResponse<ReadReceipt>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let message_id = Uuid::try_parse(&message_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let (receipt, members, advanced) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                ChatQueries::mark_read(conn, message_id, token)
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    if advanced {
+        let others = members
+            .into_iter()
+            .filter(|id| *id != token)
+            .collect::<Vec<_>>();
+        let _ = ws_state
+            .send_to_members(
+                &receipt.conversation_id,
+                &others,
+                WsMessage::ReadReceipt(receipt.clone()),
+            )
+            .await;
+    }
+    Ok(ApiResponse::new(receipt))
+}
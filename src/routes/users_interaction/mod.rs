@@ -1,19 +1,26 @@
+use std::sync::Arc;
+
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::State;
 use uuid::Uuid;
 
 use crate::{
     database::Db,
-    errors::ApiError,
+    errors::{ApiError, ApiErrorType},
     models::{
         api_response::ApiResponse,
         auth::AuthResult,
+        presence::Presence,
         user::{PubUser, User},
+        ws_state::WsState,
     },
     schema::{friends, users},
 };
 
 pub mod chat;
 pub mod conversations;
+pub mod groups;
+pub mod message_actions;
 mod helpers;
 
 /// # GET /friends/list
@@ -49,3 +56,55 @@ pub async fn get_friends(
         Err(e) => Err(ApiResponse::from_error(e)),
     }
 }
+
+/// # GET /friends/presence?<ids>
+/// Returns whether friends of the user are online, away or offline
+/// # Arguments
+/// * ids - Comma separated ids of friends, all the friends when left out.
+///   Ids of users who are not friends are ignored
+/// * auth - The token of the user
+/// # Returns
+/// * presence - The presence of each friend, `last_seen` is their last chat activity
+/// ```json
+/// [
+///     {
+///         "user_id": <user_id>,
+///         "status": "online" | "away" | "offline",
+///         "last_seen": <last_activity>
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/presence?<ids>")]
+pub async fn get_friends_presence(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    ids: Option<String>,
+) -> Result<ApiResponse<Vec<Presence>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let requested = ids
+        .map(|ids| {
+            ids.split(',')
+                .map(|id| Uuid::try_parse(id.trim()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    let friend_ids = db
+        .run(move |conn| {
+            let mut query = friends::table
+                .filter(friends::user_id.eq(token))
+                .select(friends::friend_id)
+                .into_boxed();
+            if let Some(requested) = requested {
+                query = query.filter(friends::friend_id.eq_any(requested));
+            }
+            query.load::<Uuid>(conn)
+        })
+        .await
+        .map_err(|e| ApiResponse::from_error(e.into()))?;
+
+    Ok(ApiResponse::new(ws_state.presence(&friend_ids).await))
+}
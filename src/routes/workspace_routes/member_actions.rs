@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::{serde::json::Json, State};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        notification_queries::NotificationQueries, workspace_queries::WorkspaceQueries, Db,
+    },
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        notification::NotificationKind,
+        workspace::{NewWorkspaceMember, PubWorkspaceMember, WorkspaceMember, WorkspaceRole},
+        ws_state::WsState,
+    },
+    schema::{users, workspace_members, workspaces},
+};
+
+use super::require_role;
+
+/// # POST /workspaces/<workspace_id>/members
+/// Adds a member to the workspace and notifies them, requires the `admin` role
+///
+/// The new member gets access to every board of the workspace
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `auth` - Takes the token of the user
+/// * `member` - The user to add and their role
+/// ```json
+/// {
+///     "user_id": <user_id>,
+///     "role": "admin" | "member"
+/// }
+/// ```
+/// # Returns
+/// * `member` - The member
+/// ```json
+/// {
+///     "user_id": <user_id>,
+///     "username": <username>,
+///     "role": "member",
+///     "joined_at": <join_time>
+/// }
+/// ```
+#[post("/<workspace_id>/members", data = "<member>")]
+pub async fn add_workspace_member(
+    db: Db,
+    auth: AuthResult,
+    ws_state: &State<Arc<WsState>>,
+    workspace_id: String,
+    member: Json<NewWorkspaceMember>,
+) -> Result<ApiResponse<PubWorkspaceMember>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let role = member.role.unwrap_or(WorkspaceRole::Member);
+    if role == WorkspaceRole::Owner {
+        return Err(ApiResponse::from_error_type(
+            ApiErrorType::InvalidWorkspaceRole,
+        ));
+    }
+
+    let (member, notification) = db
+        .run(move |conn| {
+            conn.transaction::<_, ApiError, _>(|conn| {
+                let current = WorkspaceQueries::membership(conn, workspace_id, token)?;
+                require_role(&current, WorkspaceRole::Admin)?;
+
+                let (workspace_name, personal) = workspaces::table
+                    .find(workspace_id)
+                    .select((workspaces::name, workspaces::personal))
+                    .first::<(String, bool)>(conn)?;
+                if personal {
+                    return Err(ApiError::from_type(ApiErrorType::PersonalWorkspace));
+                }
+                let username = users::table
+                    .find(member.user_id)
+                    .select(users::username)
+                    .first::<String>(conn)
+                    .map_err(|_| ApiError::from_type(ApiErrorType::UserNotFound))?;
+
+                let member = diesel::insert_into(workspace_members::table)
+                    .values(WorkspaceMember {
+                        workspace_id,
+                        user_id: member.user_id,
+                        role: role.as_str().to_string(),
+                        joined_at: Utc::now().naive_utc(),
+                    })
+                    .get_result::<WorkspaceMember>(conn)?;
+                WorkspaceQueries::grant_boards(conn, workspace_id, &[member.user_id])?;
+
+                let notification = NotificationQueries::create(
+                    conn,
+                    member.user_id,
+                    Some(token),
+                    NotificationKind::AddedToWorkspace,
+                    json!({ "workspace_id": workspace_id, "workspace_name": workspace_name }),
+                )?;
+
+                Ok((
+                    PubWorkspaceMember {
+                        user_id: member.user_id,
+                        username,
+                        role,
+                        joined_at: member.joined_at,
+                    },
+                    notification,
+                ))
+            })
+        })
+        .await
+        .map_err(ApiResponse::from_error)?;
+
+    ws_state.notify(vec![notification]).await;
+    Ok(ApiResponse::new(member))
+}
+
+/// # PUT /workspaces/<workspace_id>/members/<user_id>
+/// Changes the role of a member, requires the `admin` role. The owner keeps their role
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `user_id` - The id of the member
+/// * `auth` - Takes the token of the user
+/// * `role` - The new role, `"admin"` or `"member"`
+/// # Returns
+/// * `role` - The new role of the member
+#[put("/<workspace_id>/members/<user_id>", data = "<role>")]
+pub async fn update_workspace_member(
+    db: Db,
+    auth: AuthResult,
+    workspace_id: String,
+    user_id: String,
+    role: Json<WorkspaceRole>,
+) -> Result<ApiResponse<WorkspaceRole>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let user_id = Uuid::try_parse(&user_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let role = role.into_inner();
+    if role == WorkspaceRole::Owner {
+        return Err(ApiResponse::from_error_type(
+            ApiErrorType::InvalidWorkspaceRole,
+        ));
+    }
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let current = WorkspaceQueries::membership(conn, workspace_id, token)?;
+            require_role(&current, WorkspaceRole::Admin)?;
+            let member = WorkspaceQueries::membership(conn, workspace_id, user_id)?;
+            if member.role() == WorkspaceRole::Owner {
+                return Err(ApiError::from_type(ApiErrorType::InvalidWorkspaceRole));
+            }
+
+            diesel::update(workspace_members::table)
+                .filter(
+                    workspace_members::workspace_id
+                        .eq(workspace_id)
+                        .and(workspace_members::user_id.eq(user_id)),
+                )
+                .set(workspace_members::role.eq(role.as_str()))
+                .execute(conn)?;
+
+            Ok(role)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /workspaces/<workspace_id>/members/<user_id>
+/// Removes a member from the workspace, requires the `admin` role unless members
+/// remove themselves. The owner can't leave their workspace
+///
+/// The member loses access to the boards of the workspace they didn't create
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `user_id` - The id of the member
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `user_id` - The id of the removed member
+#[delete("/<workspace_id>/members/<user_id>")]
+pub async fn remove_workspace_member(
+    db: Db,
+    auth: AuthResult,
+    workspace_id: String,
+    user_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let user_id = Uuid::try_parse(&user_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let current = WorkspaceQueries::membership(conn, workspace_id, token)?;
+            if user_id != token {
+                require_role(&current, WorkspaceRole::Admin)?;
+            }
+            let member = WorkspaceQueries::membership(conn, workspace_id, user_id)?;
+            if member.role() == WorkspaceRole::Owner {
+                return Err(ApiError::from_type(ApiErrorType::InvalidWorkspaceRole));
+            }
+
+            diesel::delete(workspace_members::table)
+                .filter(
+                    workspace_members::workspace_id
+                        .eq(workspace_id)
+                        .and(workspace_members::user_id.eq(user_id)),
+                )
+                .execute(conn)?;
+            WorkspaceQueries::revoke_boards(conn, workspace_id, user_id)?;
+
+            Ok(user_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
@@ -0,0 +1,13 @@
+use crate::errors::{ApiError, ApiErrorType};
+use crate::models::workspace::{WorkspaceMember, WorkspaceRole};
+
+pub mod member_actions;
+pub mod workspace_actions;
+
+/// Fails with `InsufficientWorkspaceRole` unless the member has at least `role`
+fn require_role(member: &WorkspaceMember, role: WorkspaceRole) -> Result<(), ApiError> {
+    if member.role() < role {
+        return Err(ApiError::from_type(ApiErrorType::InsufficientWorkspaceRole));
+    }
+    Ok(())
+}
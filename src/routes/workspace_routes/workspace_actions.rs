@@ -0,0 +1,290 @@
+use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use rocket::serde::json::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{workspace_queries::WorkspaceQueries, Db},
+    errors::{ApiError, ApiErrorType},
+    models::{
+        api_response::ApiResponse,
+        auth::AuthResult,
+        workspace::{
+            NewWorkspace, PubWorkspace, PubWorkspaceMember, Workspace, WorkspaceInfo,
+            WorkspaceMember, WorkspaceRole,
+        },
+        PubBoard,
+    },
+    schema::{boards, users, workspace_members, workspaces},
+};
+
+use super::require_role;
+
+/// # POST /workspaces
+/// Creates a new workspace owned by the user
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// * `workspace` - The workspace
+/// ```json
+/// {
+///     "name": <workspace_name>
+/// }
+/// ```
+/// # Returns
+/// * `workspace` - The workspace
+/// ```json
+/// {
+///     "id": <workspace_id>,
+///     "name": <workspace_name>,
+///     "owner_id": <user_id>,
+///     "personal": false,
+///     "created_at": <creation_time>
+/// }
+/// ```
+#[post("/", data = "<workspace>")]
+pub async fn create_workspace(
+    db: Db,
+    auth: AuthResult,
+    workspace: Json<NewWorkspace>,
+) -> Result<ApiResponse<Workspace>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let name = workspace.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiResponse::from_error_type(ApiErrorType::EmptyFields));
+    }
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            Ok(WorkspaceQueries::create(conn, token, &name, false)?)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /workspaces
+/// Returns the workspaces the user is a member of, starting with their personal one
+/// # Arguments
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `workspaces` - A list of workspaces
+/// ```json
+/// [
+///     {
+///         "id": <workspace_id>,
+///         "name": <workspace_name>,
+///         "personal": true,
+///         "role": "owner" | "admin" | "member"
+///     },
+///     ...
+/// ]
+/// ```
+#[get("/")]
+pub async fn get_workspaces(
+    db: Db,
+    auth: AuthResult,
+) -> Result<ApiResponse<Vec<PubWorkspace>>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+
+    db.run(move |conn| {
+        WorkspaceQueries::personal_workspace(conn, token)?;
+
+        let workspaces = workspace_members::table
+            .inner_join(workspaces::table)
+            .filter(workspace_members::user_id.eq(token))
+            .order((workspaces::personal.desc(), workspaces::name.asc()))
+            .select((Workspace::as_select(), WorkspaceMember::as_select()))
+            .load::<(Workspace, WorkspaceMember)>(conn)?
+            .into_iter()
+            .map(|(workspace, member)| PubWorkspace {
+                id: workspace.id,
+                name: workspace.name,
+                personal: workspace.personal,
+                role: member.role(),
+            })
+            .collect::<Vec<PubWorkspace>>();
+
+        Ok::<Vec<PubWorkspace>, ApiError>(workspaces)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # GET /workspaces/<workspace_id>
+/// Returns the workspace with its members and boards
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `workspace` - The workspace
+/// ```json
+/// {
+///     "id": <workspace_id>,
+///     "name": <workspace_name>,
+///     "owner_id": <user_id>,
+///     "personal": false,
+///     "members": [
+///         {
+///             "user_id": <user_id>,
+///             "username": <username>,
+///             "role": "owner" | "admin" | "member",
+///             "joined_at": <join_time>
+///         },
+///         ...
+///     ],
+///     "boards": [
+///         {
+///             "id": <board_id>,
+///             "name": <board_name>,
+///             "workspace_id": <workspace_id>
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+#[get("/<workspace_id>")]
+pub async fn get_workspace(
+    db: Db,
+    auth: AuthResult,
+    workspace_id: String,
+) -> Result<ApiResponse<WorkspaceInfo>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        let _ = WorkspaceQueries::membership(conn, workspace_id, token)?;
+
+        let workspace = workspaces::table
+            .find(workspace_id)
+            .select(Workspace::as_select())
+            .first::<Workspace>(conn)?;
+        let members = workspace_members::table
+            .inner_join(users::table)
+            .filter(workspace_members::workspace_id.eq(workspace_id))
+            .order(workspace_members::joined_at.asc())
+            .select((WorkspaceMember::as_select(), users::username))
+            .load::<(WorkspaceMember, String)>(conn)?
+            .into_iter()
+            .map(|(member, username)| PubWorkspaceMember {
+                user_id: member.user_id,
+                username,
+                role: member.role(),
+                joined_at: member.joined_at,
+            })
+            .collect::<Vec<PubWorkspaceMember>>();
+        let boards = boards::table
+            .filter(boards::workspace_id.eq(workspace_id))
+            .order(boards::name.asc())
+            .select((boards::id, boards::name))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .map(|(id, name)| PubBoard {
+                id,
+                name,
+                workspace_id,
+            })
+            .collect::<Vec<PubBoard>>();
+
+        Ok::<WorkspaceInfo, ApiError>(WorkspaceInfo {
+            id: workspace.id,
+            name: workspace.name,
+            owner_id: workspace.owner_id,
+            personal: workspace.personal,
+            members,
+            boards,
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # PUT /workspaces/<workspace_id>
+/// Renames the workspace, requires the `admin` role
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `auth` - Takes the token of the user
+/// * `workspace` - The new name, same as when creating the workspace
+/// # Returns
+/// * `workspace` - The workspace
+#[put("/<workspace_id>", data = "<workspace>")]
+pub async fn update_workspace(
+    db: Db,
+    auth: AuthResult,
+    workspace_id: String,
+    workspace: Json<NewWorkspace>,
+) -> Result<ApiResponse<Workspace>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+    let name = workspace.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiResponse::from_error_type(ApiErrorType::EmptyFields));
+    }
+
+    db.run(move |conn| {
+        let member = WorkspaceQueries::membership(conn, workspace_id, token)?;
+        require_role(&member, WorkspaceRole::Admin)?;
+
+        let workspace = diesel::update(workspaces::table.find(workspace_id))
+            .set(workspaces::name.eq(name))
+            .get_result::<Workspace>(conn)?;
+
+        Ok::<Workspace, ApiError>(workspace)
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
+
+/// # DELETE /workspaces/<workspace_id>
+/// Deletes the workspace, only its owner can do it and only once all of its boards
+/// were deleted or moved to another workspace
+/// # Arguments
+/// * `workspace_id` - The id of the workspace
+/// * `auth` - Takes the token of the user
+/// # Returns
+/// * `workspace_id` - The id of the deleted workspace
+#[delete("/<workspace_id>")]
+pub async fn delete_workspace(
+    db: Db,
+    auth: AuthResult,
+    workspace_id: String,
+) -> Result<ApiResponse<Uuid>, ApiResponse<ApiError>> {
+    let token = auth.unpack()?.id;
+    let workspace_id = Uuid::try_parse(&workspace_id)
+        .map_err(|_| ApiError::from_type(ApiErrorType::FailedToParseUUID))?;
+
+    db.run(move |conn| {
+        conn.transaction::<_, ApiError, _>(|conn| {
+            let member = WorkspaceQueries::membership(conn, workspace_id, token)?;
+            require_role(&member, WorkspaceRole::Owner)?;
+
+            let personal = workspaces::table
+                .find(workspace_id)
+                .select(workspaces::personal)
+                .first::<bool>(conn)?;
+            if personal {
+                return Err(ApiError::from_type(ApiErrorType::PersonalWorkspace));
+            }
+            let boards = boards::table
+                .filter(boards::workspace_id.eq(workspace_id))
+                .count()
+                .get_result::<i64>(conn)?;
+            if boards > 0 {
+                return Err(ApiError::from_type(ApiErrorType::WorkspaceNotEmpty));
+            }
+
+            let workspace_id = diesel::delete(workspaces::table.find(workspace_id))
+                .returning(workspaces::id)
+                .get_result::<Uuid>(conn)?;
+
+            Ok(workspace_id)
+        })
+    })
+    .await
+    .map(ApiResponse::new)
+    .map_err(ApiResponse::from_error)
+}
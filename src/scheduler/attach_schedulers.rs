@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use rocket::{fairing::AdHoc, tokio, Build, Rocket};
+
+use crate::{
+    database::Db,
+    models::{operation::UndoConfig, webhook::WebhookQueue, ws_state::WsState},
+};
+
+use super::{
+    chat_activity, expired_operations, passed_dates, recurring_cards, webhook_deliveries, Scheduler,
+};
+
+impl Scheduler for Rocket<Build> {
+    fn attach_schedulers(self) -> Self {
+        self.attach(AdHoc::on_liftoff("Recurring cards", |rocket| {
+            Box::pin(async move {
+                let pool = Db::pool(rocket).expect("database pool").clone();
+                tokio::spawn(recurring_cards::run(pool));
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Passed dates", |rocket| {
+            Box::pin(async move {
+                let pool = Db::pool(rocket).expect("database pool").clone();
+                tokio::spawn(passed_dates::run(pool));
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Expired operations", |rocket| {
+            Box::pin(async move {
+                let pool = Db::pool(rocket).expect("database pool").clone();
+                let window = rocket.state::<UndoConfig>().expect("undo config").window();
+                tokio::spawn(expired_operations::run(pool, window));
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Webhook deliveries", |rocket| {
+            Box::pin(async move {
+                let pool = Db::pool(rocket).expect("database pool").clone();
+                let queue = rocket
+                    .state::<Arc<WebhookQueue>>()
+                    .expect("webhook queue")
+                    .clone();
+                tokio::spawn(webhook_deliveries::run(pool, queue));
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Chat activity", |rocket| {
+            Box::pin(async move {
+                let ws_state = rocket.state::<Arc<WsState>>().expect("ws state").clone();
+                tokio::spawn(chat_activity::run(ws_state));
+            })
+        }))
+    }
+}
@@ -0,0 +1,16 @@
+use std::{sync::Arc, time::Duration};
+
+use rocket::tokio::time;
+
+use crate::models::ws_state::WsState;
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Periodically expires stale typing indicators and marks idle chat members away
+pub async fn run(ws_state: Arc<WsState>) {
+    let mut interval = time::interval(TICK);
+    loop {
+        interval.tick().await;
+        ws_state.expire().await;
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use diesel::Connection;
+use rocket::tokio::time;
+
+use crate::database::operation_queries::OperationQueries;
+
+use super::DbPool;
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Periodically drops the board operations that fell out of the undo window
+///
+/// This is also when the attachment files of deleted cards finally leave the disk
+pub async fn run(pool: DbPool, window: chrono::Duration) {
+    let mut interval = time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let Some(conn) = pool.get().await else {
+            continue;
+        };
+        let purged = conn
+            .run(move |conn| conn.transaction(|conn| OperationQueries::purge_expired(conn, window)))
+            .await;
+        match purged {
+            // Files only leave the disk once the purge is committed
+            Ok(stale_files) => {
+                for file in stale_files {
+                    let _ = std::fs::remove_file(file);
+                }
+            }
+            Err(e) => eprintln!("Failed to purge board operations: {}", e),
+        }
+    }
+}
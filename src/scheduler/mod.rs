@@ -0,0 +1,16 @@
+use rocket_sync_db_pools::ConnectionPool;
+
+use crate::database::Db;
+
+mod attach_schedulers;
+mod chat_activity;
+mod expired_operations;
+mod passed_dates;
+mod recurring_cards;
+mod webhook_deliveries;
+
+pub type DbPool = ConnectionPool<Db, diesel::PgConnection>;
+
+pub trait Scheduler {
+    fn attach_schedulers(self) -> Self;
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use diesel::Connection;
+use rocket::tokio::time;
+
+use crate::database::automation_queries::AutomationQueries;
+
+use super::DbPool;
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Periodically runs the automation rules triggered by a date custom field passing
+///
+/// Which card and date each rule already ran for is read from the execution log,
+/// so a restart neither skips nor repeats anything
+pub async fn run(pool: DbPool) {
+    let mut interval = time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let Some(conn) = pool.get().await else {
+            continue;
+        };
+        let fired = conn
+            .run(|conn| conn.transaction(AutomationQueries::fire_passed_dates))
+            .await;
+        if let Err(e) = fired {
+            eprintln!("Failed to run date rules: {}", e);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl, SelectableHelper,
+};
+use rocket::tokio::time;
+use uuid::Uuid;
+
+use crate::{
+    database::{analytics_queries::AnalyticsQueries, automation_queries::AutomationQueries},
+    models::{
+        automation::RuleEvent,
+        custom_field::CardFieldValue,
+        recurrence::{CardRecurrence, RecurrenceRule},
+        ColumnCard,
+    },
+    schema::{board_column, card_field_values, card_recurrences, column_card},
+};
+
+use super::DbPool;
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Periodically creates the next instance of every recurring card that is due
+///
+/// All the state lives in `card_recurrences`, so a restart simply picks up
+/// whatever became due while the server was down
+pub async fn run(pool: DbPool) {
+    let mut interval = time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let Some(conn) = pool.get().await else {
+            continue;
+        };
+        if let Err(e) = conn.run(spawn_due_instances).await {
+            eprintln!("Failed to create recurring cards: {}", e);
+        }
+    }
+}
+
+/// A recurrence is due when its current card is completed or its period has elapsed
+fn spawn_due_instances(conn: &mut PgConnection) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let now = Utc::now().naive_utc();
+        let completed_cards = column_card::table
+            .filter(column_card::completed_at.is_not_null())
+            .select(column_card::id);
+        let due = card_recurrences::table
+            .filter(
+                card_recurrences::next_run_at
+                    .le(now)
+                    .or(card_recurrences::card_id.eq_any(completed_cards)),
+            )
+            .for_update()
+            .skip_locked()
+            .load::<CardRecurrence>(conn)?;
+
+        let mut created = 0;
+        for recurrence in due {
+            let Ok(rule) = recurrence.rule.parse::<RecurrenceRule>() else {
+                eprintln!("Skipping invalid recurrence rule {}", recurrence.rule);
+                continue;
+            };
+            let Some(next_run_at) = rule.next_after_now(recurrence.next_run_at, now) else {
+                eprintln!("Skipping recurrence {} without a next run", recurrence.id);
+                continue;
+            };
+            let (name, description, parent_id, lane_id) = column_card::table
+                .filter(column_card::id.eq(recurrence.card_id))
+                .select((
+                    column_card::name,
+                    column_card::description,
+                    column_card::parent_id,
+                    column_card::lane_id,
+                ))
+                .first::<(String, Option<String>, Option<Uuid>, Option<Uuid>)>(conn)?;
+            let position = column_card::table
+                .filter(column_card::column_id.eq(recurrence.column_id))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            let card_id = diesel::insert_into(column_card::table)
+                .values(ColumnCard {
+                    id: None,
+                    name,
+                    column_id: recurrence.column_id,
+                    position: position as i32,
+                    description,
+                    parent_id,
+                    lane_id,
+                })
+                .returning(column_card::id)
+                .get_result::<Uuid>(conn)?;
+            let values = card_field_values::table
+                .filter(card_field_values::card_id.eq(recurrence.card_id))
+                .select(CardFieldValue::as_select())
+                .load::<CardFieldValue>(conn)?
+                .into_iter()
+                .map(|value| CardFieldValue { card_id, ..value })
+                .collect::<Vec<CardFieldValue>>();
+            diesel::insert_into(card_field_values::table)
+                .values(&values)
+                .execute(conn)?;
+            let board_id = board_column::table
+                .find(recurrence.column_id)
+                .select(board_column::board_id)
+                .first::<Uuid>(conn)?;
+            AnalyticsQueries::record_movement(
+                conn,
+                board_id,
+                card_id,
+                None,
+                Some(recurrence.column_id),
+                None,
+            )?;
+            AutomationQueries::fire(
+                conn,
+                board_id,
+                card_id,
+                RuleEvent::CardCreated {
+                    column_id: recurrence.column_id,
+                },
+            )?;
+
+            diesel::update(card_recurrences::table)
+                .filter(card_recurrences::id.eq(recurrence.id))
+                .set((
+                    card_recurrences::card_id.eq(card_id),
+                    card_recurrences::next_run_at.eq(next_run_at),
+                ))
+                .execute(conn)?;
+            created += 1;
+        }
+        Ok(created)
+    })
+}
@@ -0,0 +1,292 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use diesel::Connection;
+use rocket::tokio::time;
+
+use crate::{
+    database::webhook_queries::WebhookQueries,
+    models::webhook::{sign, DueDelivery, WebhookQueue},
+};
+
+use super::DbPool;
+
+const TICK: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH: i64 = 20;
+
+/// Sends the queued webhook deliveries, woken up as soon as new ones are queued
+///
+/// The queue lives in the database, so deliveries that are pending when the
+/// server stops are sent after it starts again
+pub async fn run(pool: DbPool, queue: Arc<WebhookQueue>) {
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .expect("webhook http client");
+    loop {
+        let _ = time::timeout(TICK, queue.woken()).await;
+        let Some(conn) = pool.get().await else {
+            continue;
+        };
+        loop {
+            let due = match conn
+                .run(|conn| conn.transaction(|conn| WebhookQueries::claim_due(conn, BATCH)))
+                .await
+            {
+                Ok(due) => due,
+                Err(e) => {
+                    eprintln!("Failed to claim webhook deliveries: {}", e);
+                    break;
+                }
+            };
+            if due.is_empty() {
+                break;
+            }
+            for due in due {
+                let outcome = deliver(&client, &due).await;
+                let result = conn
+                    .run(move |conn| match outcome {
+                        Ok(status_code) => {
+                            WebhookQueries::mark_delivered(conn, due.delivery.id, status_code)
+                        }
+                        Err((status_code, error)) => {
+                            WebhookQueries::mark_failed(conn, &due.delivery, status_code, &error)
+                        }
+                    })
+                    .await;
+                if let Err(e) = result {
+                    eprintln!("Failed to record webhook delivery: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Posts the signed payload, any 2xx answer counts as delivered
+async fn deliver(
+    client: &reqwest::Client,
+    due: &DueDelivery,
+) -> Result<i32, (Option<i32>, String)> {
+    let body = serde_json::to_vec(&due.delivery.payload).map_err(|e| (None, e.to_string()))?;
+    let response = client
+        .post(&due.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", &due.delivery.event)
+        .header("X-Webhook-Delivery", due.delivery.id.to_string())
+        .header("X-Webhook-Signature", sign(&due.secret, &body))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    let status_code = i32::from(status.as_u16());
+    match status.is_success() {
+        true => Ok(status_code),
+        false => Err((Some(status_code), format!("Receiver answered {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{Duration, Utc};
+    use rocket::tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::webhook::{backoff, DeliveryStatus, WebhookDelivery, MAX_ATTEMPTS};
+
+    /// A request as the local receiver saw it, header names are lowercase
+    struct Received {
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// Listens on a free local port, answers one request with `status` and returns it
+    async fn receiver(status: u16) -> (String, JoinHandle<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = rocket::tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let head_end = loop {
+                let mut chunk = [0; 1024];
+                let read = stream.read(&mut chunk).await.unwrap();
+                assert!(read > 0, "connection closed before the headers ended");
+                buf.extend_from_slice(&chunk[..read]);
+                if let Some(at) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break at;
+                }
+            };
+            let headers = String::from_utf8_lossy(&buf[..head_end])
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                .collect::<HashMap<_, _>>();
+            let length = headers["content-length"].parse::<usize>().unwrap();
+            let mut body = buf[head_end + 4..].to_vec();
+            while body.len() < length {
+                let mut chunk = [0; 1024];
+                let read = stream.read(&mut chunk).await.unwrap();
+                body.extend_from_slice(&chunk[..read]);
+            }
+            let answer = format!(
+                "HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream.write_all(answer.as_bytes()).await.unwrap();
+            Received { headers, body }
+        });
+        (url, handle)
+    }
+
+    fn due(url: String, delivery: WebhookDelivery) -> DueDelivery {
+        DueDelivery {
+            delivery,
+            url,
+            secret: "s3cret".to_string(),
+        }
+    }
+
+    fn delivery() -> WebhookDelivery {
+        WebhookDelivery::new(
+            Uuid::new_v4(),
+            "create_card",
+            json!({ "event": "create_card", "data": { "name": "Card" } }),
+            Utc::now().naive_utc(),
+        )
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().timeout(TIMEOUT).build().unwrap()
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[rocket::async_test]
+    async fn delivers_signed_payload() {
+        let (url, received) = receiver(200).await;
+        let due = due(url, delivery());
+
+        assert_eq!(deliver(&client(), &due).await, Ok(200));
+
+        let received = received.await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&received.body).unwrap(),
+            due.delivery.payload
+        );
+        assert_eq!(
+            received.headers["x-webhook-signature"],
+            sign("s3cret", &received.body)
+        );
+        assert_ne!(
+            received.headers["x-webhook-signature"],
+            sign("other", &received.body)
+        );
+        assert_eq!(received.headers["x-webhook-event"], "create_card");
+        assert_eq!(
+            received.headers["x-webhook-delivery"],
+            due.delivery.id.to_string()
+        );
+        assert_eq!(received.headers["content-type"], "application/json");
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_an_hour() {
+        let schedule = (1..=MAX_ATTEMPTS).map(backoff).collect::<Vec<_>>();
+        let expected = [20, 40, 80, 160, 320, 640, 1280, 2560]
+            .into_iter()
+            .map(Duration::seconds)
+            .collect::<Vec<_>>();
+        assert_eq!(schedule, expected);
+        assert_eq!(backoff(9), Duration::hours(1));
+        assert_eq!(backoff(i32::MAX), Duration::hours(1));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        assert_eq!(
+            DeliveryStatus::after_failure(MAX_ATTEMPTS - 1),
+            DeliveryStatus::Pending
+        );
+        assert_eq!(
+            DeliveryStatus::after_failure(MAX_ATTEMPTS),
+            DeliveryStatus::Failed
+        );
+    }
+
+    #[rocket::async_test]
+    async fn failing_receiver_is_retried() {
+        let (url, received) = receiver(500).await;
+        let due = due(url, delivery());
+
+        let (status_code, error) = deliver(&client(), &due).await.unwrap_err();
+        assert_eq!(status_code, Some(500));
+        assert!(error.contains("500"));
+        received.await.unwrap();
+        assert_eq!(
+            DeliveryStatus::after_failure(due.delivery.attempts + 1),
+            DeliveryStatus::Pending
+        );
+    }
+
+    #[rocket::async_test]
+    async fn unreachable_receiver_has_no_status_code() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let (status_code, _) = deliver(&client(), &due(url, delivery())).await.unwrap_err();
+        assert_eq!(status_code, None);
+    }
+
+    #[rocket::async_test]
+    async fn redelivers_same_payload_as_new_delivery() {
+        let mut failed = delivery();
+        failed.status = DeliveryStatus::Failed.as_str().to_string();
+        failed.attempts = MAX_ATTEMPTS;
+        failed.last_status_code = Some(500);
+        let again = failed.redelivery(Utc::now().naive_utc());
+
+        assert_ne!(again.id, failed.id);
+        assert_eq!(again.webhook_id, failed.webhook_id);
+        assert_eq!(again.event, failed.event);
+        assert_eq!(again.status, DeliveryStatus::Pending.as_str());
+        assert_eq!(again.attempts, 0);
+        assert_eq!(again.last_status_code, None);
+
+        let (first_url, first) = receiver(500).await;
+        let (second_url, second) = receiver(200).await;
+        let _ = deliver(&client(), &due(first_url, failed.clone())).await;
+        assert_eq!(
+            deliver(&client(), &due(second_url, again.clone())).await,
+            Ok(200)
+        );
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+        assert_eq!(first.body, second.body);
+        assert_eq!(
+            first.headers["x-webhook-signature"],
+            second.headers["x-webhook-signature"]
+        );
+        assert_eq!(second.headers["x-webhook-delivery"], again.id.to_string());
+        assert_ne!(
+            first.headers["x-webhook-delivery"],
+            second.headers["x-webhook-delivery"]
+        );
+    }
+}
@@ -1,5 +1,22 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        #[max_length = 16]
+        prefix -> Varchar,
+        scopes -> Jsonb,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     board_column (id) {
         id -> Uuid,
@@ -7,6 +24,45 @@ diesel::table! {
         name -> Nullable<Varchar>,
         position -> Int4,
         board_id -> Uuid,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    board_custom_fields (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 32]
+        kind -> Varchar,
+        options -> Jsonb,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    board_lane (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        name -> Nullable<Varchar>,
+        position -> Int4,
+        board_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    board_rules (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        trigger -> Jsonb,
+        conditions -> Jsonb,
+        actions -> Jsonb,
+        enabled -> Bool,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamp,
     }
 }
 
@@ -14,6 +70,23 @@ diesel::table! {
     board_users_relation (board_id, user_id) {
         board_id -> Uuid,
         user_id -> Uuid,
+        direct -> Bool,
+        via_workspace -> Bool,
+    }
+}
+
+diesel::table! {
+    board_operations (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        #[max_length = 32]
+        kind -> Varchar,
+        target_id -> Uuid,
+        snapshot -> Nullable<Jsonb>,
+        result_version -> Nullable<Int4>,
+        created_at -> Timestamp,
+        undone_at -> Nullable<Timestamp>,
     }
 }
 
@@ -23,6 +96,18 @@ diesel::table! {
         creator_id -> Uuid,
         #[max_length = 255]
         name -> Varchar,
+        workspace_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    calendar_feeds (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        board_id -> Nullable<Uuid>,
+        #[max_length = 64]
+        token -> Varchar,
+        created_at -> Timestamp,
     }
 }
 
@@ -33,6 +118,60 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    card_field_values (card_id, field_id) {
+        card_id -> Uuid,
+        field_id -> Uuid,
+        value -> Jsonb,
+    }
+}
+
+diesel::table! {
+    card_movements (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        card_id -> Uuid,
+        from_column_id -> Nullable<Uuid>,
+        to_column_id -> Nullable<Uuid>,
+        moved_by -> Nullable<Uuid>,
+        moved_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    card_recurrences (id) {
+        id -> Uuid,
+        card_id -> Uuid,
+        column_id -> Uuid,
+        #[max_length = 255]
+        rule -> Varchar,
+        next_run_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    card_revisions (id) {
+        id -> Uuid,
+        card_id -> Uuid,
+        revision -> Int4,
+        #[max_length = 255]
+        name -> Varchar,
+        description -> Nullable<Text>,
+        author_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    chat_message_edits (id) {
+        id -> Uuid,
+        message_id -> Uuid,
+        content -> Text,
+        edited_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     chat_messages (id) {
         id -> Uuid,
@@ -56,14 +195,43 @@ diesel::table! {
         name -> Varchar,
         #[max_length = 255]
         cover_attachment -> Nullable<Varchar>,
+        parent_id -> Nullable<Uuid>,
+        completed_at -> Nullable<Timestamp>,
+        assignee_id -> Nullable<Uuid>,
+        lane_id -> Nullable<Uuid>,
+        sprint_id -> Nullable<Uuid>,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    conversation_members (conversation_id, user_id) {
+        conversation_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 16]
+        role -> Varchar,
+        joined_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    conversation_reads (conversation_id, user_id) {
+        conversation_id -> Uuid,
+        user_id -> Uuid,
+        message_id -> Uuid,
+        message_created_at -> Timestamp,
+        read_at -> Timestamp,
     }
 }
 
 diesel::table! {
     conversations (id) {
         id -> Uuid,
-        member_one -> Uuid,
-        member_two -> Uuid,
+        member_one -> Nullable<Uuid>,
+        member_two -> Nullable<Uuid>,
+        board_id -> Nullable<Uuid>,
+        #[max_length = 255]
+        name -> Nullable<Varchar>,
     }
 }
 
@@ -85,6 +253,76 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    mentions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        author_id -> Uuid,
+        card_id -> Nullable<Uuid>,
+        message_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        actor_id -> Nullable<Uuid>,
+        #[max_length = 64]
+        kind -> Varchar,
+        payload -> Jsonb,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    rule_executions (id) {
+        id -> Uuid,
+        rule_id -> Uuid,
+        board_id -> Uuid,
+        card_id -> Uuid,
+        #[max_length = 32]
+        event -> Varchar,
+        #[max_length = 16]
+        status -> Varchar,
+        context -> Nullable<Jsonb>,
+        detail -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sprints (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        goal -> Nullable<Text>,
+        starts_on -> Date,
+        ends_on -> Date,
+        #[max_length = 16]
+        state -> Varchar,
+        carried_over -> Int4,
+        created_at -> Timestamp,
+        completed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    time_entries (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        card_id -> Uuid,
+        user_id -> Uuid,
+        started_at -> Timestamp,
+        ended_at -> Nullable<Timestamp>,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
@@ -103,26 +341,138 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        #[max_length = 64]
+        event -> Varchar,
+        payload -> Jsonb,
+        #[max_length = 16]
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_status_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        board_id -> Uuid,
+        #[max_length = 2048]
+        url -> Varchar,
+        #[max_length = 255]
+        secret -> Varchar,
+        events -> Jsonb,
+        active -> Bool,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    workspace_members (workspace_id, user_id) {
+        workspace_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 16]
+        role -> Varchar,
+        joined_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    workspaces (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        owner_id -> Uuid,
+        personal -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(api_tokens -> users (user_id));
 diesel::joinable!(board_column -> boards (board_id));
+diesel::joinable!(board_custom_fields -> boards (board_id));
+diesel::joinable!(board_lane -> boards (board_id));
+diesel::joinable!(board_operations -> boards (board_id));
+diesel::joinable!(board_operations -> users (user_id));
+diesel::joinable!(board_rules -> boards (board_id));
+diesel::joinable!(board_rules -> users (created_by));
 diesel::joinable!(board_users_relation -> boards (board_id));
 diesel::joinable!(board_users_relation -> users (user_id));
 diesel::joinable!(boards -> users (creator_id));
+diesel::joinable!(boards -> workspaces (workspace_id));
+diesel::joinable!(calendar_feeds -> boards (board_id));
+diesel::joinable!(calendar_feeds -> users (user_id));
 diesel::joinable!(card_attachments -> files (file_id));
+diesel::joinable!(card_field_values -> board_custom_fields (field_id));
+diesel::joinable!(card_movements -> boards (board_id));
+diesel::joinable!(card_recurrences -> board_column (column_id));
+diesel::joinable!(card_revisions -> users (author_id));
+diesel::joinable!(chat_message_edits -> chat_messages (message_id));
 diesel::joinable!(chat_messages -> conversations (conversation_id));
 diesel::joinable!(chat_messages -> files (file_id));
 diesel::joinable!(chat_messages -> users (sender_id));
 diesel::joinable!(column_card -> board_column (column_id));
+diesel::joinable!(conversations -> boards (board_id));
+diesel::joinable!(conversation_members -> conversations (conversation_id));
+diesel::joinable!(conversation_members -> users (user_id));
+diesel::joinable!(conversation_reads -> chat_messages (message_id));
+diesel::joinable!(conversation_reads -> conversations (conversation_id));
+diesel::joinable!(conversation_reads -> users (user_id));
+diesel::joinable!(column_card -> board_lane (lane_id));
+diesel::joinable!(column_card -> sprints (sprint_id));
+diesel::joinable!(column_card -> users (assignee_id));
 diesel::joinable!(files -> users (user_id));
+diesel::joinable!(rule_executions -> board_rules (rule_id));
+diesel::joinable!(rule_executions -> boards (board_id));
+diesel::joinable!(sprints -> boards (board_id));
+diesel::joinable!(time_entries -> boards (board_id));
+diesel::joinable!(time_entries -> users (user_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhooks -> boards (board_id));
+diesel::joinable!(webhooks -> users (created_by));
+diesel::joinable!(workspace_members -> users (user_id));
+diesel::joinable!(workspace_members -> workspaces (workspace_id));
+diesel::joinable!(workspaces -> users (owner_id));
+diesel::joinable!(mentions -> chat_messages (message_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
     board_column,
+    board_custom_fields,
+    board_lane,
+    board_operations,
+    board_rules,
     board_users_relation,
     boards,
+    calendar_feeds,
     card_attachments,
+    card_field_values,
+    card_movements,
+    card_recurrences,
+    card_revisions,
+    chat_message_edits,
     chat_messages,
     column_card,
+    conversation_members,
+    conversation_reads,
     conversations,
     files,
     friends,
+    mentions,
+    notifications,
+    rule_executions,
+    sprints,
+    time_entries,
     users,
+    webhook_deliveries,
+    webhooks,
+    workspace_members,
+    workspaces,
 );